@@ -0,0 +1,140 @@
+//! Expansion of clinical abbreviations
+//!
+//! Embeddings know "hipertensión arterial" far better than "HTA". Matching
+//! reuses DictionaryMatcher without case or accent folding, so only whole,
+//! exactly written abbreviations are replaced: "HTA" but not "hta" or the
+//! "HTA" inside "HTAs".
+
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+use pyo3::prelude::*;
+
+use crate::dictionary::DictionaryMatcher;
+
+/// Abbreviations expanded when the caller gives no table
+const DEFAULT_ABBREVIATIONS: &[(&str, &str)] = &[
+    ("HTA", "hipertensión arterial"),
+    ("DM", "diabetes mellitus"),
+    ("DM1", "diabetes mellitus tipo 1"),
+    ("DM2", "diabetes mellitus tipo 2"),
+    ("DBT", "diabetes"),
+    ("DBT2", "diabetes tipo 2"),
+    ("IAM", "infarto agudo de miocardio"),
+    ("ACV", "accidente cerebrovascular"),
+    ("EPOC", "enfermedad pulmonar obstructiva crónica"),
+    ("IC", "insuficiencia cardíaca"),
+    ("IRC", "insuficiencia renal crónica"),
+    ("ERC", "enfermedad renal crónica"),
+    ("FA", "fibrilación auricular"),
+    ("TEP", "tromboembolismo pulmonar"),
+    ("TVP", "trombosis venosa profunda"),
+    ("ITU", "infección del tracto urinario"),
+    ("NAC", "neumonía adquirida en la comunidad"),
+    ("HDA", "hemorragia digestiva alta"),
+    ("ECG", "electrocardiograma"),
+    ("TAC", "tomografía axial computarizada"),
+    ("RMN", "resonancia magnética nuclear"),
+    ("VO", "vía oral"),
+    ("EV", "endovenoso"),
+    ("AINE", "antiinflamatorio no esteroideo"),
+    ("IBP", "inhibidor de la bomba de protones"),
+];
+
+static DEFAULT_EXPANDER: Lazy<Expander> = Lazy::new(|| {
+    let table = DEFAULT_ABBREVIATIONS.iter().map(|&(short, long)| (short.to_string(), long.to_string())).collect();
+    Expander::new(table).expect("Invalid default abbreviation table - this is a bug")
+});
+
+/// Replace abbreviations with their expansions
+///
+/// Only standalone tokens written exactly as in the table are replaced;
+/// where entries overlap ("DM" and "DM2"), the longest wins.
+///
+/// Args:
+///     text: The input text
+///     table: Dict of abbreviation -> expansion (default: common Spanish
+///            clinical abbreviations: HTA, DM2, EPOC, IAM, ACV, ...)
+///
+/// Returns:
+///     The text with every abbreviation expanded
+///
+/// Raises:
+///     ValueError: If the table cannot be compiled
+#[pyfunction]
+#[pyo3(signature = (text, table=None))]
+pub fn expand_abbreviations(py: Python<'_>, text: &str, table: Option<HashMap<String, String>>) -> PyResult<String> {
+    let custom = table.map(Expander::new).transpose()?;
+    let expander = custom.as_ref().unwrap_or(&DEFAULT_EXPANDER);
+    Ok(crate::allow_threads_if_large(py, text.len(), || expander.expand(text)))
+}
+
+pub(crate) struct Expander {
+    matcher: DictionaryMatcher,
+    table: HashMap<String, String>,
+}
+
+impl Expander {
+    pub(crate) fn new(table: HashMap<String, String>) -> PyResult<Self> {
+        let matcher = DictionaryMatcher::new(table.keys().cloned().collect(), false, false, true, true)?;
+        Ok(Self { matcher, table })
+    }
+
+    pub(crate) fn expand(&self, text: &str) -> String {
+        let mentions = self.matcher.find_internal(text);
+        if mentions.is_empty() {
+            return text.to_string();
+        }
+        // Character offsets of the mentions, as byte offsets
+        let mut byte_at: Vec<usize> = text.char_indices().map(|(index, _)| index).collect();
+        byte_at.push(text.len());
+
+        let mut expanded = String::with_capacity(text.len() * 2);
+        let mut last = 0;
+        for (short, start, end) in mentions {
+            let (start, end) = (byte_at[start], byte_at[end]);
+            expanded.push_str(&text[last..start]);
+            expanded.push_str(self.table.get(&short).map_or(&text[start..end], String::as_str));
+            last = end;
+        }
+        expanded.push_str(&text[last..]);
+        expanded
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default_expand(text: &str) -> String {
+        DEFAULT_EXPANDER.expand(text)
+    }
+
+    #[test]
+    fn test_whole_tokens_only() {
+        assert_eq!(
+            default_expand("Antecedentes: HTA, DM2 y EPOC. Niega IAM."),
+            "Antecedentes: hipertensión arterial, diabetes mellitus tipo 2 y enfermedad pulmonar obstructiva crónica. \
+             Niega infarto agudo de miocardio."
+        );
+        // Substrings, other casings and unknown tokens stay
+        let untouched = "HTAs, hta, FACTOR, DMX, ácido VOlátil";
+        assert_eq!(default_expand(untouched), untouched);
+        assert_eq!(default_expand("Ñandú DM."), "Ñandú diabetes mellitus.");
+    }
+
+    #[test]
+    fn test_caller_table() {
+        crate::with_py(|py| {
+            let table: HashMap<String, String> =
+                [("SOS", "si es necesario"), ("c/8h", "cada 8 horas")].iter().map(|&(k, v)| (k.into(), v.into())).collect();
+            assert_eq!(
+                expand_abbreviations(py, "Paracetamol c/8h SOS. HTA.", Some(table)).unwrap(),
+                "Paracetamol cada 8 horas si es necesario. HTA."
+            );
+            assert_eq!(expand_abbreviations(py, "Paciente con HTA", None).unwrap(), "Paciente con hipertensión arterial");
+            assert_eq!(expand_abbreviations(py, "sin cambios", Some(HashMap::new())).unwrap(), "sin cambios");
+        });
+    }
+}
@@ -0,0 +1,209 @@
+//! BM25 lexical retrieval over a chunk collection
+//!
+//! Fallback for when the vector database is unavailable. Documents are
+//! tokenized like tokenize (lowercased words) into an inverted index of
+//! term -> (document, term frequency) postings, so a search only touches
+//! documents sharing a term with the query. Scoring uses the Lucene IDF,
+//! ln(1 + (N - df + 0.5) / (df + 0.5)), which never goes negative.
+//! Scores depend only on the documents and their order: query terms are
+//! summed in query order and ties rank by document index.
+
+use std::collections::HashMap;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+
+/// A BM25 index over a list of documents
+///
+/// Args:
+///     documents: Texts to index; their positions are the indices that
+///                search returns
+///     k1: Term-frequency saturation (default: 1.5)
+///     b: Length normalization, 0.0-1.0 (default: 0.75)
+///
+/// Raises:
+///     ValueError: If k1 is negative or b is outside 0.0-1.0
+#[pyclass(module = "ferro_engine")]
+pub struct Bm25Index {
+    k1: f64,
+    b: f64,
+    /// term -> (document index, term frequency), by ascending index
+    postings: HashMap<String, Vec<(usize, u32)>>,
+    /// Token count of each document
+    lengths: Vec<usize>,
+    total_length: usize,
+}
+
+#[pymethods]
+impl Bm25Index {
+    #[new]
+    #[pyo3(signature = (documents, k1=1.5, b=0.75))]
+    fn py_new(py: Python<'_>, documents: Vec<String>, k1: f64, b: f64) -> PyResult<Self> {
+        let mut index = Self::new(k1, b)?;
+        py.allow_threads(|| index.add(&documents));
+        Ok(index)
+    }
+
+    /// Index more documents
+    ///
+    /// New documents are numbered after the existing ones.
+    ///
+    /// Args:
+    ///     documents: Texts to add
+    fn add_documents(&mut self, py: Python<'_>, documents: Vec<String>) {
+        py.allow_threads(|| self.add(&documents));
+    }
+
+    /// Best-matching documents for a query
+    ///
+    /// Args:
+    ///     query: Query text, tokenized like the documents
+    ///     top_k: Maximum number of results (default: 10)
+    ///
+    /// Returns:
+    ///     List of (doc_index, score), highest score first; documents
+    ///     sharing no term with the query are not returned
+    ///
+    /// Raises:
+    ///     ValueError: If top_k is 0
+    #[pyo3(signature = (query, top_k=10))]
+    fn search(&self, query: &str, top_k: usize) -> PyResult<Vec<(usize, f64)>> {
+        if top_k == 0 {
+            return Err(PyValueError::new_err("top_k must be greater than 0"));
+        }
+        Ok(self.search_internal(query, top_k))
+    }
+
+    fn __len__(&self) -> usize {
+        self.lengths.len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Bm25Index(documents={}, terms={}, k1={}, b={})", self.lengths.len(), self.postings.len(), self.k1, self.b)
+    }
+}
+
+impl Bm25Index {
+    pub(crate) fn new(k1: f64, b: f64) -> PyResult<Self> {
+        if k1.is_nan() || k1 < 0.0 {
+            return Err(PyValueError::new_err("k1 must be non-negative"));
+        }
+        if !(0.0..=1.0).contains(&b) {
+            return Err(PyValueError::new_err("b must be between 0.0 and 1.0"));
+        }
+        Ok(Self { k1, b, postings: HashMap::new(), lengths: Vec::new(), total_length: 0 })
+    }
+
+    /// Tokenize in parallel, then append postings in document order
+    pub(crate) fn add(&mut self, documents: &[String]) {
+        let counted: Vec<(usize, HashMap<String, u32>)> = documents
+            .par_iter()
+            .map(|document| {
+                let tokens = crate::word_tokens(document, true, false);
+                let mut counts: HashMap<String, u32> = HashMap::new();
+                for token in &tokens {
+                    *counts.entry(token.clone()).or_default() += 1;
+                }
+                (tokens.len(), counts)
+            })
+            .collect();
+        for (length, counts) in counted {
+            let index = self.lengths.len();
+            for (term, frequency) in counts {
+                self.postings.entry(term).or_default().push((index, frequency));
+            }
+            self.lengths.push(length);
+            self.total_length += length;
+        }
+    }
+
+    pub(crate) fn search_internal(&self, query: &str, top_k: usize) -> Vec<(usize, f64)> {
+        let documents = self.lengths.len() as f64;
+        let average_length = if self.lengths.is_empty() { 0.0 } else { self.total_length as f64 / documents };
+        let mut terms = crate::word_tokens(query, true, false);
+        // Repeated query terms count once; first occurrence keeps the order
+        let mut seen = std::collections::HashSet::new();
+        terms.retain(|term| seen.insert(term.clone()));
+
+        let mut scores: HashMap<usize, f64> = HashMap::new();
+        for term in &terms {
+            let Some(postings) = self.postings.get(term) else { continue };
+            let df = postings.len() as f64;
+            let idf = (1.0 + (documents - df + 0.5) / (df + 0.5)).ln();
+            for &(index, frequency) in postings {
+                let tf = f64::from(frequency);
+                let length_ratio = if average_length > 0.0 { self.lengths[index] as f64 / average_length } else { 0.0 };
+                let norm = self.k1 * (1.0 - self.b + self.b * length_ratio);
+                *scores.entry(index).or_default() += idf * tf * (self.k1 + 1.0) / (tf + norm);
+            }
+        }
+
+        let mut ranked: Vec<(usize, f64)> = scores.into_iter().collect();
+        ranked.sort_unstable_by(|(a_index, a), (b_index, b)| b.total_cmp(a).then(a_index.cmp(b_index)));
+        ranked.truncate(top_k);
+        ranked
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(documents: &[&str]) -> Bm25Index {
+        let mut index = Bm25Index::new(1.5, 0.75).unwrap();
+        index.add(&documents.iter().map(|d| d.to_string()).collect::<Vec<_>>());
+        index
+    }
+
+    const CHUNKS: &[&str] = &[
+        "Paciente con neumonía adquirida en la comunidad, inicia amoxicilina.",
+        "Control de diabetes tipo 2 con metformina; glucemia en rango.",
+        "Neumonía bilateral con insuficiencia respiratoria. Neumonía grave, pasa a UTI.",
+        "Fractura de cadera derecha, se programa cirugía.",
+    ];
+
+    #[test]
+    fn test_ranking_and_scores() {
+        let index = index(CHUNKS);
+        let results = index.search_internal("neumonía grave", 10);
+        let order: Vec<usize> = results.iter().map(|&(i, _)| i).collect();
+        assert_eq!(order, vec![2, 0]);
+        assert!(results[0].1 > results[1].1 && results[1].1 > 0.0);
+        assert!(index.search_internal("cardiología", 10).is_empty());
+        assert_eq!(index.search_internal("neumonía diabetes cadera", 2).len(), 2);
+    }
+
+    #[test]
+    fn test_deterministic_and_incremental() {
+        let whole = index(CHUNKS);
+        let mut grown = index(&CHUNKS[..2]);
+        grown.add(&CHUNKS[2..].iter().map(|d| d.to_string()).collect::<Vec<_>>());
+        for query in ["neumonía", "control de glucemia", "fractura cirugía neumonía"] {
+            let expected = whole.search_internal(query, 10);
+            assert_eq!(grown.search_internal(query, 10), expected);
+            assert_eq!(index(CHUNKS).search_internal(query, 10), expected);
+        }
+        // Equal scores rank by document index
+        let twins = index(&["fiebre alta", "tos seca", "fiebre alta"]);
+        let order: Vec<usize> = twins.search_internal("fiebre", 10).iter().map(|&(i, _)| i).collect();
+        assert_eq!(order, vec![0, 2]);
+    }
+
+    #[test]
+    fn test_pyclass_interface() {
+        crate::with_py(|py| {
+            let documents: Vec<String> = CHUNKS.iter().map(|d| d.to_string()).collect();
+            let mut index = Bm25Index::py_new(py, documents, 1.2, 0.75).unwrap();
+            assert_eq!(index.__len__(), 4);
+            index.add_documents(py, vec!["Neumonía por COVID-19".to_string()]);
+            assert_eq!(index.__len__(), 5);
+            assert!(index.search("covid", 10).unwrap().iter().any(|&(i, _)| i == 4));
+            assert!(index.search("covid", 0).is_err());
+            assert!(Bm25Index::py_new(py, vec![], -1.0, 0.75).is_err());
+            assert!(Bm25Index::py_new(py, vec![], 1.2, 1.5).is_err());
+            assert!(Bm25Index::py_new(py, vec![], 1.2, 0.75).unwrap().search("x", 3).unwrap().is_empty());
+        });
+    }
+}
@@ -0,0 +1,273 @@
+//! Exact token counts with OpenAI's BPE encodings
+//!
+//! count_tokens is a cheap estimate; the functions here run the real
+//! tiktoken encodings so counts match what the OpenAI API bills. Encoding
+//! tables are built once per process on first use.
+//!
+//! A vocabulary can also be loaded from a `.tiktoken` file (one base64
+//! token and its rank per line, as published for cl100k_base) with
+//! load_tokenizer; count_tokens_exact then counts with that vocabulary.
+
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use once_cell::sync::Lazy;
+use pyo3::exceptions::{PyOSError, PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use tiktoken_rs::tokenizer::{get_tokenizer, Tokenizer};
+use tiktoken_rs::{CoreBPE, Rank};
+
+/// Pre-tokenization pattern of cl100k_base, used unless load_tokenizer gets another
+const CL100K_PATTERN: &str = r"(?i:'s|'t|'re|'ve|'m|'ll|'d)|[^\r\n\p{L}\p{N}]?\p{L}+|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n]*|\s*[\r\n]+|\s+(?!\S)|\s+";
+
+/// Vocabulary installed by load_tokenizer
+static LOADED: Lazy<RwLock<Option<Arc<CoreBPE>>>> = Lazy::new(|| RwLock::new(None));
+
+/// Exact BPE token count for OpenAI models
+///
+/// Special-token markers such as "<|endoftext|>" are counted as ordinary
+/// text, as the API does for message content.
+///
+/// Args:
+///     text: The input text
+///     model: Encoding name ("cl100k_base", "o200k_base", "p50k_base",
+///            "r50k_base") or model name ("gpt-4o", "gpt-4",
+///            "gpt-3.5-turbo", ...) (default: "cl100k_base")
+///
+/// Returns:
+///     Number of tokens
+///
+/// Raises:
+///     ValueError: If the model or encoding name is unknown
+#[pyfunction]
+#[pyo3(signature = (text, model="cl100k_base"))]
+pub fn count_tokens_bpe(py: Python<'_>, text: &str, model: &str) -> PyResult<usize> {
+    let bpe = encoding_for(model)?;
+    Ok(crate::allow_threads_if_large(py, text.len(), || count_with(bpe, text)))
+}
+
+/// Resolve an encoding or model name to its shared encoder
+pub(crate) fn encoding_for(model: &str) -> PyResult<&'static CoreBPE> {
+    let tokenizer = match model {
+        "o200k_base" => Tokenizer::O200kBase,
+        "cl100k_base" => Tokenizer::Cl100kBase,
+        "p50k_base" => Tokenizer::P50kBase,
+        "p50k_edit" => Tokenizer::P50kEdit,
+        "r50k_base" | "gpt2" => Tokenizer::R50kBase,
+        _ => get_tokenizer(model).ok_or_else(|| PyValueError::new_err(format!("Unknown model or encoding: {:?}", model)))?,
+    };
+    Ok(match tokenizer {
+        Tokenizer::O200kBase => tiktoken_rs::o200k_base_singleton(),
+        Tokenizer::Cl100kBase => tiktoken_rs::cl100k_base_singleton(),
+        Tokenizer::P50kBase => tiktoken_rs::p50k_base_singleton(),
+        Tokenizer::P50kEdit => tiktoken_rs::p50k_edit_singleton(),
+        Tokenizer::R50kBase | Tokenizer::Gpt2 => tiktoken_rs::r50k_base_singleton(),
+    })
+}
+
+/// Load a BPE vocabulary for count_tokens_exact
+///
+/// Replaces any previously loaded vocabulary for the whole process.
+///
+/// Args:
+///     path: Path to a `.tiktoken` file: one "<base64 token> <rank>" per line
+///     pattern: Pre-tokenization regex (default: the cl100k_base pattern)
+///
+/// Returns:
+///     Number of tokens in the vocabulary
+///
+/// Raises:
+///     OSError: If the file cannot be read
+///     ValueError: If a line is malformed, a token or rank is repeated, one
+///                 of the 256 single-byte tokens is missing, or the pattern
+///                 is invalid
+#[pyfunction]
+#[pyo3(signature = (path, pattern=None))]
+pub fn load_tokenizer(py: Python<'_>, path: &str, pattern: Option<&str>) -> PyResult<usize> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|err| PyOSError::new_err(format!("Cannot read tokenizer file {:?}: {}", path, err)))?;
+    let (bpe, size) = py.allow_threads(|| build_encoding(&contents, pattern.unwrap_or(CL100K_PATTERN)))?;
+    *LOADED.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(Arc::new(bpe));
+    Ok(size)
+}
+
+/// Exact token count with the vocabulary from load_tokenizer
+///
+/// Args:
+///     text: The input text
+///
+/// Returns:
+///     Number of tokens
+///
+/// Raises:
+///     RuntimeError: If no tokenizer has been loaded
+#[pyfunction]
+pub fn count_tokens_exact(py: Python<'_>, text: &str) -> PyResult<usize> {
+    let bpe = loaded()?;
+    Ok(crate::allow_threads_if_large(py, text.len(), || count_with(&bpe, text)))
+}
+
+/// Batch count_tokens_exact, on all cores with the GIL released
+///
+/// Args:
+///     texts: List of texts
+///
+/// Returns:
+///     List of token counts, in input order
+///
+/// Raises:
+///     RuntimeError: If no tokenizer has been loaded
+#[pyfunction]
+pub fn count_tokens_exact_batch(py: Python<'_>, texts: Vec<String>) -> PyResult<Vec<usize>> {
+    let bpe = loaded()?;
+    Ok(py.allow_threads(|| texts.par_iter().map(|text| count_with(&bpe, text)).collect()))
+}
+
+/// The vocabulary installed by load_tokenizer, or RuntimeError
+pub(crate) fn loaded() -> PyResult<Arc<CoreBPE>> {
+    loaded_encoding().ok_or_else(|| PyRuntimeError::new_err("No tokenizer loaded; call load_tokenizer(path) first"))
+}
+
+/// The vocabulary installed by load_tokenizer, if any
+pub(crate) fn loaded_encoding() -> Option<Arc<CoreBPE>> {
+    LOADED.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+}
+
+/// Parse a `.tiktoken` vocabulary and build its encoder
+pub(crate) fn build_encoding(contents: &str, pattern: &str) -> PyResult<(CoreBPE, usize)> {
+    let mut tokens: Vec<(Vec<u8>, Rank)> = Vec::new();
+    let mut seen_tokens = HashSet::new();
+    let mut seen_ranks = HashSet::new();
+    for (line_no, line) in contents.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        let invalid = |reason: &str| PyValueError::new_err(format!("Line {}: {}: {:?}", line_no + 1, reason, line));
+        let mut parts = line.split_whitespace();
+        let (Some(raw), Some(rank), None) = (parts.next(), parts.next(), parts.next()) else {
+            return Err(invalid("expected \"<base64 token> <rank>\""));
+        };
+        let token = STANDARD.decode(raw).map_err(|_| invalid("invalid base64"))?;
+        let rank: Rank = rank.parse().map_err(|_| invalid("invalid rank"))?;
+        // CoreBPE asserts on duplicates; reject them here instead
+        if !seen_tokens.insert(token.clone()) || !seen_ranks.insert(rank) {
+            return Err(invalid("duplicate token or rank"));
+        }
+        tokens.push((token, rank));
+    }
+    // Every piece falls back to single bytes; CoreBPE panics on a missing one
+    if let Some(byte) = (0..=255u8).find(|&byte| !seen_tokens.contains(&vec![byte])) {
+        return Err(PyValueError::new_err(format!("Vocabulary is missing the single-byte token {:#04x}", byte)));
+    }
+    let size = tokens.len();
+    let bpe = CoreBPE::new(tokens.into_iter().collect(), Default::default(), pattern)
+        .map_err(|err| PyValueError::new_err(format!("Invalid tokenizer: {}", err)))?;
+    Ok((bpe, size))
+}
+
+pub(crate) fn count_with(bpe: &CoreBPE, text: &str) -> usize {
+    bpe.encode_ordinary(text).len()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(text: &str, model: &str) -> usize {
+        count_with(encoding_for(model).unwrap(), text)
+    }
+
+    #[test]
+    fn test_known_counts() {
+        assert_eq!(count("", "cl100k_base"), 0);
+        assert_eq!(count("hello world", "cl100k_base"), 2);
+        assert_eq!(count("tiktoken is great!", "cl100k_base"), 6);
+        assert_eq!(count("hello world", "o200k_base"), 2);
+        assert_eq!(count("hello world", "r50k_base"), 2);
+        assert_eq!(count("<|endoftext|>", "cl100k_base"), 7);
+    }
+
+    #[test]
+    fn test_model_names_resolve_to_encodings() {
+        let text = "Paciente de 45 años con dolor torácico opresivo.";
+        assert_eq!(count(text, "gpt-4"), count(text, "cl100k_base"));
+        assert_eq!(count(text, "gpt-3.5-turbo"), count(text, "cl100k_base"));
+        assert_eq!(count(text, "gpt-4o"), count(text, "o200k_base"));
+        assert_eq!(count(text, "gpt-4o-mini"), count(text, "o200k_base"));
+    }
+
+    /// Byte-level vocabulary (every single byte) plus a few merges
+    fn toy_vocabulary(merges: &[&str]) -> String {
+        let singles = (0..=255u8).map(|b| vec![b]);
+        let merged = merges.iter().map(|m| m.as_bytes().to_vec());
+        singles
+            .chain(merged)
+            .enumerate()
+            .map(|(rank, token)| format!("{} {}\n", STANDARD.encode(token), rank))
+            .collect()
+    }
+
+    #[test]
+    fn test_loaded_vocabulary_counts() {
+        let (bpe, size) = build_encoding(&toy_vocabulary(&["do", "lo", "lor", "dolor"]), CL100K_PATTERN).unwrap();
+        assert_eq!(size, 260);
+        assert_eq!(count_with(&bpe, "dolor"), 1);
+        assert_eq!(count_with(&bpe, "dolores"), 3);
+        assert_eq!(count_with(&bpe, "sin dolor"), 5);
+        assert_eq!(count_with(&bpe, ""), 0);
+    }
+
+    #[test]
+    fn test_load_tokenizer_and_batch() {
+        let path = std::env::temp_dir().join(format!("ferro_toy_{}.tiktoken", std::process::id()));
+        std::fs::write(&path, toy_vocabulary(&["do", "lo", "lor", "dolor"])).unwrap();
+        crate::with_py(|py| {
+            assert_eq!(load_tokenizer(py, path.to_str().unwrap(), None).unwrap(), 260);
+            assert_eq!(count_tokens_exact(py, "dolor").unwrap(), 1);
+            let texts: Vec<String> = (0..500).map(|i| format!("dolor {}", i)).collect();
+            let batch = count_tokens_exact_batch(py, texts.clone()).unwrap();
+            let serial: Vec<usize> = texts.iter().map(|t| count_tokens_exact(py, t).unwrap()).collect();
+            assert_eq!(batch, serial);
+
+            let missing = load_tokenizer(py, "/nonexistent/vocab.tiktoken", None).unwrap_err();
+            assert!(missing.is_instance_of::<PyOSError>(py));
+        });
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_malformed_vocabularies_are_rejected() {
+        crate::with_py(|py| {
+            for contents in ["YQ== 0\nYg== 0\n", "YQ== 0\nYQ== 1\n", "!!! 0\n", "YQ==\n", "YQ== x\n"] {
+                let Err(err) = build_encoding(contents, CL100K_PATTERN) else {
+                    panic!("{:?} should be rejected", contents);
+                };
+                assert!(err.is_instance_of::<PyValueError>(py), "{}", err);
+            }
+            let Err(err) = build_encoding(&toy_vocabulary(&[]), "(unclosed") else { panic!("bad pattern accepted") };
+            assert!(err.is_instance_of::<PyValueError>(py));
+        });
+    }
+
+    #[test]
+    fn test_truncated_vocabulary_is_rejected() {
+        // Without byte 0x7f the encoder could not fall back on "\x7f"
+        let truncated: String =
+            toy_vocabulary(&["do"]).lines().filter(|line| !line.starts_with("fw== ")).map(|line| format!("{}\n", line)).collect();
+        let path = std::env::temp_dir().join(format!("ferro_truncated_{}.tiktoken", std::process::id()));
+        std::fs::write(&path, &truncated).unwrap();
+        crate::with_py(|py| {
+            let err = load_tokenizer(py, path.to_str().unwrap(), None).unwrap_err();
+            assert!(err.is_instance_of::<PyValueError>(py));
+            assert!(err.to_string().contains("0x7f"), "{}", err);
+        });
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_unknown_model_is_rejected() {
+        let Err(err) = encoding_for("llama-3") else { panic!("llama-3 should be unknown") };
+        crate::with_py(|py| assert!(err.is_instance_of::<PyValueError>(py)));
+    }
+}
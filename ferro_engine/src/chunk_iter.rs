@@ -0,0 +1,305 @@
+//! Lazy chunking of large texts and files
+//!
+//! chunk_text returns every chunk at once, which doubles the memory of a
+//! 300 MB OCR dump. ChunkIterator yields them one by one instead. The
+//! source is read in blocks that end at a newline; a newline always ends a
+//! sentence, so the blocks split into exactly the sentences chunk_text
+//! sees, and the same Packer turns them into the same chunks.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufReader, Read};
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::{ChunkUnit, Packer, SentenceSplitter};
+
+/// Bytes read before looking for a newline to end the block
+const BLOCK_BYTES: usize = 64 * 1024;
+
+/// A block without any newline is cut at a space once it grows this large
+const MAX_BLOCK_BYTES: usize = 16 * BLOCK_BYTES;
+
+/// Where the text comes from
+enum Source {
+    Text { text: String, position: usize },
+    File { reader: BufReader<File>, pending: Vec<u8>, eof: bool },
+}
+
+impl Source {
+    /// Byte range of the next block of an in-memory text
+    fn next_text_block(text: &str, position: usize, block_bytes: usize) -> Option<(usize, usize)> {
+        if position >= text.len() {
+            return None;
+        }
+        let rest = &text[position..];
+        let end = if rest.len() <= block_bytes {
+            rest.len()
+        } else {
+            // Past the last newline of the block, or else the first one after it
+            let mut limit = block_bytes;
+            while !rest.is_char_boundary(limit) {
+                limit += 1;
+            }
+            rest[..limit].rfind('\n').or_else(|| rest[limit..].find('\n').map(|i| limit + i)).map_or(rest.len(), |i| i + 1)
+        };
+        Some((position, position + end))
+    }
+
+    /// The next block read from a file, decoded
+    fn next_file_block(reader: &mut BufReader<File>, pending: &mut Vec<u8>, eof: &mut bool, block_bytes: usize) -> PyResult<Option<String>> {
+        let mut buffer = vec![0; block_bytes];
+        while !*eof && (pending.len() < block_bytes || !pending.contains(&b'\n')) && pending.len() < MAX_BLOCK_BYTES.max(block_bytes) {
+            let read = reader.read(&mut buffer)?;
+            if read == 0 {
+                *eof = true;
+            }
+            pending.extend_from_slice(&buffer[..read]);
+        }
+        if pending.is_empty() {
+            return Ok(None);
+        }
+        let end = if *eof {
+            pending.len()
+        } else if let Some(newline) = pending.iter().rposition(|&b| b == b'\n') {
+            newline + 1
+        } else {
+            // One huge line: cut after its last space, or at a character boundary
+            let valid = match std::str::from_utf8(pending) {
+                Ok(_) => pending.len(),
+                // An invalid sequence, not one cut short by the read
+                Err(err) if err.error_len().is_some() => return Err(not_utf8()),
+                Err(err) => err.valid_up_to(),
+            };
+            let end = pending[..valid].iter().rposition(|b| b.is_ascii_whitespace()).map_or(valid, |space| space + 1);
+            // An empty block would leave pending full and never read again
+            if end == 0 {
+                return Err(not_utf8());
+            }
+            end
+        };
+        let block: Vec<u8> = pending.drain(..end).collect();
+        String::from_utf8(block).map(Some).map_err(|_| not_utf8())
+    }
+}
+
+fn not_utf8() -> PyErr {
+    PyValueError::new_err("file is not valid UTF-8")
+}
+
+/// Iterator over the chunks of a text or file, computed as they are asked for
+///
+/// Yields the same chunks as chunk_text with default delimiters, holding
+/// only the current block and the chunk being filled.
+#[pyclass(module = "ferro_engine")]
+pub struct ChunkIterator {
+    source: Source,
+    block_bytes: usize,
+    chunk_size: usize,
+    unit: ChunkUnit,
+    splitter: SentenceSplitter,
+    packer: Packer<String>,
+    ready: VecDeque<String>,
+    done: bool,
+}
+
+#[pymethods]
+impl ChunkIterator {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&mut self) -> PyResult<Option<String>> {
+        self.next_chunk()
+    }
+}
+
+impl ChunkIterator {
+    fn new(source: Source, chunk_size: usize, overlap: usize, unit: ChunkUnit, block_bytes: usize) -> Self {
+        Self {
+            source,
+            block_bytes,
+            chunk_size,
+            unit,
+            splitter: SentenceSplitter::chunking(),
+            packer: Packer::new(chunk_size, overlap, unit),
+            ready: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    pub(crate) fn next_chunk(&mut self) -> PyResult<Option<String>> {
+        loop {
+            if let Some(chunk) = self.ready.pop_front() {
+                return Ok(Some(chunk));
+            }
+            if self.done {
+                return Ok(None);
+            }
+            let Self { source, block_bytes, chunk_size, unit, splitter, packer, ready, done } = self;
+            let mut feed = |block: &str| {
+                for sentence in splitter.split(block) {
+                    for piece in crate::split_oversized(sentence, *chunk_size, *unit) {
+                        if let Some((chunk, _)) = packer.push(piece.to_string()) {
+                            ready.push_back(chunk);
+                        }
+                    }
+                }
+            };
+            let fed = match source {
+                Source::Text { text, position } => match Source::next_text_block(text, *position, *block_bytes) {
+                    Some((start, end)) => {
+                        feed(&text[start..end]);
+                        *position = end;
+                        true
+                    }
+                    None => false,
+                },
+                Source::File { reader, pending, eof } => match Source::next_file_block(reader, pending, eof, *block_bytes)? {
+                    Some(block) => {
+                        feed(&block);
+                        true
+                    }
+                    None => false,
+                },
+            };
+            if !fed {
+                *done = true;
+                if let Some((chunk, _)) = packer.finish() {
+                    ready.push_back(chunk);
+                }
+            }
+        }
+    }
+}
+
+/// Chunk a text lazily
+///
+/// Args:
+///     text: The input text
+///     chunk_size: Maximum characters (or tokens) per chunk (default: 1000)
+///     overlap: Characters (or tokens) to overlap (default: 200)
+///     unit: "chars" or "tokens", as in chunk_text (default: "chars")
+///
+/// Returns:
+///     A ChunkIterator yielding the chunks chunk_text would return
+///
+/// Raises:
+///     ValueError: If chunk_size is 0, overlap >= chunk_size or unit is unknown
+#[pyfunction]
+#[pyo3(signature = (text, chunk_size=1000, overlap=200, unit="chars"))]
+pub fn chunk_text_iter(text: String, chunk_size: usize, overlap: usize, unit: &str) -> PyResult<ChunkIterator> {
+    let unit = ChunkUnit::parse(unit)?;
+    crate::validate_chunk_params(chunk_size, overlap)?;
+    Ok(ChunkIterator::new(Source::Text { text, position: 0 }, chunk_size, overlap, unit, BLOCK_BYTES))
+}
+
+/// Chunk a UTF-8 file lazily, without loading it whole
+///
+/// The file is read with buffered IO in blocks ending at a newline. A
+/// line longer than 1 MiB is cut at its last space first, so its
+/// sentences may split differently than with chunk_text; chunk sizes and
+/// overlaps follow the same rules.
+///
+/// Args:
+///     path: Path of the file
+///     chunk_size: Maximum characters (or tokens) per chunk (default: 1000)
+///     overlap: Characters (or tokens) to overlap (default: 200)
+///     unit: "chars" or "tokens", as in chunk_text (default: "chars")
+///
+/// Returns:
+///     A ChunkIterator over the file's chunks
+///
+/// Raises:
+///     ValueError: If chunk_size is 0, overlap >= chunk_size or unit is
+///                 unknown; while iterating, if the file is not UTF-8
+///     OSError: If the file cannot be opened or read
+#[pyfunction]
+#[pyo3(signature = (path, chunk_size=1000, overlap=200, unit="chars"))]
+pub fn chunk_file_iter(path: std::path::PathBuf, chunk_size: usize, overlap: usize, unit: &str) -> PyResult<ChunkIterator> {
+    let unit = ChunkUnit::parse(unit)?;
+    crate::validate_chunk_params(chunk_size, overlap)?;
+    let reader = BufReader::new(File::open(path)?);
+    let source = Source::File { reader, pending: Vec::new(), eof: false };
+    Ok(ChunkIterator::new(source, chunk_size, overlap, unit, BLOCK_BYTES))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collect(mut iterator: ChunkIterator) -> Vec<String> {
+        std::iter::from_fn(|| iterator.next_chunk().unwrap()).collect()
+    }
+
+    fn note(lines: usize) -> String {
+        (0..lines)
+            .map(|i| match i % 3 {
+                0 => format!("Día {}: paciente estable, afebril, TA 120/80. Dosis 0.5 mg c/8h.", i),
+                1 => "Evolución favorable; tolera dieta y deambula sin asistencia alguna por el pasillo del piso.".to_string(),
+                _ => "ñandú ".repeat(40),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_text_blocks_match_chunk_text() {
+        let text = note(200);
+        for (chunk_size, overlap, unit) in [(200, 50, ChunkUnit::Chars), (80, 0, ChunkUnit::Chars), (30, 5, ChunkUnit::Tokens)] {
+            let expected = crate::chunk_text_internal(&text, chunk_size, overlap, unit);
+            // Small blocks force many block boundaries
+            for block_bytes in [64, 1000, BLOCK_BYTES] {
+                let source = Source::Text { text: text.clone(), position: 0 };
+                assert_eq!(collect(ChunkIterator::new(source, chunk_size, overlap, unit, block_bytes)), expected);
+            }
+        }
+        assert!(collect(chunk_text_iter(String::new(), 100, 10, "chars").unwrap()).is_empty());
+        assert!(chunk_text_iter("x".into(), 10, 10, "chars").is_err());
+    }
+
+    #[test]
+    fn test_file_matches_chunk_text() {
+        let text = note(300);
+        let path = std::env::temp_dir().join(format!("ferro_chunk_iter_{}.txt", std::process::id()));
+        std::fs::write(&path, &text).unwrap();
+        let expected = crate::chunk_text_internal(&text, 150, 30, ChunkUnit::Chars);
+        for block_bytes in [100, 4096] {
+            let reader = BufReader::new(File::open(&path).unwrap());
+            let source = Source::File { reader, pending: Vec::new(), eof: false };
+            assert_eq!(collect(ChunkIterator::new(source, 150, 30, ChunkUnit::Chars, block_bytes)), expected);
+        }
+        assert_eq!(collect(chunk_file_iter(path.clone(), 150, 30, "chars").unwrap()), expected);
+        std::fs::remove_file(&path).unwrap();
+        assert!(chunk_file_iter(path, 150, 30, "chars").is_err());
+    }
+
+    #[test]
+    fn test_latin1_line_without_newline_fails() {
+        // "ñ" in Latin-1 is the lone byte 0xf1; no newline for over 1 MiB
+        let mut bytes = b"Paciente con dolor ".to_vec();
+        bytes.extend(b"ni\xf1o".repeat(MAX_BLOCK_BYTES / 2));
+        let path = std::env::temp_dir().join(format!("ferro_chunk_iter_latin1_{}.txt", std::process::id()));
+        std::fs::write(&path, &bytes).unwrap();
+        let mut iterator = chunk_file_iter(path.clone(), 150, 30, "chars").unwrap();
+        assert!(iterator.next_chunk().is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_python_iteration() {
+        crate::with_py(|py| {
+            let text = note(30);
+            let iterator = Py::new(py, chunk_text_iter(text.clone(), 120, 20, "chars").unwrap()).unwrap();
+            let chunks: Vec<String> = iterator
+                .bind(py)
+                .iter()
+                .unwrap()
+                .map(|chunk| chunk.unwrap().extract().unwrap())
+                .collect();
+            assert_eq!(chunks, crate::chunk_text_internal(&text, 120, 20, ChunkUnit::Chars));
+        });
+    }
+}
@@ -0,0 +1,163 @@
+//! User-defined entity patterns
+//!
+//! Lets each hospital extract its own formats (bed numbers, episode IDs,
+//! internal codes) without touching the built-in categories. Compiled
+//! patterns are kept in a process-wide LRU cache keyed by pattern string,
+//! so calling extract_custom in a loop with the same dict compiles once.
+
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+
+use lru::LruCache;
+use once_cell::sync::Lazy;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use regex::{Regex, RegexBuilder};
+
+/// Compiled patterns kept around between calls
+const CACHE_CAPACITY: usize = 256;
+
+/// Upper bound on a compiled pattern's program and lazy DFA (bytes)
+///
+/// The regex engine matches in linear time, so there is no catastrophic
+/// backtracking; the remaining risk is patterns like "(a{1000}){1000}"
+/// whose compiled form explodes. These are rejected at compile time.
+const PATTERN_SIZE_LIMIT: usize = 1 << 20;
+
+static PATTERN_CACHE: Lazy<Mutex<LruCache<String, Regex>>> = Lazy::new(|| {
+    Mutex::new(LruCache::new(NonZeroUsize::new(CACHE_CAPACITY).unwrap_or(NonZeroUsize::MIN)))
+});
+
+/// Extract entities with caller-supplied regex patterns
+///
+/// Patterns use Rust regex syntax (no look-around or backreferences).
+/// Each category reports its whole matches, in order.
+///
+/// Args:
+///     text: The input text
+///     patterns: Dict of category name -> regex pattern
+///
+/// Returns:
+///     Dict of category -> list of matched strings
+///
+/// Raises:
+///     ValueError: If a pattern is invalid or compiles to more than 1 MiB;
+///                 the message names the offending category
+#[pyfunction]
+pub fn extract_custom(
+    py: Python<'_>,
+    text: &str,
+    patterns: HashMap<String, String>,
+) -> PyResult<HashMap<String, Vec<String>>> {
+    let compiled = compile_patterns(&patterns)?;
+    Ok(crate::allow_threads_if_large(py, text.len(), || extract_custom_internal(text, &compiled)))
+}
+
+/// Compile (or fetch from cache) every pattern, keyed by category
+pub(crate) fn compile_patterns(patterns: &HashMap<String, String>) -> PyResult<Vec<(String, Regex)>> {
+    patterns
+        .iter()
+        .map(|(category, pattern)| {
+            let re = cached_regex(pattern).map_err(|err| {
+                PyValueError::new_err(format!("Invalid pattern for {:?}: {}", category, err))
+            })?;
+            Ok((category.clone(), re))
+        })
+        .collect()
+}
+
+pub(crate) fn extract_custom_internal(text: &str, compiled: &[(String, Regex)]) -> HashMap<String, Vec<String>> {
+    compiled
+        .iter()
+        .map(|(category, re)| {
+            let found = re.find_iter(text).map(|m| m.as_str().to_string()).collect();
+            (category.clone(), found)
+        })
+        .collect()
+}
+
+/// Look a pattern up in the cache, compiling and inserting it on a miss
+fn cached_regex(pattern: &str) -> Result<Regex, regex::Error> {
+    // A poisoned lock only means another thread panicked mid-update;
+    // the cache itself is still usable
+    let mut cache = PATTERN_CACHE.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(re) = cache.get(pattern) {
+        return Ok(re.clone());
+    }
+    let re = RegexBuilder::new(pattern)
+        .size_limit(PATTERN_SIZE_LIMIT)
+        .dfa_size_limit(PATTERN_SIZE_LIMIT)
+        .build()?;
+    cache.put(pattern.to_string(), re.clone());
+    Ok(re)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_custom_categories() {
+        let text = "Internado en cama 12-B, episodio EP-2025-00042. Traslado a cama 3-A.";
+        let compiled = compile_patterns(&patterns(&[
+            ("beds", r"cama \d+-[A-Z]"),
+            ("episodes", r"EP-\d{4}-\d{5}"),
+            ("missing", r"HC\d+"),
+        ]))
+        .unwrap();
+        let found = extract_custom_internal(text, &compiled);
+        assert_eq!(found["beds"], vec!["cama 12-B", "cama 3-A"]);
+        assert_eq!(found["episodes"], vec!["EP-2025-00042"]);
+        assert!(found["missing"].is_empty());
+    }
+
+    #[test]
+    fn test_study_identifiers_through_pyfunction() {
+        let text = "Protocolo NCT04567890, muestras S-0012 y S-0013; protocolo interno ONC-2024/07.";
+        crate::with_py(|py| {
+            let found = extract_custom(
+                py,
+                text,
+                patterns(&[("protocols", r"NCT\d{8}|[A-Z]{3}-\d{4}/\d{2}"), ("samples", r"\bS-\d{4}\b")]),
+            )
+            .unwrap();
+            assert_eq!(found["protocols"], vec!["NCT04567890", "ONC-2024/07"]);
+            assert_eq!(found["samples"], vec!["S-0012", "S-0013"]);
+
+            // One bad pattern fails the whole call and is named in the error
+            let err = extract_custom(py, text, patterns(&[("samples", r"S-\d{4}"), ("protocols", r"NCT[0-9")]))
+                .unwrap_err();
+            assert!(err.is_instance_of::<PyValueError>(py));
+            assert!(err.to_string().contains("protocols"), "{}", err);
+        });
+    }
+
+    #[test]
+    fn test_repeated_patterns_hit_the_cache() {
+        let pattern = r"cache-test-\d+";
+        let first = cached_regex(pattern).unwrap();
+        let second = cached_regex(pattern).unwrap();
+        assert_eq!(first.as_str(), second.as_str());
+        let cache = PATTERN_CACHE.lock().unwrap();
+        assert!(cache.contains(pattern));
+    }
+
+    #[test]
+    fn test_invalid_and_oversized_patterns_name_the_category() {
+        crate::with_py(|py| {
+            for (category, pattern) in [("broken", r"cama (\d+"), ("huge", r"(?:\w{100}){100}")] {
+                let Err(err) = compile_patterns(&patterns(&[(category, pattern)])) else {
+                    panic!("{} should be rejected", category);
+                };
+                assert!(err.is_instance_of::<PyValueError>(py));
+                assert!(err.to_string().contains(category), "{}", err);
+            }
+        });
+    }
+}
@@ -0,0 +1,327 @@
+//! Deterministic date shifting for de-identified exports
+//!
+//! Every date in a note moves by the same number of days, so intervals
+//! ("ingresó el 3, alta el 10") survive while the absolute dates do not.
+//! Each date is written back in the format it was found in: separators,
+//! zero padding and year width for numeric dates; connectors, month name
+//! or abbreviation and capitalization for textual ones.
+
+use once_cell::sync::Lazy;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::postprocess::{self, DateParts, EntityOptions, Year};
+
+const MONTH_NAMES: [&str; 12] = [
+    "enero", "febrero", "marzo", "abril", "mayo", "junio",
+    "julio", "agosto", "septiembre", "octubre", "noviembre", "diciembre",
+];
+
+const MONTH_ABBREVIATIONS: [&str; 12] = ["ene", "feb", "mar", "abr", "may", "jun", "jul", "ago", "sep", "oct", "nov", "dic"];
+
+/// Calendar and year-range checks used to skip invalid dates
+static VALIDATION: Lazy<EntityOptions> = Lazy::new(EntityOptions::default);
+
+/// Which date spellings shift_dates rewrites
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DateFormats {
+    All,
+    Numeric,
+    Textual,
+}
+
+impl DateFormats {
+    fn parse(formats: &str) -> PyResult<Self> {
+        match formats {
+            "auto" => Ok(Self::All),
+            "numeric" => Ok(Self::Numeric),
+            "textual" => Ok(Self::Textual),
+            _ => Err(PyValueError::new_err(format!(
+                "Unknown date formats: {:?} (expected \"auto\", \"numeric\" or \"textual\")",
+                formats
+            ))),
+        }
+    }
+
+    fn accepts(self, numeric: bool) -> bool {
+        match self {
+            Self::All => true,
+            Self::Numeric => numeric,
+            Self::Textual => !numeric,
+        }
+    }
+}
+
+/// Move every date in the text by a fixed number of days
+///
+/// Dates are found as in extract_entities ("15/01/2026", "15-1-26",
+/// "15 de enero de 2026", "20 DIC. 2024") and rewritten in the same
+/// format: "15/01/2026" shifted by 20 days is "04/02/2026", and "15 de
+/// enero de 2026" becomes "4 de febrero de 2026". Dates that fail calendar
+/// validation ("31/04/2025") are left untouched. Two-digit years are taken
+/// as 20YY for leap-year purposes; dates without a year are shifted within
+/// a common (non-leap) year, or a leap year for "29 de febrero".
+///
+/// Args:
+///     text: The input text
+///     offset_days: Days to add (negative to move dates back)
+///     formats: "auto" (all dates), "numeric" or "textual" (default: "auto")
+///
+/// Returns:
+///     (shifted_text, number_of_dates_shifted)
+///
+/// Raises:
+///     ValueError: If formats is unknown or offset_days is outside the
+///                 32-bit signed integer range
+#[pyfunction]
+#[pyo3(signature = (text, offset_days, formats="auto"))]
+pub fn shift_dates(py: Python<'_>, text: &str, offset_days: i64, formats: &str) -> PyResult<(String, usize)> {
+    let formats = DateFormats::parse(formats)?;
+    if i32::try_from(offset_days).is_err() {
+        return Err(PyValueError::new_err(format!("offset_days out of range: {}", offset_days)));
+    }
+    Ok(crate::allow_threads_if_large(py, text.len(), || shift_dates_internal(text, offset_days, formats)))
+}
+
+fn shift_dates_internal(text: &str, offset_days: i64, formats: DateFormats) -> (String, usize) {
+    let mut shifted = String::with_capacity(text.len());
+    let mut count = 0;
+    let mut last = 0;
+    for m in crate::DATE_RE.find_iter(text) {
+        let raw = m.as_str();
+        let numeric = is_numeric(raw);
+        let replacement = Some(raw)
+            .filter(|_| formats.accepts(numeric))
+            .filter(|raw| postprocess::is_valid("dates", raw, &VALIDATION))
+            .and_then(postprocess::parse_date)
+            .and_then(|date| {
+                let moved = shift(date, offset_days)?;
+                if numeric {
+                    rewrite_numeric(raw, moved)
+                } else {
+                    rewrite_textual(raw, moved)
+                }
+            });
+        if let Some(replacement) = replacement {
+            shifted.push_str(&text[last..m.start()]);
+            shifted.push_str(&replacement);
+            last = m.end();
+            count += 1;
+        }
+    }
+    shifted.push_str(&text[last..]);
+    (shifted, count)
+}
+
+fn is_numeric(raw: &str) -> bool {
+    raw.split(['/', '-']).count() == 3
+}
+
+/// The date `offset_days` later, keeping the kind of year that was written;
+/// None if it falls outside what a four-digit year can hold
+fn shift(date: DateParts, offset_days: i64) -> Option<DateParts> {
+    let year = match date.year {
+        Some(Year::Full(year)) => i64::from(year),
+        Some(Year::TwoDigit(year)) => 2000 + i64::from(year),
+        // Any leap year for February 29, any common year otherwise
+        None if date.month == 2 && date.day == 29 => 2000,
+        None => 2001,
+    };
+    let (year, month, day) = civil_from_days(days_from_civil(year, date.month, date.day).checked_add(offset_days)?);
+    let year = match date.year {
+        Some(Year::Full(_)) => Some(Year::Full(i32::try_from(year).ok().filter(|year| (0..=9999).contains(year))?)),
+        Some(Year::TwoDigit(_)) => Some(Year::TwoDigit(year.rem_euclid(100) as u32)),
+        None => None,
+    };
+    Some(DateParts { day, month, year })
+}
+
+/// "15/01/2026" -> "04/02/2026", keeping separators and field widths
+fn rewrite_numeric(raw: &str, date: DateParts) -> Option<String> {
+    let fields: Vec<&str> = raw.split(['/', '-']).collect();
+    let [day, month, _] = fields[..] else { return None };
+    let first_separator = &raw[day.len()..day.len() + 1];
+    let second_separator = &raw[day.len() + 1 + month.len()..day.len() + 2 + month.len()];
+    let year = match date.year? {
+        Year::Full(year) => format!("{:04}", year),
+        Year::TwoDigit(year) => format!("{:02}", year),
+    };
+    Some(format!(
+        "{:0day_width$}{}{:0month_width$}{}{}",
+        date.day,
+        first_separator,
+        date.month,
+        second_separator,
+        year,
+        day_width = day.len(),
+        month_width = month.len(),
+    ))
+}
+
+/// "15 de enero de 2026" -> "4 de febrero de 2026", keeping everything
+/// between the day, month and year
+fn rewrite_textual(raw: &str, date: DateParts) -> Option<String> {
+    let day_end = raw.find(|c: char| !c.is_ascii_digit())?;
+    let month_word = raw.split(|c: char| !c.is_alphabetic()).find(|word| is_month_word(word))?;
+    let month_start = crate::byte_offset(raw, month_word);
+    let month_end = month_start + month_word.len();
+
+    // Zero-padded only when written that way ("05 de mayo")
+    let width = if raw.starts_with('0') { 2 } else { 1 };
+    let mut rewritten = format!("{:0width$}", date.day, width = width);
+    let between = &raw[day_end..month_start];
+    // "1° de mayo" is the ordinal "primero"; other days are cardinal
+    match between.strip_prefix(['°', 'º']) {
+        Some(rest) if date.day != 1 => rewritten.push_str(rest),
+        _ => rewritten.push_str(between),
+    }
+    rewritten.push_str(&month_in_style(month_word, date.month));
+    match date.year {
+        // The year is always the last four characters of a textual match
+        Some(Year::Full(year)) => {
+            rewritten.push_str(&raw[month_end..raw.len() - 4]);
+            rewritten.push_str(&format!("{:04}", year));
+        }
+        _ => rewritten.push_str(&raw[month_end..]),
+    }
+    Some(rewritten)
+}
+
+fn is_month_word(word: &str) -> bool {
+    let lower = word.to_lowercase();
+    !lower.is_empty()
+        && (MONTH_NAMES.contains(&lower.as_str())
+            || MONTH_ABBREVIATIONS.contains(&lower.as_str())
+            || ["setiembre", "sept", "set"].contains(&lower.as_str()))
+}
+
+/// Name of `month` as a full name or abbreviation, cased like `original`
+fn month_in_style(original: &str, month: u32) -> String {
+    let index = (month as usize).saturating_sub(1).min(11);
+    let lower = original.to_lowercase();
+    let is_full = MONTH_NAMES.contains(&lower.as_str()) || lower == "setiembre";
+    let name = if is_full { MONTH_NAMES[index] } else { MONTH_ABBREVIATIONS[index] };
+    if original.chars().count() > 1 && original.chars().all(char::is_uppercase) {
+        name.to_uppercase()
+    } else if original.starts_with(char::is_uppercase) {
+        let mut chars = name.chars();
+        chars.next().map_or_else(String::new, |first| first.to_uppercase().chain(chars).collect())
+    } else {
+        name.to_string()
+    }
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date
+pub(crate) fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = i64::from(month);
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Inverse of days_from_civil: (year, month, day)
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shifted(text: &str, offset_days: i64) -> String {
+        shift_dates_internal(text, offset_days, DateFormats::All).0
+    }
+
+    #[test]
+    fn test_civil_day_numbers_round_trip() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1) - days_from_civil(2000, 2, 28), 2);
+        assert_eq!(days_from_civil(1900, 3, 1) - days_from_civil(1900, 2, 28), 1);
+        for days in [-800_000, -1, 0, 59, 10_957, 20_468, 800_000] {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+    }
+
+    #[test]
+    fn test_leap_years_and_rollovers() {
+        let cases = [
+            ("28/02/2024", 1, "29/02/2024"),
+            ("28/02/2025", 1, "01/03/2025"),
+            ("29/02/2024", 365, "28/02/2025"),
+            ("01/03/2024", -1, "29/02/2024"),
+            ("31/01/2026", 1, "01/02/2026"),
+            ("30/04/2026", 1, "01/05/2026"),
+            ("31/12/2025", 1, "01/01/2026"),
+            ("15/01/2026", 20, "04/02/2026"),
+            ("1/1/2000", -1, "31/12/1999"),
+        ];
+        for (raw, offset, expected) in cases {
+            assert_eq!(shifted(raw, offset), expected, "{} {:+}", raw, offset);
+        }
+    }
+
+    #[test]
+    fn test_formats_are_preserved() {
+        let text = "Ingreso 15/01/2026, control 5-3-26, alta el 15 de enero de 2026; \
+                    cirugía 20 DIC. 2024, consulta 1° de Mayo, 31 de diciembre del 2025.";
+        let (result, count) = shift_dates_internal(text, 20, DateFormats::All);
+        assert_eq!(
+            result,
+            "Ingreso 04/02/2026, control 25-3-26, alta el 4 de febrero de 2026; \
+             cirugía 9 ENE. 2025, consulta 21 de Mayo, 20 de enero del 2026."
+        );
+        assert_eq!(count, 6);
+    }
+
+    #[test]
+    fn test_ordinal_first_of_month() {
+        assert_eq!(shifted("1° de mayo", 1), "2 de mayo");
+        assert_eq!(shifted("30 de abril", 1), "1 de mayo");
+        assert_eq!(shifted("05 de abril", 1), "06 de abril");
+        assert_eq!(shifted("1º de Mayo de 2025", 31), "1º de Junio de 2025");
+    }
+
+    #[test]
+    fn test_invalid_dates_and_format_filter() {
+        let text = "Lote 45/23/0000, control 31/04/2025, alta 10 de marzo de 2025 y 10/03/2025";
+        assert_eq!(
+            shift_dates_internal(text, 1, DateFormats::All),
+            ("Lote 45/23/0000, control 31/04/2025, alta 11 de marzo de 2025 y 11/03/2025".to_string(), 2)
+        );
+        assert_eq!(shift_dates_internal(text, 1, DateFormats::Numeric).1, 1);
+        assert_eq!(
+            shift_dates_internal(text, 1, DateFormats::Textual).0,
+            "Lote 45/23/0000, control 31/04/2025, alta 11 de marzo de 2025 y 10/03/2025"
+        );
+        assert!(DateFormats::parse("iso").is_err());
+        assert_eq!(shifted("Sin fechas", 10), "Sin fechas");
+    }
+
+    #[test]
+    fn test_offset_range() {
+        crate::with_py(|py| {
+            for offset in [i64::MAX, i64::MIN, i64::from(i32::MAX) + 1] {
+                let err = shift_dates(py, "alta 10/03/2025", offset, "auto").unwrap_err();
+                assert!(err.is_instance_of::<PyValueError>(py));
+            }
+        });
+        // Past year 9999 the date is left as written
+        assert_eq!(shift_dates_internal("alta 10/03/2025", i64::from(i32::MAX), DateFormats::All).1, 0);
+        // Two-digit years only keep the last two digits
+        assert_eq!(shift_dates_internal("alta 10/03/25", i64::from(i32::MIN), DateFormats::All).1, 1);
+    }
+}
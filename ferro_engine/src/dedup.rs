@@ -0,0 +1,74 @@
+//! Exact deduplication of chunks
+//!
+//! Chunking overlapping versions of a document yields many byte-identical
+//! chunks; embedding each only once saves the calls. Near-duplicates are
+//! left to find_near_duplicates.
+
+use std::collections::HashMap;
+
+use pyo3::prelude::*;
+
+/// Drop byte-identical chunks, remembering where each one went
+///
+/// Args:
+///     chunks: Chunks, possibly repeated
+///
+/// Returns:
+///     (unique, mapping): the distinct chunks in first-seen order, and for
+///     each input chunk the index of its copy in unique, so that
+///     [unique[i] for i in mapping] == chunks
+#[pyfunction]
+pub fn dedup_chunks(py: Python<'_>, chunks: Vec<String>) -> PyResult<(Vec<String>, Vec<usize>)> {
+    Ok(py.allow_threads(|| dedup_chunks_internal(chunks)))
+}
+
+pub(crate) fn dedup_chunks_internal(chunks: Vec<String>) -> (Vec<String>, Vec<usize>) {
+    let mut index_of: HashMap<&str, usize> = HashMap::with_capacity(chunks.len());
+    let mut first_seen: Vec<usize> = Vec::new();
+    let mapping: Vec<usize> = chunks
+        .iter()
+        .enumerate()
+        .map(|(position, chunk)| {
+            *index_of.entry(chunk.as_str()).or_insert_with(|| {
+                first_seen.push(position);
+                first_seen.len() - 1
+            })
+        })
+        .collect();
+
+    let mut chunks: Vec<Option<String>> = chunks.into_iter().map(Some).collect();
+    let unique = first_seen.into_iter().filter_map(|position| chunks[position].take()).collect();
+    (unique, mapping)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeated_chunks() {
+        let chunks: Vec<String> = ["a", "b", "a", "c", "b", "a", "A", "a "].map(String::from).to_vec();
+        let (unique, mapping) = dedup_chunks_internal(chunks.clone());
+        // Only byte-identical chunks merge: "A" and "a " stay distinct
+        assert_eq!(unique, vec!["a", "b", "c", "A", "a "]);
+        assert_eq!(mapping, vec![0, 1, 0, 2, 1, 0, 3, 4]);
+        let rebuilt: Vec<String> = mapping.iter().map(|&i| unique[i].clone()).collect();
+        assert_eq!(rebuilt, chunks);
+    }
+
+    #[test]
+    fn test_edge_cases() {
+        assert_eq!(dedup_chunks_internal(vec![]), (vec![], vec![]));
+        let (unique, mapping) = dedup_chunks_internal(vec!["niño".into(), "niño".into(), String::new(), String::new()]);
+        assert_eq!((unique.len(), mapping), (2, vec![0, 0, 1, 1]));
+
+        crate::with_py(|py| {
+            let chunks: Vec<String> = (0..1000).map(|i| format!("chunk {}", i % 10)).collect();
+            let (unique, mapping) = dedup_chunks(py, chunks).unwrap();
+            assert_eq!(unique.len(), 10);
+            assert_eq!(mapping.len(), 1000);
+            assert_eq!(mapping[..12], [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 0, 1]);
+        });
+    }
+}
@@ -0,0 +1,411 @@
+//! Patient demographics written in the note
+//!
+//! Ages are only taken with context: a person word or "edad" before the
+//! number ("paciente de 45 años", "lactante de 3 meses", "edad: 70") or
+//! "de edad"/"de vida" after it ("45 años de edad"). A bare "hace 5 años"
+//! or "5 meses de tratamiento" is a duration, not an age.
+//!
+//! Weight and height belong to extract_demographics: they are only read
+//! after a label ("Peso: 82 kg", "Talla 1,75 m") and normalized to kg and
+//! cm. extract_measurements and extract_measurements_structured leave the
+//! "82 kg" inside such a label out, so each weight is reported once.
+use once_cell::sync::Lazy;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use regex::Regex;
+
+use crate::measurements::{parse_number, NUMBER};
+use crate::CharCursor;
+
+/// Oldest age accepted, in years
+const MAX_YEARS: u32 = 120;
+
+static AGE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?xi)
+        (?:
+            \b(?P<label>
+                edad | paciente | pte\.? | lactante | neonato | rn | reci[eé]n\ nacid[oa]
+              | niñ[oa] | beb[eé] | beba | adolescente | joven | adult[oa] | ancian[oa]
+              | var[oó]n | mujer | hombre | masculino | femenino | señora | señor | sra\. | sr\.
+            )
+            [\s,]* (?: de \s+ | : \s* )?
+        )?
+        \b(?P<value> \d{1,3} ) (?: \s* (?P<unit> años | año | meses | mes ) | (?P<short> a ) )?\b
+        (?P<suffix> \s+ de \s+ (?: edad | vida ) \b )?",
+    )
+    .expect("Invalid age regex - this is a bug")
+});
+
+static SEX_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?xi)
+        \b(?: sexo | g[eé]nero ) \s* [:=]? \s* (?: \s* de \s+ )?
+            (?P<code> masculino | femenino | masc | fem | m | f )\b
+      | \b(?P<word> masculin[oa] | femenin[oa] | var[oó]n | hombre | mujer )\b",
+    )
+    .expect("Invalid sex regex - this is a bug")
+});
+
+static WEIGHT_RE: Lazy<Regex> = Lazy::new(|| {
+    let pattern = format!(
+        r"(?xi)
+        \b(?: peso | pesa ) (?: \s+ (?: actual | al \s+ (?: ingreso | nacer ) | de \s+ ingreso ) )?
+        \s* [:=]? \s* (?: de \s+ )? (?P<value> {num} ) \s* (?P<unit> kilogramos? | kilos? | kgs? | gramos? | gr | g )?\b",
+        num = NUMBER,
+    );
+    Regex::new(&pattern).expect("Invalid weight regex - this is a bug")
+});
+
+static HEIGHT_RE: Lazy<Regex> = Lazy::new(|| {
+    let pattern = format!(
+        r"(?xi)
+        \b(?: talla | altura | estatura ) \s* [:=]? \s* (?: de \s+ )? (?P<value> {num} )
+        \s* (?P<unit> cent[ií]metros? | cms? | metros? | mts? | m )?\b",
+        num = NUMBER,
+    );
+    Regex::new(&pattern).expect("Invalid height regex - this is a bug")
+});
+
+/// Plausible weights, in kg, from a premature newborn up
+const WEIGHT_KG: std::ops::RangeInclusive<f64> = 0.3..=400.0;
+
+/// Plausible heights, in cm
+const HEIGHT_CM: std::ops::RangeInclusive<f64> = 30.0..=250.0;
+
+/// Unit of an extracted age
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum AgeUnit {
+    Years,
+    Months,
+}
+
+impl AgeUnit {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Years => "years",
+            Self::Months => "months",
+        }
+    }
+}
+
+/// Extract patient ages
+///
+/// A number counts as an age when a person word or "edad" comes right
+/// before it ("paciente de 45 años", "varón, 60 años", "lactante de 3
+/// meses", "edad: 70") or "de edad"/"de vida" right after ("8 meses de
+/// vida"). Without a unit, only "edad" makes it an age, in years.
+///
+/// Args:
+///     text: The input text
+///
+/// Returns:
+///     List of (value, unit) with unit "years" or "months", in text order
+#[pyfunction]
+pub fn extract_ages(py: Python<'_>, text: &str) -> PyResult<Vec<(u32, String)>> {
+    Ok(crate::allow_threads_if_large(py, text.len(), || {
+        extract_ages_internal(text).into_iter().map(|(value, unit)| (value, unit.name().to_string())).collect()
+    }))
+}
+
+pub(crate) fn extract_ages_internal(text: &str) -> Vec<(u32, AgeUnit)> {
+    ages(text).into_iter().map(|age| age.value).collect()
+}
+
+/// One stated value with the text supporting it
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Stated<T> {
+    pub value: T,
+    pub raw: String,
+    /// Character offsets of the match
+    pub start: usize,
+    pub end: usize,
+}
+
+impl<T> Stated<T> {
+    fn new(value: T, whole: regex::Match<'_>, cursor: &mut CharCursor<'_>) -> Self {
+        Self {
+            value,
+            raw: whole.as_str().to_string(),
+            start: cursor.char_offset(whole.start()),
+            end: cursor.char_offset(whole.end()),
+        }
+    }
+
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("raw", &self.raw)?;
+        dict.set_item("span", (self.start, self.end))?;
+        Ok(dict)
+    }
+}
+
+/// Everything extract_demographics found
+#[derive(Debug, Clone, PartialEq, Default)]
+pub(crate) struct Demographics {
+    pub ages: Vec<Stated<(u32, AgeUnit)>>,
+    /// 'M' or 'F' per mention
+    pub sexes: Vec<Stated<char>>,
+    /// In kg
+    pub weights: Vec<Stated<f64>>,
+    /// In cm
+    pub heights: Vec<Stated<f64>>,
+}
+
+impl Demographics {
+    /// The sex every mention agrees on, if any
+    pub(crate) fn sex(&self) -> Option<char> {
+        let first = self.sexes.first()?.value;
+        self.sexes.iter().all(|sex| sex.value == first).then_some(first)
+    }
+}
+
+/// Extract age, sex, weight and height
+///
+/// Ages follow extract_ages, plus the short "72a". Sex comes from "sexo:
+/// M/F", "sexo masculino" or the words masculino/femenina, varón, hombre
+/// and mujer ("su mujer" is the patient's wife and is skipped). Weight and
+/// height need a label ("Peso: 82 kg", "peso al nacer 3200 g", "Talla 1,75
+/// m", "estatura: 168 cm"); without a unit, a weight is in kg (in grams
+/// from 500 on) and a height in m below 3, in cm otherwise. Implausible
+/// values are dropped. All mentions are returned, conflicting ones included.
+///
+/// Args:
+///     text: The input text
+///
+/// Returns:
+///     Dict with:
+///     - "ages": list of {"years" (float), "value", "unit" ("years" or
+///       "months"), "raw", "span"}
+///     - "sex": "M" or "F" when every mention agrees, else None
+///     - "sex_mentions": list of {"value" ("M" or "F"), "raw", "span"}
+///     - "weight": list of {"value" (float), "unit" ("kg"), "raw", "span"}
+///     - "height": list of {"value" (float), "unit" ("cm"), "raw", "span"}
+///     with span as (start_char, end_char)
+#[pyfunction]
+pub fn extract_demographics<'py>(py: Python<'py>, text: &str) -> PyResult<Bound<'py, PyDict>> {
+    let found = crate::allow_threads_if_large(py, text.len(), || extract_demographics_internal(text));
+    let result = PyDict::new_bound(py);
+    let ages = found
+        .ages
+        .iter()
+        .map(|age| {
+            let (value, unit) = age.value;
+            let dict = age.to_dict(py)?;
+            let years = match unit {
+                AgeUnit::Years => f64::from(value),
+                AgeUnit::Months => f64::from(value) / 12.0,
+            };
+            dict.set_item("years", years)?;
+            dict.set_item("value", value)?;
+            dict.set_item("unit", unit.name())?;
+            Ok(dict)
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+    result.set_item("ages", ages)?;
+    result.set_item("sex", found.sex().map(String::from))?;
+    let sexes = found
+        .sexes
+        .iter()
+        .map(|sex| {
+            let dict = sex.to_dict(py)?;
+            dict.set_item("value", sex.value.to_string())?;
+            Ok(dict)
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+    result.set_item("sex_mentions", sexes)?;
+    for (key, unit, values) in [("weight", "kg", &found.weights), ("height", "cm", &found.heights)] {
+        let values = values
+            .iter()
+            .map(|stated| {
+                let dict = stated.to_dict(py)?;
+                dict.set_item("value", stated.value)?;
+                dict.set_item("unit", unit)?;
+                Ok(dict)
+            })
+            .collect::<PyResult<Vec<_>>>()?;
+        result.set_item(key, values)?;
+    }
+    Ok(result)
+}
+
+pub(crate) fn extract_demographics_internal(text: &str) -> Demographics {
+    Demographics { ages: ages(text), sexes: sexes(text), weights: weights(text), heights: heights(text) }
+}
+
+fn ages(text: &str) -> Vec<Stated<(u32, AgeUnit)>> {
+    let mut cursor = CharCursor::new(text);
+    AGE_RE
+        .captures_iter(text)
+        .filter_map(|caps| {
+            let label = caps.name("label").map(|label| label.as_str().to_lowercase());
+            let unit = match caps.name("unit").or(caps.name("short")).map(|unit| unit.as_str().to_lowercase()) {
+                Some(unit) if unit.starts_with("a") => Some(AgeUnit::Years),
+                Some(_) => Some(AgeUnit::Months),
+                None => None,
+            };
+            let unit = match (unit, label.as_deref(), caps.name("suffix")) {
+                (Some(unit), Some(_), _) | (Some(unit), None, Some(_)) => unit,
+                (None, Some("edad"), _) => AgeUnit::Years,
+                _ => return None,
+            };
+            let value: u32 = caps.name("value")?.as_str().parse().ok()?;
+            let plausible = match unit {
+                AgeUnit::Years => value <= MAX_YEARS,
+                AgeUnit::Months => value <= MAX_YEARS * 12,
+            };
+            let whole = caps.get(0)?;
+            plausible.then(|| Stated::new((value, unit), whole, &mut cursor))
+        })
+        .collect()
+}
+
+fn sexes(text: &str) -> Vec<Stated<char>> {
+    let mut cursor = CharCursor::new(text);
+    SEX_RE
+        .captures_iter(text)
+        .filter_map(|caps| {
+            let whole = caps.get(0)?;
+            let word = caps.name("code").or(caps.name("word"))?.as_str().to_lowercase();
+            let possessive = text[..whole.start()].split_whitespace().next_back().is_some_and(|w| w.eq_ignore_ascii_case("su"));
+            if caps.name("word").is_some() && possessive {
+                return None;
+            }
+            let sex = if word.starts_with('f') || word == "mujer" { 'F' } else { 'M' };
+            Some(Stated::new(sex, whole, &mut cursor))
+        })
+        .collect()
+}
+
+/// Character spans of the weights and heights extract_demographics reports
+pub(crate) fn body_measure_spans(text: &str) -> Vec<(usize, usize)> {
+    let (weights, heights) = (weights(text), heights(text));
+    weights.iter().chain(&heights).map(|stated| (stated.start, stated.end)).collect()
+}
+
+fn weights(text: &str) -> Vec<Stated<f64>> {
+    let mut cursor = CharCursor::new(text);
+    WEIGHT_RE
+        .captures_iter(text)
+        .filter_map(|caps| {
+            let value = parse_number(caps.name("value")?.as_str())?;
+            let grams = match caps.name("unit") {
+                Some(unit) => unit.as_str().to_lowercase().starts_with('g'),
+                None => value >= 500.0,
+            };
+            let kg = if grams { value / 1000.0 } else { value };
+            let whole = caps.get(0)?;
+            WEIGHT_KG.contains(&kg).then(|| Stated::new(kg, whole, &mut cursor))
+        })
+        .collect()
+}
+
+fn heights(text: &str) -> Vec<Stated<f64>> {
+    let mut cursor = CharCursor::new(text);
+    HEIGHT_RE
+        .captures_iter(text)
+        .filter_map(|caps| {
+            let value = parse_number(caps.name("value")?.as_str())?;
+            let metres = match caps.name("unit") {
+                Some(unit) => unit.as_str().to_lowercase().starts_with('m'),
+                None => value < 3.0,
+            };
+            let cm = if metres { value * 100.0 } else { value };
+            let whole = caps.get(0)?;
+            HEIGHT_CM.contains(&cm).then(|| Stated::new(cm, whole, &mut cursor))
+        })
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_years_and_months() {
+        let text = "Paciente de 45 años con HTA. Lactante de 3 meses; varón, 60 años. \
+                    Niña de 1 año. RN de 2 meses de vida. Edad: 70. Masculino de 33 años de edad.";
+        assert_eq!(
+            extract_ages_internal(text),
+            vec![
+                (45, AgeUnit::Years),
+                (3, AgeUnit::Months),
+                (60, AgeUnit::Years),
+                (1, AgeUnit::Years),
+                (2, AgeUnit::Months),
+                (70, AgeUnit::Years),
+                (33, AgeUnit::Years),
+            ]
+        );
+        assert_eq!(extract_ages_internal("Se trata de un paciente de sexo femenino de 28 años"), vec![(28, AgeUnit::Years)]);
+    }
+
+    #[test]
+    fn test_bare_numbers_are_not_ages() {
+        let text = "Fuma hace 20 años, 5 meses de tratamiento, cama 45, 3 comprimidos, TA 120/80, \
+                    paciente 12345, edad gestacional desconocida";
+        assert!(extract_ages_internal(text).is_empty());
+        // Implausible ages are dropped
+        assert!(extract_ages_internal("paciente de 150 años").is_empty());
+    }
+
+    #[test]
+    fn test_demographics_with_spans() {
+        let text = "Paciente de 72a, sexo: M. Peso: 82 kg, talla 1,75 m. Varón con su mujer.";
+        let found = extract_demographics_internal(text);
+        assert_eq!(found.ages.iter().map(|age| age.value).collect::<Vec<_>>(), vec![(72, AgeUnit::Years)]);
+        assert_eq!(found.sexes.iter().map(|sex| sex.value).collect::<Vec<_>>(), vec!['M', 'M']);
+        assert_eq!(found.sex(), Some('M'));
+        assert_eq!(found.weights.iter().map(|w| w.value).collect::<Vec<_>>(), vec![82.0]);
+        assert_eq!(found.heights.iter().map(|h| h.value).collect::<Vec<_>>(), vec![175.0]);
+        let chars: Vec<char> = text.chars().collect();
+        let span = |start: usize, end: usize| chars[start..end].iter().collect::<String>();
+        assert_eq!(span(found.ages[0].start, found.ages[0].end), "Paciente de 72a");
+        assert_eq!(span(found.weights[0].start, found.weights[0].end), "Peso: 82 kg");
+        assert_eq!(span(found.heights[0].start, found.heights[0].end), "talla 1,75 m");
+
+        // The measurement extractor leaves the weight to extract_demographics
+        assert!(crate::measurements::extract_measurements_structured_internal(text, false).is_empty());
+        let measurements = crate::measurements::extract_measurements_structured_internal(text, true);
+        assert_eq!(measurements.len(), 1);
+        assert!(found.weights[0].start <= measurements[0].start && measurements[0].end <= found.weights[0].end);
+    }
+
+    #[test]
+    fn test_conflicts_and_units() {
+        let found = extract_demographics_internal("Edad: 45. Paciente de 47 años, sexo femenino. Hombre de 45 años.");
+        assert_eq!(found.ages.len(), 3);
+        assert_eq!(found.sexes.iter().map(|sex| sex.value).collect::<Vec<_>>(), vec!['F', 'M']);
+        assert_eq!(found.sex(), None);
+
+        let newborn = extract_demographics_internal("RN de 3 meses de edad. Peso al nacer: 3200 g. Peso actual 5,1. Estatura: 58 cm");
+        assert_eq!(newborn.ages.iter().map(|age| age.value).collect::<Vec<_>>(), vec![(3, AgeUnit::Months)]);
+        assert_eq!(newborn.weights.iter().map(|w| w.value).collect::<Vec<_>>(), vec![3.2, 5.1]);
+        assert_eq!(newborn.heights.iter().map(|h| h.value).collect::<Vec<_>>(), vec![58.0]);
+        assert_eq!(newborn.sex(), None);
+
+        // Unlabeled or implausible values are not weights or heights
+        let none = extract_demographics_internal("Lesión de 2 cm, 500 mg de paracetamol, peso 0, talla 4 m, a la altura de L4");
+        assert!(none.weights.is_empty() && none.heights.is_empty(), "{:?}", none);
+    }
+
+    #[test]
+    fn test_pyfunction_units() {
+        crate::with_py(|py| {
+            assert_eq!(
+                extract_ages(py, "Mujer de 32 años con bebé de 4 meses").unwrap(),
+                vec![(32, "years".to_string()), (4, "months".to_string())]
+            );
+            let found = extract_demographics(py, "Lactante de 6 meses, sexo: F, peso 7 kg").unwrap();
+            let ages: Vec<Bound<'_, PyDict>> = found.get_item("ages").unwrap().unwrap().extract().unwrap();
+            let years: f64 = ages[0].get_item("years").unwrap().unwrap().extract().unwrap();
+            assert_eq!(years, 0.5);
+            let sex: Option<String> = found.get_item("sex").unwrap().unwrap().extract().unwrap();
+            assert_eq!(sex.as_deref(), Some("F"));
+            let weights: Vec<Bound<'_, PyDict>> = found.get_item("weight").unwrap().unwrap().extract().unwrap();
+            let span: (usize, usize) = weights[0].get_item("span").unwrap().unwrap().extract().unwrap();
+            assert_eq!(span, (30, 39));
+        });
+    }
+}
@@ -0,0 +1,235 @@
+//! Gazetteer matching with Aho-Corasick
+//!
+//! Finding every mention of a 30k-entry vademécum with one regex per term
+//! is far too slow; an Aho-Corasick automaton scans each text once for all
+//! terms. Case and accent folding map every character to exactly one
+//! character, so offsets in the folded text are offsets in the original.
+//! Accent folding keeps "ñ" unless asked not to: "ano" is not "año".
+
+use aho_corasick::AhoCorasick;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+
+/// (term, start_char, end_char) of each mention
+pub(crate) type Mentions = Vec<(String, usize, usize)>;
+
+/// A reusable matcher for a fixed list of terms
+///
+/// Build it once per dictionary and call find / find_batch as often as
+/// needed.
+///
+/// Args:
+///     terms: Dictionary entries (drug names, diagnoses, ...)
+///     case_insensitive: Ignore case (default: True)
+///     accent_insensitive: Ignore accents, so "acido" finds "ácido"
+///                         (default: True)
+///     keep_enie: With accent_insensitive, keep "ñ" apart from "n", so
+///                "ano" does not find "año" (default: True)
+///     whole_words: Only report mentions that start and end at word
+///                  boundaries, so "aspirina" is not found inside
+///                  "aspirinado" (default: True)
+///
+/// Raises:
+///     ValueError: If the automaton cannot be built
+#[pyclass(module = "ferro_engine")]
+pub struct DictionaryMatcher {
+    automaton: AhoCorasick,
+    /// Entry reported for each automaton pattern, as given
+    terms: Vec<String>,
+    case_insensitive: bool,
+    accent_insensitive: bool,
+    keep_enie: bool,
+    whole_words: bool,
+}
+
+#[pymethods]
+impl DictionaryMatcher {
+    #[new]
+    #[pyo3(signature = (terms, case_insensitive=true, accent_insensitive=true, whole_words=true, keep_enie=true))]
+    fn py_new(
+        py: Python<'_>,
+        terms: Vec<String>,
+        case_insensitive: bool,
+        accent_insensitive: bool,
+        whole_words: bool,
+        keep_enie: bool,
+    ) -> PyResult<Self> {
+        py.allow_threads(|| Self::new(terms, case_insensitive, accent_insensitive, whole_words, keep_enie))
+    }
+
+    /// Find dictionary mentions in a text
+    ///
+    /// Where mentions overlap, the leftmost wins, then the longest
+    /// ("ácido valproico" over "ácido").
+    ///
+    /// Args:
+    ///     text: The input text
+    ///
+    /// Returns:
+    ///     List of (term, start_char, end_char), in text order, with term
+    ///     as it appears in the dictionary
+    fn find(&self, py: Python<'_>, text: &str) -> Mentions {
+        crate::allow_threads_if_large(py, text.len(), || self.find_internal(text))
+    }
+
+    /// find over many texts, in parallel without the GIL
+    ///
+    /// Args:
+    ///     texts: List of texts
+    ///
+    /// Returns:
+    ///     One find result per text, in input order
+    fn find_batch(&self, py: Python<'_>, texts: Vec<String>) -> Vec<Mentions> {
+        py.allow_threads(|| texts.par_iter().map(|text| self.find_internal(text)).collect())
+    }
+
+    fn __len__(&self) -> usize {
+        self.terms.len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "DictionaryMatcher(terms={}, case_insensitive={}, accent_insensitive={}, whole_words={}, keep_enie={})",
+            self.terms.len(),
+            self.case_insensitive,
+            self.accent_insensitive,
+            self.whole_words,
+            self.keep_enie
+        )
+    }
+}
+
+impl DictionaryMatcher {
+    pub(crate) fn new(
+        terms: Vec<String>,
+        case_insensitive: bool,
+        accent_insensitive: bool,
+        whole_words: bool,
+        keep_enie: bool,
+    ) -> PyResult<Self> {
+        // Entries that fold to the same pattern are reported as the first one
+        let mut seen = std::collections::HashSet::new();
+        let mut patterns = Vec::with_capacity(terms.len());
+        let mut kept = Vec::with_capacity(terms.len());
+        for term in terms {
+            let pattern: String =
+                term.trim().chars().map(|c| crate::fold_char(c, case_insensitive, accent_insensitive, keep_enie)).collect();
+            if !pattern.is_empty() && seen.insert(pattern.clone()) {
+                patterns.push(pattern);
+                kept.push(term);
+            }
+        }
+        let automaton = AhoCorasick::new(&patterns)
+            .map_err(|e| PyValueError::new_err(format!("cannot build dictionary automaton: {}", e)))?;
+        Ok(Self { automaton, terms: kept, case_insensitive, accent_insensitive, keep_enie, whole_words })
+    }
+
+    pub(crate) fn find_internal(&self, text: &str) -> Mentions {
+        // char_at[byte offset in folded] = character offset, at char starts
+        let mut folded = String::with_capacity(text.len());
+        let mut char_at = Vec::with_capacity(text.len() + 1);
+        for (index, c) in text.chars().enumerate() {
+            let c = crate::fold_char(c, self.case_insensitive, self.accent_insensitive, self.keep_enie);
+            char_at.extend(std::iter::repeat_n(index, c.len_utf8()));
+            folded.push(c);
+        }
+        char_at.push(text.chars().count());
+        let chars: Vec<char> = folded.chars().collect();
+        let is_word = |index: usize| chars.get(index).is_some_and(|c| c.is_alphanumeric());
+
+        let mut found: Vec<(usize, usize, usize)> = self
+            .automaton
+            .find_overlapping_iter(&folded)
+            .map(|m| (char_at[m.start()], char_at[m.end()], m.pattern().as_usize()))
+            .filter(|&(start, end, _)| !self.whole_words || !(start.checked_sub(1).is_some_and(is_word) || is_word(end)))
+            .collect();
+
+        // Leftmost, then longest; drop anything overlapping a kept mention
+        found.sort_unstable_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+        let mut mentions = Mentions::new();
+        let mut covered = 0;
+        for (start, end, pattern) in found {
+            if start >= covered {
+                mentions.push((self.terms[pattern].clone(), start, end));
+                covered = end;
+            }
+        }
+        mentions
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matcher(terms: &[&str], case: bool, accents: bool, whole: bool) -> DictionaryMatcher {
+        DictionaryMatcher::new(terms.iter().map(|t| t.to_string()).collect(), case, accents, whole, true).unwrap()
+    }
+
+    fn mention(term: &str, start: usize, end: usize) -> (String, usize, usize) {
+        (term.to_string(), start, end)
+    }
+
+    #[test]
+    fn test_folding_and_offsets() {
+        let drugs = matcher(&["Ácido valproico", "ácido", "Amoxicilina", "enalapril"], true, true, true);
+        let text = "Niño con ACIDO VALPROICO y amoxicilina; suspendió Enalapril. Ácido fólico.";
+        let found = drugs.find_internal(text);
+        assert_eq!(
+            found,
+            vec![
+                mention("Ácido valproico", 9, 24),
+                mention("Amoxicilina", 27, 38),
+                mention("enalapril", 50, 59),
+                mention("ácido", 61, 66),
+            ]
+        );
+        let chars: Vec<char> = text.chars().collect();
+        assert_eq!(chars[9..24].iter().collect::<String>(), "ACIDO VALPROICO");
+        assert_eq!(chars[61..66].iter().collect::<String>(), "Ácido");
+
+        let strict = matcher(&["ácido"], false, false, true);
+        assert_eq!(strict.find_internal("ACIDO, acido, ácido"), vec![mention("ácido", 14, 19)]);
+    }
+
+    #[test]
+    fn test_enie_is_kept_apart() {
+        let text = "Fístula de ano hace un AÑO";
+        assert_eq!(matcher(&["ano"], true, true, true).find_internal(text), vec![mention("ano", 11, 14)]);
+        assert_eq!(matcher(&["año"], true, true, true).find_internal(text), vec![mention("año", 23, 26)]);
+        let folded = DictionaryMatcher::new(vec!["ano".to_string()], true, true, true, false).unwrap();
+        assert_eq!(folded.find_internal(text), vec![mention("ano", 11, 14), mention("ano", 23, 26)]);
+    }
+
+    #[test]
+    fn test_whole_words() {
+        let text = "Aspirinado, aspirina 100 mg, antiaspirina";
+        assert_eq!(matcher(&["aspirina"], true, true, true).find_internal(text), vec![mention("aspirina", 12, 20)]);
+        let partial = matcher(&["aspirina"], true, true, false).find_internal(text);
+        assert_eq!(partial.iter().map(|m| m.1).collect::<Vec<_>>(), vec![0, 12, 33]);
+        // A longer entry failing the boundary check leaves the shorter one
+        assert_eq!(
+            matcher(&["insulina", "insulina nph"], true, true, true).find_internal("insulina nphx"),
+            vec![mention("insulina", 0, 8)]
+        );
+    }
+
+    #[test]
+    fn test_pyclass_batch() {
+        crate::with_py(|py| {
+            let terms = vec!["metformina".to_string(), "METFORMINA".to_string(), String::new(), "insulina".to_string()];
+            let matcher = DictionaryMatcher::py_new(py, terms, true, true, true, true).unwrap();
+            assert_eq!(matcher.__len__(), 2);
+            let texts = vec!["Metformina 850 mg".to_string(), "sin medicación".to_string(), "insulina y metformina".to_string()];
+            let batch = matcher.find_batch(py, texts.clone());
+            assert_eq!(batch.len(), 3);
+            for (text, found) in texts.iter().zip(&batch) {
+                assert_eq!(&matcher.find(py, text), found);
+            }
+            assert_eq!(batch[2], vec![mention("insulina", 0, 8), mention("metformina", 11, 21)]);
+            assert!(batch[1].is_empty());
+        });
+    }
+}
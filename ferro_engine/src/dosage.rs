@@ -0,0 +1,327 @@
+//! Structured dosage extraction (drug + dose + frequency)
+//!
+//! Recognizes prescription phrasings common in Spanish clinical notes:
+//! - "Amoxicilina 500 mg cada 8 horas"
+//! - "ibuprofeno 400mg c/6h"
+//! - "enalapril 10 mg 1-0-1"
+//! - "Metformina 850 mg dos veces al día"
+
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use regex::Regex;
+
+use crate::measurements::{parse_number, NUMBER};
+use crate::CharCursor;
+
+static DOSE_RE: Lazy<Regex> = Lazy::new(|| {
+    let pattern = format!(
+        r"(?xi)
+        \b(?P<value>{num}) \s*
+        (?P<unit>mg|mcg|µg|g|ml|ui|gotas|comprimidos?|comp)\b
+        (?:
+            [\s,]+
+            (?P<freq>
+                (?:cada|c/) \s* \d+ \s* (?:horas?|hs?)\b
+              | \d+ \s* - \s* \d+ \s* - \s* \d+
+              | (?:una|dos|tres|cuatro|\d+) \s+ (?:vez|veces) \s+ (?:al|por) \s+ d[ií]a
+              | \d+ \s* / \s* d[ií]a
+              | (?:una\s+vez\s+)?(?:al|por)\s+d[ií]a
+              | diari[oa]
+            )
+        )?",
+        num = NUMBER,
+    );
+    Regex::new(&pattern).expect("Invalid dose regex - this is a bug")
+});
+
+/// Capitalized words that precede doses but are not drug names
+const NOT_DRUGS: &[&str] = &[
+    "dosis", "total", "toma", "tomar", "administrar", "indicar", "indica", "peso", "volumen",
+];
+
+/// One prescription found in the text
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Dosage {
+    pub drug: String,
+    pub dose_value: f64,
+    pub dose_unit: String,
+    pub frequency: Option<String>,
+    /// Character offsets of the whole phrase, drug name included
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Dosage {
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("drug", &self.drug)?;
+        dict.set_item("dose_value", self.dose_value)?;
+        dict.set_item("dose_unit", &self.dose_unit)?;
+        dict.set_item("frequency", &self.frequency)?;
+        dict.set_item("span", (self.start, self.end))?;
+        Ok(dict)
+    }
+}
+
+
+/// Extract drug dosages with frequency
+///
+/// A dose is a number followed by a unit (mg, mcg, g, ml, UI, gotas,
+/// comprimidos), optionally followed by a Spanish frequency phrase
+/// ("cada 8 horas", "c/6h", "1-0-1", "dos veces al día", "1/día").
+/// The drug is the word right before the dose: either any entry of
+/// `drugs` (case-insensitive, may span several words) or, when no list
+/// is given, a capitalized word. Doses with no recognizable drug are
+/// skipped; use extract_entities for bare measurements.
+///
+/// Args:
+///     text: The input text
+///     drugs: Optional list of known drug names
+///
+/// Returns:
+///     List of dicts with drug, dose_value, dose_unit, frequency (or None)
+///     and span (start_char, end_char)
+#[pyfunction]
+#[pyo3(signature = (text, drugs=None))]
+pub fn extract_dosages<'py>(
+    py: Python<'py>,
+    text: &str,
+    drugs: Option<Vec<String>>,
+) -> PyResult<Vec<Bound<'py, PyDict>>> {
+    extract_dosages_internal(text, drugs.as_deref())
+        .iter()
+        .map(|dosage| dosage.to_dict(py))
+        .collect()
+}
+
+pub(crate) fn extract_dosages_internal(text: &str, drugs: Option<&[String]>) -> Vec<Dosage> {
+    let drugs: Option<Vec<String>> = drugs.map(|list| list.iter().map(|d| d.to_lowercase()).collect());
+    let mut cursor = CharCursor::new(text);
+    let mut dosages = Vec::new();
+
+    for caps in DOSE_RE.captures_iter(text) {
+        let (Some(whole), Some(value), Some(unit)) = (caps.get(0), caps.name("value"), caps.name("unit")) else {
+            continue;
+        };
+        let before = text[..whole.start()].trim_end();
+        let drug_start = match &drugs {
+            Some(list) => known_drug_start(before, list),
+            None => capitalized_word_start(before),
+        };
+        let Some(drug_start) = drug_start else {
+            continue;
+        };
+        let Some(dose_value) = parse_number(value.as_str()) else {
+            continue;
+        };
+
+        let start = cursor.char_offset(drug_start);
+        let end = cursor.char_offset(whole.end());
+        dosages.push(Dosage {
+            drug: before[drug_start..].to_string(),
+            dose_value,
+            dose_unit: unit.as_str().to_lowercase(),
+            frequency: caps.name("freq").map(|f| f.as_str().to_string()),
+            start,
+            end,
+        });
+    }
+
+    dosages
+}
+
+/// Extract medications as name / dose / unit / frequency strings
+///
+/// Same detection as extract_dosages without a drug list: the name is the
+/// capitalized word right before the dose. Frequencies are normalized so
+/// equivalent phrasings compare equal:
+/// - "cada 8 horas", "c/8h", "cada 8 hs" -> "cada 8 horas"
+/// - "1/día", "una vez al día", "diario" -> "1 vez al día"
+/// - "dos veces al día", "2/día" -> "2 veces al día"
+/// - "1-0-1" is kept as written
+///
+/// Args:
+///     text: The input text
+///
+/// Returns:
+///     List of dicts with "name", "dose" (decimal point: "0,5" -> "0.5"),
+///     "unit" (lowercase) and "frequency" ("" when none is written)
+#[pyfunction]
+pub fn extract_medications(text: &str) -> PyResult<Vec<HashMap<String, String>>> {
+    Ok(extract_dosages_internal(text, None).iter().map(medication_fields).collect())
+}
+
+fn medication_fields(dosage: &Dosage) -> HashMap<String, String> {
+    let frequency = dosage.frequency.as_deref().map(normalize_frequency).unwrap_or_default();
+    HashMap::from([
+        ("name".to_string(), dosage.drug.clone()),
+        ("dose".to_string(), dosage.dose_value.to_string()),
+        ("unit".to_string(), dosage.dose_unit.clone()),
+        ("frequency".to_string(), frequency),
+    ])
+}
+
+/// Canonical form of a frequency matched by DOSE_RE
+pub(crate) fn normalize_frequency(raw: &str) -> String {
+    let lower = raw.to_lowercase();
+    let compact: String = lower.chars().filter(|c| !c.is_whitespace()).collect();
+    if compact.matches('-').count() == 2 {
+        return compact;
+    }
+    let count = lower
+        .split(|c: char| !c.is_alphanumeric())
+        .find_map(|word| match word {
+            "una" => Some(1),
+            "dos" => Some(2),
+            "tres" => Some(3),
+            "cuatro" => Some(4),
+            // "8h" in "c/8h"
+            _ => word.trim_end_matches(char::is_alphabetic).parse::<u32>().ok(),
+        });
+    if lower.starts_with("cada") || lower.starts_with("c/") {
+        return match count {
+            Some(hours) => format!("cada {} horas", hours),
+            None => lower,
+        };
+    }
+    match count.unwrap_or(1) {
+        1 => "1 vez al día".to_string(),
+        times => format!("{} veces al día", times),
+    }
+}
+
+
+/// Byte start of the capitalized word `before` ends with, if any
+fn capitalized_word_start(before: &str) -> Option<usize> {
+    let start = before
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| c.is_alphabetic() || *c == '-')
+        .last()
+        .map(|(idx, _)| idx)?;
+    let word = &before[start..];
+    let is_capitalized = word.chars().next().is_some_and(char::is_uppercase);
+    let is_drug_like = word.chars().count() >= 3 && !NOT_DRUGS.contains(&word.to_lowercase().as_str());
+    (is_capitalized && is_drug_like).then_some(start)
+}
+
+/// Byte start of the longest known drug name `before` ends with, if any
+fn known_drug_start(before: &str, drugs: &[String]) -> Option<usize> {
+    let lower = before.to_lowercase();
+    drugs
+        .iter()
+        .filter(|drug| !drug.is_empty() && lower.ends_with(drug.as_str()))
+        .map(|drug| lower.len() - drug.len())
+        // Must start at a word boundary ("pirina" is not "aspirina")
+        .filter(|&idx| !lower[..idx].chars().next_back().is_some_and(char::is_alphanumeric))
+        .min()
+        // Lowercasing can change byte lengths; map back via char count
+        .and_then(|idx| {
+            let chars_before = lower[..idx].chars().count();
+            before.char_indices().nth(chars_before).map(|(i, _)| i)
+        })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capitalized_drug_with_frequency() {
+        let found = extract_dosages_internal("Se indica Amoxicilina 500 mg cada 8 horas por 7 días.", None);
+        assert_eq!(
+            found,
+            vec![Dosage {
+                drug: "Amoxicilina".to_string(),
+                dose_value: 500.0,
+                dose_unit: "mg".to_string(),
+                frequency: Some("cada 8 horas".to_string()),
+                start: 10,
+                end: 41,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_frequency_phrasings() {
+        let text = "Ibuprofeno 400mg c/6h. Enalapril 10 mg 1-0-1. Metformina 850 mg dos veces al día. \
+                    Omeprazol 20 mg 1/día. Atorvastatina 40 mg cada 24 hs. Clonazepam 0,5 mg";
+        let found = extract_dosages_internal(text, None);
+        let summary: Vec<(&str, f64, Option<&str>)> = found
+            .iter()
+            .map(|d| (d.drug.as_str(), d.dose_value, d.frequency.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("Ibuprofeno", 400.0, Some("c/6h")),
+                ("Enalapril", 10.0, Some("1-0-1")),
+                ("Metformina", 850.0, Some("dos veces al día")),
+                ("Omeprazol", 20.0, Some("1/día")),
+                ("Atorvastatina", 40.0, Some("cada 24 hs")),
+                ("Clonazepam", 0.5, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_drug_list_allows_lowercase_and_multiword_names() {
+        let drugs = vec!["ibuprofeno".to_string(), "ácido acetilsalicílico".to_string()];
+        let text = "toma ibuprofeno 400mg c/8h y Ácido acetilsalicílico 100 mg diario. Dosis 5 mg";
+        let found = extract_dosages_internal(text, Some(&drugs));
+        let names: Vec<&str> = found.iter().map(|d| d.drug.as_str()).collect();
+        assert_eq!(names, vec!["ibuprofeno", "Ácido acetilsalicílico"]);
+
+        let chars: Vec<char> = text.chars().collect();
+        let second: String = chars[found[1].start..found[1].end].iter().collect();
+        assert_eq!(second, "Ácido acetilsalicílico 100 mg diario");
+    }
+
+    #[test]
+    fn test_medications_with_normalized_frequency() {
+        let text = "Paracetamol 500mg cada 8 horas. Ibuprofeno 400 mg c/8h, Omeprazol 20 mg 1/día, \
+                    Enalapril 10 mg dos veces al día, Levotiroxina 75 mcg diario, Clonazepam 0,5 mg, \
+                    Furosemida 40 mg 1-0-0, Amoxicilina 875 mg cada 12 hs";
+        let found: Vec<(String, String, String, String)> = extract_medications(text)
+            .unwrap()
+            .into_iter()
+            .map(|m| (m["name"].clone(), m["dose"].clone(), m["unit"].clone(), m["frequency"].clone()))
+            .collect();
+        let expected = [
+            ("Paracetamol", "500", "mg", "cada 8 horas"),
+            ("Ibuprofeno", "400", "mg", "cada 8 horas"),
+            ("Omeprazol", "20", "mg", "1 vez al día"),
+            ("Enalapril", "10", "mg", "2 veces al día"),
+            ("Levotiroxina", "75", "mcg", "1 vez al día"),
+            ("Clonazepam", "0.5", "mg", ""),
+            ("Furosemida", "40", "mg", "1-0-0"),
+            ("Amoxicilina", "875", "mg", "cada 12 horas"),
+        ];
+        let expected: Vec<(String, String, String, String)> = expected
+            .iter()
+            .map(|(n, d, u, f)| (n.to_string(), d.to_string(), u.to_string(), f.to_string()))
+            .collect();
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn test_thousands_separators() {
+        let text = "Heparina 5.000 UI cada 12 horas, Penicilina 1.200.000 UI, Dipirona 2,5 g";
+        let values: Vec<f64> = extract_dosages_internal(text, None).iter().map(|d| d.dose_value).collect();
+        assert_eq!(values, vec![5000.0, 1_200_000.0, 2.5]);
+        let medications = extract_medications("Heparina 5.000 UI cada 12 horas").unwrap();
+        assert_eq!(medications[0]["dose"], "5000");
+    }
+
+    #[test]
+    fn test_doses_without_drug_are_skipped() {
+        assert!(extract_dosages_internal("Dosis 500 mg cada 8 horas", None).is_empty());
+        assert!(extract_dosages_internal("peso 70 kg, dieta 1500 kcal", None).is_empty());
+        let drugs = vec!["aspirina".to_string()];
+        assert!(extract_dosages_internal("Antiaspirina 100 mg", Some(&drugs)).is_empty());
+    }
+}
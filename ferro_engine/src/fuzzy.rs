@@ -0,0 +1,225 @@
+//! Edit distance and approximate term search
+//!
+//! Dictated notes misspell drug names ("amoxicilna", "ibuprofneo"). All
+//! distances count Unicode characters, not bytes, so "á" vs "a" is one
+//! edit, like any other substitution.
+
+use std::collections::HashMap;
+
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::CharCursor;
+
+/// (matched text, start_char, end_char, distance) of each approximate match
+pub(crate) type FuzzyMatches = Vec<(String, usize, usize, usize)>;
+
+/// Levenshtein distance between two strings
+///
+/// Insertions, deletions and substitutions of single characters each cost
+/// one. Comparison is case-sensitive.
+///
+/// Args:
+///     a: First string
+///     b: Second string
+///
+/// Returns:
+///     Number of edits turning a into b
+#[pyfunction]
+pub fn levenshtein(a: &str, b: &str) -> PyResult<usize> {
+    Ok(levenshtein_internal(a, b))
+}
+
+/// Levenshtein distance normalized to a 0.0-1.0 similarity
+///
+/// Args:
+///     a: First string
+///     b: Second string
+///
+/// Returns:
+///     1 - distance / length of the longer string (in characters); 1.0
+///     when both are empty
+#[pyfunction]
+pub fn similarity_ratio(a: &str, b: &str) -> PyResult<f64> {
+    let longest = a.chars().count().max(b.chars().count());
+    if longest == 0 {
+        return Ok(1.0);
+    }
+    Ok(1.0 - levenshtein_internal(a, b) as f64 / longest as f64)
+}
+
+/// Find approximate occurrences of a term
+///
+/// The text is scanned in windows of as many words as the term has
+/// ("amoxicilina" -> one word, "acido valproico" -> two) and each window is
+/// compared case-insensitively with the term. Windows within max_distance
+/// edits are reported; where matches overlap, the closer one is kept.
+///
+/// Args:
+///     text: The input text
+///     term: Term to look for
+///     max_distance: Maximum edit distance to report (default: 2)
+///
+/// Returns:
+///     List of (match, start_char, end_char, distance), in text order,
+///     with the match as written in the text
+#[pyfunction]
+#[pyo3(signature = (text, term, max_distance=2))]
+pub fn fuzzy_find(py: Python<'_>, text: &str, term: &str, max_distance: usize) -> PyResult<FuzzyMatches> {
+    Ok(crate::allow_threads_if_large(py, text.len(), || {
+        fuzzy_find_internal(text, &word_spans(text), term, max_distance)
+    }))
+}
+
+/// fuzzy_find for several terms, in parallel over the terms
+///
+/// Args:
+///     text: The input text
+///     terms: Terms to look for
+///     max_distance: Maximum edit distance to report (default: 2)
+///
+/// Returns:
+///     Dict of term -> fuzzy_find result
+#[pyfunction]
+#[pyo3(signature = (text, terms, max_distance=2))]
+pub fn fuzzy_match_terms(
+    py: Python<'_>,
+    text: &str,
+    terms: Vec<String>,
+    max_distance: usize,
+) -> PyResult<HashMap<String, FuzzyMatches>> {
+    Ok(py.allow_threads(|| {
+        let words = word_spans(text);
+        terms
+            .par_iter()
+            .map(|term| (term.clone(), fuzzy_find_internal(text, &words, term, max_distance)))
+            .collect()
+    }))
+}
+
+/// Byte spans of the text's words
+fn word_spans(text: &str) -> Vec<(usize, usize)> {
+    text.unicode_word_indices().map(|(start, word)| (start, start + word.len())).collect()
+}
+
+pub(crate) fn fuzzy_find_internal(text: &str, words: &[(usize, usize)], term: &str, max_distance: usize) -> FuzzyMatches {
+    let term_words = term.unicode_words().count();
+    if term_words == 0 || words.len() < term_words {
+        return Vec::new();
+    }
+    let term: Vec<char> = term.to_lowercase().chars().collect();
+
+    let mut found: Vec<(usize, usize, usize)> = words
+        .windows(term_words)
+        .filter_map(|window| {
+            let (start, end) = (window[0].0, window[term_words - 1].1);
+            let candidate: Vec<char> = text[start..end].to_lowercase().chars().collect();
+            if candidate.len().abs_diff(term.len()) > max_distance {
+                return None;
+            }
+            let distance = char_levenshtein(&candidate, &term);
+            (distance <= max_distance).then_some((distance, start, end))
+        })
+        .collect();
+
+    // Closest first, then drop anything overlapping an already kept match
+    found.sort_unstable();
+    let mut kept: Vec<(usize, usize, usize)> = Vec::with_capacity(found.len());
+    for candidate in found {
+        if kept.iter().all(|&(_, start, end)| candidate.2 <= start || candidate.1 >= end) {
+            kept.push(candidate);
+        }
+    }
+    kept.sort_unstable_by_key(|&(_, start, _)| start);
+
+    let mut cursor = CharCursor::new(text);
+    kept.into_iter()
+        .map(|(distance, start, end)| {
+            (text[start..end].to_string(), cursor.char_offset(start), cursor.char_offset(end), distance)
+        })
+        .collect()
+}
+
+pub(crate) fn levenshtein_internal(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    char_levenshtein(&a, &b)
+}
+
+/// Two-row dynamic programming over characters
+fn char_levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, &ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levenshtein_counts_characters() {
+        assert_eq!(levenshtein_internal("amoxicilina", "amoxicilna"), 1);
+        assert_eq!(levenshtein_internal("ibuprofeno", "ibuprofneo"), 2);
+        assert_eq!(levenshtein_internal("", "abc"), 3);
+        assert_eq!(levenshtein_internal("kitten", "sitting"), 3);
+        // One edit each, whatever their UTF-8 length
+        assert_eq!(levenshtein_internal("dolor", "dolór"), 1);
+        assert_eq!(levenshtein_internal("niño", "nino"), 1);
+        assert_eq!(levenshtein_internal("Niño", "niño"), 1);
+    }
+
+    #[test]
+    fn test_similarity_ratio() {
+        assert_eq!(similarity_ratio("", "").unwrap(), 1.0);
+        assert_eq!(similarity_ratio("abc", "").unwrap(), 0.0);
+        assert_eq!(similarity_ratio("amoxicilina", "amoxicilina").unwrap(), 1.0);
+        assert!((similarity_ratio("ibuprofeno", "ibuprofneo").unwrap() - 0.8).abs() < 1e-12);
+        assert!((similarity_ratio("acción", "accion").unwrap() - 5.0 / 6.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_fuzzy_find_spans_and_overlaps() {
+        let text = "Se indicó Amoxicilna 500 mg; alérgica a la amoxicilina. Ácido valproco 250 mg";
+        let chars: Vec<char> = text.chars().collect();
+        let words = word_spans(text);
+
+        let found = fuzzy_find_internal(text, &words, "amoxicilina", 2);
+        assert_eq!(
+            found,
+            vec![("Amoxicilna".to_string(), 10, 20, 1), ("amoxicilina".to_string(), 43, 54, 0)]
+        );
+        let two_words = fuzzy_find_internal(text, &words, "ácido valproico", 1);
+        assert_eq!(two_words.len(), 1);
+        let (matched, start, end, distance) = &two_words[0];
+        assert_eq!((matched.as_str(), *distance), ("Ácido valproco", 1));
+        assert_eq!(&chars[*start..*end].iter().collect::<String>(), matched);
+
+        assert!(fuzzy_find_internal(text, &words, "ibuprofeno", 2).is_empty());
+        assert!(fuzzy_find_internal(text, &words, "", 2).is_empty());
+    }
+
+    #[test]
+    fn test_fuzzy_match_terms_batch() {
+        let text = "Toma ibuprofneo y paracetamol, suspendió enalaprill.";
+        crate::with_py(|py| {
+            let terms = ["ibuprofeno", "paracetamol", "enalapril", "metformina"].map(String::from).to_vec();
+            let found = fuzzy_match_terms(py, text, terms, 2).unwrap();
+            assert_eq!(found["ibuprofeno"], vec![("ibuprofneo".to_string(), 5, 15, 2)]);
+            assert_eq!(found["paracetamol"][0].3, 0);
+            assert_eq!(found["enalapril"][0].0, "enalaprill");
+            assert!(found["metformina"].is_empty());
+            assert_eq!(fuzzy_find(py, text, "enalapril", 0).unwrap(), vec![]);
+        });
+    }
+}
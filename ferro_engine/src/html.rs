@@ -0,0 +1,222 @@
+//! Conservative HTML tag scanner for clean_medical_text
+//!
+//! Clinical notes are full of bare comparisons ("FC <60 lpm y TA >90") that
+//! a `<[^>]+>` regex swallows whole. Here `<` only counts as markup when it
+//! opens something tag-shaped:
+//! - `<name>`, `</name>` or `<name/>` where name starts with an ASCII letter
+//! - `<name attr="...">` when name is a known HTML tag or namespaced (`o:p`)
+//!   and the attributes are well formed and end on the same line
+//! - comments (`<!-- ... -->`), doctypes and processing instructions
+//!
+//! Anything else, including unterminated tags, is left as text.
+
+/// Tags whose attribute-carrying form is recognized
+const KNOWN_TAGS: &[&str] = &[
+    "a", "abbr", "article", "b", "blockquote", "body", "br", "button", "caption", "center", "code",
+    "col", "colgroup", "dd", "div", "dl", "dt", "em", "font", "footer", "form", "h1", "h2", "h3",
+    "h4", "h5", "h6", "head", "header", "hr", "html", "i", "img", "input", "label", "li", "link",
+    "meta", "nav", "ol", "option", "p", "pre", "s", "script", "section", "select", "small", "span",
+    "strong", "style", "sub", "sup", "table", "tbody", "td", "textarea", "tfoot", "th", "thead",
+    "title", "tr", "u", "ul",
+];
+
+/// Longest attribute list, in bytes, looked through for its closing `>`
+const MAX_ATTRIBUTES_LEN: usize = 1024;
+
+/// Tags replaced by a line break instead of nothing
+const BREAK_TAGS: &[&str] = &["br", "p"];
+
+/// A run of plain text or the line break left by a `<br>`/`<p>` tag
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Piece<'a> {
+    Text(&'a str),
+    LineBreak,
+}
+
+/// Iterator over the text pieces of `text` with tags removed
+pub(crate) struct Pieces<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Pieces<'a> {
+    pub(crate) fn new(text: &'a str) -> Self {
+        Self { text, pos: 0 }
+    }
+}
+
+impl<'a> Iterator for Pieces<'a> {
+    type Item = Piece<'a>;
+
+    fn next(&mut self) -> Option<Piece<'a>> {
+        // Consume tags sitting at the cursor
+        while let Some((len, is_break)) = tag_at(&self.text[self.pos..]) {
+            self.pos += len;
+            if is_break {
+                return Some(Piece::LineBreak);
+            }
+        }
+        if self.pos >= self.text.len() {
+            return None;
+        }
+
+        // Plain text runs up to the next real tag
+        let start = self.pos;
+        // Skip the first char: it is either text or a `<` that opens no tag
+        let mut search = start + self.text[start..].chars().next().map_or(1, char::len_utf8);
+        let end = loop {
+            match self.text[search..].find('<') {
+                Some(idx) if tag_at(&self.text[search + idx..]).is_some() => break search + idx,
+                Some(idx) => search += idx + 1,
+                None => break self.text.len(),
+            }
+        };
+        self.pos = end;
+        Some(Piece::Text(&self.text[start..end]))
+    }
+}
+
+/// Byte length of the tag `s` starts with and whether it is a line break
+fn tag_at(s: &str) -> Option<(usize, bool)> {
+    let bytes = s.as_bytes();
+    if bytes.first() != Some(&b'<') {
+        return None;
+    }
+    if let Some(comment) = s.strip_prefix("<!--") {
+        return comment.find("-->").map(|idx| (4 + idx + 3, false));
+    }
+    if matches!(bytes.get(1), Some(b'!' | b'?')) {
+        return match bytes.get(2) {
+            Some(b) if b.is_ascii_alphabetic() => s.find('>').map(|idx| (idx + 1, false)),
+            _ => None,
+        };
+    }
+
+    let name_start = if bytes.get(1) == Some(&b'/') { 2 } else { 1 };
+    if !bytes.get(name_start).is_some_and(u8::is_ascii_alphabetic) {
+        return None;
+    }
+    let name_end = bytes[name_start..]
+        .iter()
+        .position(|b| !(b.is_ascii_alphanumeric() || matches!(b, b':' | b'_' | b'-')))
+        .map_or(bytes.len(), |idx| name_start + idx);
+    let name = s[name_start..name_end].to_ascii_lowercase();
+    let is_break = BREAK_TAGS.contains(&name.as_str());
+
+    let len = match bytes.get(name_end)? {
+        b'>' => name_end + 1,
+        b'/' if bytes.get(name_end + 1) == Some(&b'>') => name_end + 2,
+        b if b.is_ascii_whitespace() && (KNOWN_TAGS.contains(&name.as_str()) || name.contains(':')) => {
+            attributes_end(&bytes[name_end..])? + name_end
+        }
+        _ => return None,
+    };
+    Some((len, is_break))
+}
+
+/// Length up to and including the `>` closing an attribute list
+///
+/// The list must hold only `name`, `name=value` or `name="value"` pairs,
+/// stay on one line and end within MAX_ATTRIBUTES_LEN bytes, so an
+/// unclosed "<a los 5 años" never reaches a `>` further on.
+fn attributes_end(bytes: &[u8]) -> Option<usize> {
+    let bytes = &bytes[..bytes.len().min(MAX_ATTRIBUTES_LEN)];
+    let is_space = |b: &u8| matches!(b, b' ' | b'\t');
+    let skip = |idx: usize, pred: &dyn Fn(&u8) -> bool| idx + bytes[idx..].iter().take_while(|b| pred(b)).count();
+    let mut idx = 0;
+    loop {
+        let before_space = idx;
+        idx = skip(idx, &is_space);
+        match bytes.get(idx)? {
+            b'>' => return Some(idx + 1),
+            b'/' if bytes.get(idx + 1) == Some(&b'>') => return Some(idx + 2),
+            // Attributes are separated by whitespace
+            b if b.is_ascii_alphabetic() && idx > before_space => {}
+            _ => return None,
+        }
+        idx = skip(idx, &|b: &u8| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b':' | b'.'));
+        let after_name = skip(idx, &is_space);
+        if bytes.get(after_name) != Some(&b'=') {
+            continue;
+        }
+        idx = skip(after_name + 1, &is_space);
+        idx = match bytes.get(idx)? {
+            &quote @ (b'"' | b'\'') => {
+                let len = bytes[idx + 1..].iter().position(|&b| b == quote || b == b'\n')?;
+                if bytes[idx + 1 + len] != quote {
+                    return None;
+                }
+                idx + len + 2
+            }
+            _ => {
+                let end = skip(idx, &|b: &u8| !(b.is_ascii_whitespace() || matches!(b, b'>' | b'<' | b'"' | b'\'' | b'=')));
+                if end == idx {
+                    return None;
+                }
+                end
+            }
+        };
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strip(text: &str) -> String {
+        Pieces::new(text)
+            .map(|piece| match piece {
+                Piece::Text(t) => t,
+                Piece::LineBreak => "\n",
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_keeps_clinical_comparisons() {
+        let cases = [
+            ("FC <60 lpm y TA >90", "FC <60 lpm y TA >90"),
+            ("<b>FC</b> <60 lpm", "FC <60 lpm"),
+            ("Glucemia < 70 o > 180 mg/dl", "Glucemia < 70 o > 180 mg/dl"),
+            ("Plaquetas <150.000, Hb <10 y >8", "Plaquetas <150.000, Hb <10 y >8"),
+            ("edad <a los 5 años", "edad <a los 5 años"),
+            ("unterminated <span class='x'", "unterminated <span class='x'"),
+            ("edad <a los 5 años, TA >90", "edad <a los 5 años, TA >90"),
+            ("Hb <b 10 y TA >90", "Hb <b 10 y TA >90"),
+            ("TA <b alta\nFC >90", "TA <b alta\nFC >90"),
+            ("<span class='x\n'>dato", "<span class='x\n'>dato"),
+            ("x<y y z>w", "x<y y z>w"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(strip(input), expected, "input: {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_removes_real_markup() {
+        let cases = [
+            ("<span style=\"color: red\">TA >140</span>", "TA >140"),
+            ("<td class='a>b'>Hb</td>", "Hb"),
+            ("<!-- nota <interna> -->Alta", "Alta"),
+            ("<!DOCTYPE html><html><body>ok</body></html>", "ok"),
+            ("<o:p></o:p>Word<o:p class=x>", "Word"),
+            ("<custom-tag>dato</custom-tag>", "dato"),
+            ("<img src=\"x.png\"/>", ""),
+            ("<input type=checkbox checked disabled>sí", "sí"),
+            ("<td colspan = \"2\" data-x=1>Hb</td>", "Hb"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(strip(input), expected, "input: {:?}", input);
+        }
+        // An attribute list too long to be markup is left alone
+        let long = format!("<span {}>x", "a ".repeat(MAX_ATTRIBUTES_LEN));
+        assert_eq!(strip(&long), long);
+    }
+
+    #[test]
+    fn test_break_tags_become_newlines() {
+        assert_eq!(strip("Línea 1<br>Línea 2<BR/>Línea 3<br />fin"), "Línea 1\nLínea 2\nLínea 3\nfin");
+        assert_eq!(strip("<p>Uno</p><p class=\"x\">Dos</p>"), "\nUno\n\nDos\n");
+    }
+}
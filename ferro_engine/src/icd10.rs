@@ -0,0 +1,157 @@
+//! ICD-10 (CIE-10) codes with context
+//!
+//! Discharge summaries hold code-shaped tokens that are vitamins ("B12"),
+//! percentiles ("P50") and temperatures ("T38.5°"). A code is only
+//! reported when its category exists in ICD-10 and a code without a
+//! subcategory has code-like context (parentheses, or "CIE-10", "Dx",
+//! "diagnóstico" earlier on the line). extract_icd10 and the "icd10"
+//! category of extract_entities apply the same rules.
+
+use once_cell::sync::Lazy;
+use pyo3::prelude::*;
+use regex::Regex;
+
+use crate::{CharCursor, Spans};
+
+/// Highest category number used under each chapter letter, A through Z
+///
+/// U is handled apart: only U00-U49 (provisional) and U82-U85 exist.
+const MAX_CATEGORY: [u8; 26] = [
+    99, 99, 97, 89, 90, 99, 99, 95, 99, 99, 93, 99, 99, // A-M
+    99, 99, 96, 99, 99, 99, 98, 85, 99, 99, 99, 98, 99, // N-Z
+];
+
+/// How far back, in characters on the same line, context may appear
+const CONTEXT_CHARS: usize = 80;
+
+/// Valid-looking codes that are far more often something else
+const NOT_CODES: &[&str] = &["B12"];
+
+static CONTEXT_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(?:cie|icd)[\s-]?10\b|\bdx\b|\bdiagn[oó]sticos?\b|\bc[oó]digos?\b")
+        .expect("Invalid ICD-10 context regex - this is a bug")
+});
+
+/// Extract ICD-10 / CIE-10 codes with their positions
+///
+/// A code is a chapter letter, two digits and optionally a dot plus up to
+/// four characters ("I10", "J18.9", "E11.65"). Codes whose category does
+/// not exist ("D95", "V00") are dropped. A code without the dotted part is
+/// only reported inside parentheses or brackets ("(I10)") or after "CIE-10",
+/// "ICD-10", "Dx" or "diagnóstico" on the same line, so "vitamina B12" and
+/// "peso en P50" are not codes. A dotted code followed by a degree sign
+/// ("T38.5°") is a temperature.
+///
+/// Args:
+///     text: The input text
+///
+/// Returns:
+///     List of (code, start_char, end_char), in text order
+#[pyfunction]
+pub fn extract_icd10(py: Python<'_>, text: &str) -> PyResult<Spans> {
+    Ok(crate::allow_threads_if_large(py, text.len(), || extract_icd10_internal(text)))
+}
+
+pub(crate) fn extract_icd10_internal(text: &str) -> Spans {
+    let mut cursor = CharCursor::new(text);
+    crate::ICD10_RE
+        .find_iter(text)
+        .filter(|m| is_valid_code(m.as_str()) && reads_as_code(text, m.start(), m.end()))
+        .map(|m| (m.as_str().to_string(), cursor.char_offset(m.start()), cursor.char_offset(m.end())))
+        .collect()
+}
+
+/// Whether a code-shaped token names an existing category and is not
+/// one of NOT_CODES
+pub(crate) fn is_valid_code(code: &str) -> bool {
+    is_valid_category(code) && !NOT_CODES.contains(&code)
+}
+
+/// Whether the text around the code at `start..end` reads as a code
+///
+/// A dotted code must not be followed by a degree sign; a code without
+/// the dotted part must be in brackets or have context on its line.
+pub(crate) fn reads_as_code(text: &str, start: usize, end: usize) -> bool {
+    if text[start..end].contains('.') {
+        !text[end..].trim_start().starts_with(['°', 'º'])
+    } else {
+        in_brackets(text, start, end) || has_context(&text[..start])
+    }
+}
+
+/// Whether the letter and two digits name an existing category
+fn is_valid_category(code: &str) -> bool {
+    let bytes = code.as_bytes();
+    let (Some(&letter), Some(number)) = (bytes.first(), code.get(1..3).and_then(|n| n.parse::<u8>().ok())) else {
+        return false;
+    };
+    if !letter.is_ascii_uppercase() {
+        return false;
+    }
+    match letter {
+        b'U' => number <= 49 || (82..=85).contains(&number),
+        b'V' => number >= 1,
+        _ => number <= MAX_CATEGORY[usize::from(letter - b'A')],
+    }
+}
+
+fn in_brackets(text: &str, start: usize, end: usize) -> bool {
+    let before = text[..start].trim_end().chars().next_back();
+    let after = text[end..].trim_start().chars().next();
+    matches!((before, after), (Some('('), Some(')')) | (Some('['), Some(']')))
+}
+
+/// A context keyword earlier on the same line, within CONTEXT_CHARS
+fn has_context(before: &str) -> bool {
+    let line = before.rsplit('\n').next().unwrap_or(before);
+    let skip = line.chars().count().saturating_sub(CONTEXT_CHARS);
+    let window = line.char_indices().nth(skip).map_or("", |(index, _)| &line[index..]);
+    CONTEXT_RE.is_match(window)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codes(text: &str) -> Vec<String> {
+        extract_icd10_internal(text).into_iter().map(|(code, _, _)| code).collect()
+    }
+
+    #[test]
+    fn test_dotted_and_context_codes() {
+        let text = "Egreso con neumonía J18.9 y DBT2 E11.65. HTA (I10). CIE-10: N39, K35.8\nDx: I21";
+        assert_eq!(codes(text), vec!["J18.9", "E11.65", "I10", "N39", "K35.8", "I21"]);
+        let chars: Vec<char> = text.chars().collect();
+        for (code, start, end) in extract_icd10_internal(text) {
+            assert_eq!(chars[start..end].iter().collect::<String>(), code);
+        }
+        assert_eq!(codes("COVID-19 [U07.1]; diagnóstico principal J45"), vec!["U07.1", "J45"]);
+    }
+
+    #[test]
+    fn test_false_positive_traps() {
+        let text = "Vitamina B12 500 µg y vitamina D3, peso en P50, T38.5° axilar, T 37.8 °C, \
+                    lote A1234, Hb A1c 6,5, CD4 350, G6PD normal, K 4.1 mEq/L";
+        assert!(codes(text).is_empty(), "{:?}", codes(text));
+        // Context on another line does not count
+        assert!(codes("Dx: neumonía\nEn P50 para la edad").is_empty());
+        // Categories that do not exist, even dotted or in context
+        assert!(codes("Dx: D95.1, V00.1, U50, H99 (B12)").is_empty());
+        assert_eq!(codes("Dx: SIDA (B20), hepatitis B18.1"), vec!["B20", "B18.1"]);
+    }
+
+    #[test]
+    fn test_category_ranges() {
+        for valid in ["A00", "C97", "D89", "E90", "H95", "K93", "P96", "T98", "U07", "U85", "V01", "Y98", "Z99"] {
+            assert!(is_valid_category(valid), "{}", valid);
+        }
+        for invalid in ["C98", "D90", "E91", "H96", "K94", "P97", "T99", "U50", "U81", "V00", "Y99", "a10"] {
+            assert!(!is_valid_category(invalid), "{}", invalid);
+        }
+
+        crate::with_py(|py| {
+            assert_eq!(extract_icd10(py, "Dx: ácido (E87.2)").unwrap(), vec![("E87.2".to_string(), 11, 16)]);
+        });
+    }
+}
@@ -0,0 +1,134 @@
+//! Key-value lines of semi-structured reports
+//!
+//! Lab reports and admission forms write one field per line ("Peso: 82 kg",
+//! "Grupo sanguíneo = 0+", "Obra social - OSDE"). A value may wrap onto
+//! the following indented lines, which are joined to it with a space.
+
+use once_cell::sync::Lazy;
+use pyo3::prelude::*;
+use regex::Regex;
+
+/// Keys longer than this many words are prose, not field names
+const MAX_KEY_WORDS: usize = 6;
+
+/// (key, value, line_number) of each pair
+pub(crate) type KeyValues = Vec<(String, String, usize)>;
+
+static KEY_VALUE_RE: Lazy<Regex> = Lazy::new(|| {
+    // The key starts with a letter and ends at the first separator: ":",
+    // "=" or a dash with spaces around it ("Obra social - OSDE")
+    Regex::new(r"^(?P<key>\p{L}[^:=\n]{0,59}?)\s*(?::|=|\s[-–]\s)\s*(?P<value>.*?)\s*$")
+        .expect("Invalid key-value regex - this is a bug")
+});
+
+/// Extract `key: value` pairs, one per line
+///
+/// "key = value" and "key - value" work too. A value continues on the
+/// following indented lines unless they hold a pair of their own, and a
+/// key with no value on its line takes them as its value. The same key
+/// may appear several times; every occurrence is returned.
+///
+/// Args:
+///     text: The report
+///
+/// Returns:
+///     Ordered list of (key, value, line_number), with 1-based line numbers
+///     of the key's line. Keys without any value are skipped.
+#[pyfunction]
+pub fn extract_key_values(py: Python<'_>, text: &str) -> PyResult<KeyValues> {
+    Ok(crate::allow_threads_if_large(py, text.len(), || extract_key_values_internal(text)))
+}
+
+pub(crate) fn extract_key_values_internal(text: &str) -> KeyValues {
+    let mut pairs: KeyValues = Vec::new();
+    // Whether the last pair may still take continuation lines
+    let mut open = false;
+    for (index, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            open = false;
+            continue;
+        }
+        let indented = line.starts_with([' ', '\t']);
+        if let Some((key, value)) = key_value(line.trim()) {
+            pairs.push((key.to_string(), value.to_string(), index + 1));
+            open = true;
+        } else if indented && open {
+            if let Some((_, value, _)) = pairs.last_mut() {
+                if !value.is_empty() {
+                    value.push(' ');
+                }
+                value.push_str(line.trim());
+            }
+        } else {
+            open = false;
+        }
+    }
+    pairs.retain(|(_, value, _)| !value.is_empty());
+    pairs
+}
+
+/// The key and value of a line, if it is a pair
+fn key_value(line: &str) -> Option<(&str, &str)> {
+    let caps = KEY_VALUE_RE.captures(line)?;
+    let key = caps.name("key")?.as_str().trim_end();
+    let value = caps.name("value")?.as_str();
+    // "https://..." is a URL, not the key "https"
+    if value.starts_with("//") || key.split_whitespace().count() > MAX_KEY_WORDS {
+        return None;
+    }
+    Some((key, value))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair(key: &str, value: &str, line: usize) -> (String, String, usize) {
+        (key.to_string(), value.to_string(), line)
+    }
+
+    #[test]
+    fn test_separators_and_accents() {
+        let text = "Peso: 82 kg\nGrupo sanguíneo: 0+\nObra social - OSDE\nTalla = 1,75 m\n\
+                    Hora de ingreso: 14:30\nÍndice de masa corporal: 26,8";
+        assert_eq!(
+            extract_key_values_internal(text),
+            vec![
+                pair("Peso", "82 kg", 1),
+                pair("Grupo sanguíneo", "0+", 2),
+                pair("Obra social", "OSDE", 3),
+                pair("Talla", "1,75 m", 4),
+                pair("Hora de ingreso", "14:30", 5),
+                pair("Índice de masa corporal", "26,8", 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_continuations_and_repeats() {
+        let text = "Antecedentes:\n  HTA en tratamiento\n  con enalapril\nDiagnóstico: neumonía\n    bilateral\n\
+                    Texto libre sin pares\n  línea indentada suelta\nGlucemia: 110 mg/dL\nGlucemia: 95 mg/dL\n\
+                    Vacío:\n\nVer https://example.org";
+        assert_eq!(
+            extract_key_values_internal(text),
+            vec![
+                pair("Antecedentes", "HTA en tratamiento con enalapril", 1),
+                pair("Diagnóstico", "neumonía bilateral", 4),
+                pair("Glucemia", "110 mg/dL", 8),
+                pair("Glucemia", "95 mg/dL", 9),
+            ]
+        );
+        // An indented pair is its own entry, not a continuation
+        assert_eq!(
+            extract_key_values_internal("Signos vitales:\n  TA: 120/80\n  FC: 72"),
+            vec![pair("TA", "120/80", 2), pair("FC", "72", 3)]
+        );
+        // Prose with a colon late in the sentence is not a pair
+        assert!(extract_key_values_internal("El paciente refiere que desde hace varios días tiene: tos").is_empty());
+
+        crate::with_py(|py| {
+            assert_eq!(extract_key_values(py, "Peso: 82 kg").unwrap(), vec![pair("Peso", "82 kg", 1)]);
+        });
+    }
+}
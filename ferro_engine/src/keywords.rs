@@ -0,0 +1,226 @@
+//! Salient terms of a document
+//!
+//! Two scorers share one token pipeline (tokenize with lowercasing, Spanish
+//! stopwords and tokens without letters dropped):
+//! - TF-IDF over a corpus, where each text is a document
+//! - RAKE for a single text: candidate phrases are runs of content words
+//!   between stopwords and punctuation, scored by word degree / frequency
+//!
+//! Results are sorted by descending score, ties broken alphabetically, so
+//! the output is deterministic.
+
+use std::collections::HashMap;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+
+use crate::stopwords::StopwordFilter;
+
+/// Extract the top TF-IDF terms of each text
+///
+/// TF is the term's share of the document's content words; IDF is the
+/// smoothed ln((1 + N) / (1 + df)) + 1, so a term present in every
+/// document still scores above zero. Documents and scoring run in
+/// parallel without the GIL.
+///
+/// Args:
+///     texts: The corpus, one document per text
+///     top_k: Keywords per document (default: 10). Documents with fewer
+///            distinct terms return all of them.
+///
+/// Returns:
+///     One list of (term, score) per text, highest score first
+///
+/// Raises:
+///     ValueError: If top_k is 0
+#[pyfunction]
+#[pyo3(signature = (texts, top_k=10))]
+pub fn extract_keywords(py: Python<'_>, texts: Vec<String>, top_k: usize) -> PyResult<Vec<Vec<(String, f64)>>> {
+    check_top_k(top_k)?;
+    let filter = StopwordFilter::new("es", None)?;
+    Ok(py.allow_threads(|| extract_keywords_internal(&texts, top_k, &filter)))
+}
+
+/// Extract the top RAKE phrases of a single text
+///
+/// A phrase's score is the sum of its words' degree / frequency, where a
+/// word's degree counts the words it shares phrases with (itself
+/// included). Long phrases of recurring words rank first; repeated phrases
+/// are reported once.
+///
+/// Args:
+///     text: The input text
+///     top_k: Phrases to return (default: 10); fewer if the text has fewer
+///
+/// Returns:
+///     List of (phrase, score), highest score first
+///
+/// Raises:
+///     ValueError: If top_k is 0
+#[pyfunction]
+#[pyo3(signature = (text, top_k=10))]
+pub fn extract_keywords_single(py: Python<'_>, text: &str, top_k: usize) -> PyResult<Vec<(String, f64)>> {
+    check_top_k(top_k)?;
+    let filter = StopwordFilter::new("es", None)?;
+    Ok(crate::allow_threads_if_large(py, text.len(), || rake_internal(text, top_k, &filter)))
+}
+
+fn check_top_k(top_k: usize) -> PyResult<()> {
+    if top_k == 0 {
+        return Err(PyValueError::new_err("top_k must be greater than 0"));
+    }
+    Ok(())
+}
+
+/// A content word: not a stopword and containing a letter
+fn is_content(token: &str, filter: &StopwordFilter) -> bool {
+    token.chars().any(char::is_alphabetic) && !filter.contains(token)
+}
+
+pub(crate) fn extract_keywords_internal(texts: &[String], top_k: usize, filter: &StopwordFilter) -> Vec<Vec<(String, f64)>> {
+    let counts: Vec<HashMap<String, usize>> = texts
+        .par_iter()
+        .map(|text| {
+            let mut counts: HashMap<String, usize> = HashMap::new();
+            for token in crate::word_tokens(text, true, false) {
+                if is_content(&token, filter) {
+                    *counts.entry(token).or_default() += 1;
+                }
+            }
+            counts
+        })
+        .collect();
+
+    let document_frequency: HashMap<&str, usize> = counts
+        .par_iter()
+        .fold(HashMap::new, |mut df: HashMap<&str, usize>, counts| {
+            for term in counts.keys() {
+                *df.entry(term.as_str()).or_default() += 1;
+            }
+            df
+        })
+        .reduce(HashMap::new, |mut a, b| {
+            for (term, n) in b {
+                *a.entry(term).or_default() += n;
+            }
+            a
+        });
+
+    let documents = texts.len() as f64;
+    counts
+        .par_iter()
+        .map(|counts| {
+            let total: usize = counts.values().sum();
+            let scored = counts.iter().map(|(term, &count)| {
+                let df = document_frequency.get(term.as_str()).copied().unwrap_or(0) as f64;
+                let idf = ((1.0 + documents) / (1.0 + df)).ln() + 1.0;
+                (term.clone(), count as f64 / total as f64 * idf)
+            });
+            top(scored.collect(), top_k)
+        })
+        .collect()
+}
+
+pub(crate) fn rake_internal(text: &str, top_k: usize, filter: &StopwordFilter) -> Vec<(String, f64)> {
+    // Runs of content words; stopwords, numbers and punctuation end a phrase
+    let mut phrases: Vec<Vec<String>> = Vec::new();
+    let mut current: Vec<String> = Vec::new();
+    for token in crate::word_tokens(text, true, true) {
+        if is_content(&token, filter) {
+            current.push(token);
+        } else if !current.is_empty() {
+            phrases.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        phrases.push(current);
+    }
+
+    let mut frequency: HashMap<&str, usize> = HashMap::new();
+    let mut degree: HashMap<&str, usize> = HashMap::new();
+    for phrase in &phrases {
+        for word in phrase {
+            *frequency.entry(word.as_str()).or_default() += 1;
+            *degree.entry(word.as_str()).or_default() += phrase.len();
+        }
+    }
+
+    let mut scored: HashMap<String, f64> = HashMap::new();
+    for phrase in &phrases {
+        let score = phrase.iter().map(|word| degree[word.as_str()] as f64 / frequency[word.as_str()] as f64).sum();
+        scored.insert(phrase.join(" "), score);
+    }
+    top(scored.into_iter().collect(), top_k)
+}
+
+/// The k best entries, by descending score then ascending term
+fn top(mut scored: Vec<(String, f64)>, top_k: usize) -> Vec<(String, f64)> {
+    scored.sort_unstable_by(|(a_term, a), (b_term, b)| b.total_cmp(a).then_with(|| a_term.cmp(b_term)));
+    scored.truncate(top_k);
+    scored
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spanish() -> StopwordFilter {
+        StopwordFilter::new("es", None).unwrap()
+    }
+
+    fn terms(scored: &[(String, f64)]) -> Vec<&str> {
+        scored.iter().map(|(term, _)| term.as_str()).collect()
+    }
+
+    #[test]
+    fn test_tfidf_prefers_distinctive_terms() {
+        let texts: Vec<String> = [
+            "Paciente con neumonía bilateral. Se inicia antibiótico por la neumonía.",
+            "Paciente con fractura de cadera. Se indica antibiótico profiláctico.",
+            "Control de paciente diabético, sin cambios en antibiótico.",
+        ]
+        .map(String::from)
+        .to_vec();
+        let keywords = extract_keywords_internal(&texts, 3, &spanish());
+        assert_eq!(keywords.len(), 3);
+        assert_eq!(keywords[0][0].0, "neumonía");
+        // "antibiótico" is in every document: lowest IDF, never the top term
+        assert!(keywords.iter().all(|doc| doc[0].0 != "antibiótico"));
+        // Stopwords ("con", "se", "de", "paciente") never appear
+        for doc in &keywords {
+            assert!(doc.iter().all(|(term, _)| !spanish().contains(term)));
+            assert!(doc.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+        }
+    }
+
+    #[test]
+    fn test_ties_and_short_texts() {
+        let texts: Vec<String> = ["fiebre tos disnea", "", "el de la"].map(String::from).to_vec();
+        let keywords = extract_keywords_internal(&texts, 10, &spanish());
+        // Equal scores: alphabetical order; fewer terms than top_k: all of them
+        assert_eq!(terms(&keywords[0]), vec!["disnea", "fiebre", "tos"]);
+        assert!(keywords[1].is_empty() && keywords[2].is_empty());
+        assert_eq!(extract_keywords_internal(&texts, 1, &spanish())[0].len(), 1);
+    }
+
+    #[test]
+    fn test_rake_phrases() {
+        let text = "Dolor torácico opresivo irradiado a brazo izquierdo. El dolor torácico cede con \
+                    nitroglicerina sublingual; se descarta dolor torácico de origen isquémico.";
+        let phrases = rake_internal(text, 3, &spanish());
+        // "dolor torácico" recurs, so phrases containing it rank first
+        assert_eq!(terms(&phrases), vec!["dolor torácico opresivo irradiado", "descarta dolor torácico", "dolor torácico cede"]);
+        let all = rake_internal(text, 10, &spanish());
+        assert!(terms(&all).contains(&"nitroglicerina sublingual"));
+        assert_eq!(all.iter().filter(|(phrase, _)| phrase == "dolor torácico").count(), 0);
+        assert!(rake_internal("", 5, &spanish()).is_empty());
+
+        crate::with_py(|py| {
+            assert!(extract_keywords_single(py, text, 0).is_err());
+            assert!(extract_keywords(py, vec![], 0).is_err());
+            assert!(extract_keywords(py, vec![], 5).unwrap().is_empty());
+        });
+    }
+}
@@ -0,0 +1,288 @@
+//! Lab results with reference ranges
+//!
+//! Lab reports list one analyte per line or per comma: "Glucosa 140 mg/dL
+//! (70-110)", "Hemoglobina: 11,2 g/dL [12 - 16]", "TSH 2,5 µUI/mL (VR:
+//! 0,4 a 4)". An analyte starts a line or follows a separator (",", ";",
+//! ":" or a sentence-ending "."), is at most three words, and needs a unit
+//! or a reference range after its value, so "FC 80" or "en 2 días" are
+//! not lab values. Values reported beyond the assay's limits keep their
+//! comparator ("Troponina <0,01 ng/mL", "Glucosa >250 mg/dL").
+
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use regex::Regex;
+
+use crate::measurements::{self, parse_number, NUMBER};
+use crate::CharCursor;
+
+/// Units found on lab reports on top of measurements::DEFAULT_UNITS
+const LAB_UNITS: &[&str] = &[
+    "U/L", "mU/L", "mg/L", "g/L", "µmol/L", "umol/L", "pmol/L", "ng/dL", "µg/dL", "pg/mL",
+    "µUI/mL", "uUI/mL", "mUI/mL", "mOsm/kg", "fL", "pg", "seg", "mm/h", "mg/24h",
+    "/mm3", "/mm³", "/µL", "/uL", "x10^3/µL", "10^3/µL",
+];
+
+static LAB_RE: Lazy<Regex> = Lazy::new(|| {
+    let units = measurements::unit_alternation(measurements::DEFAULT_UNITS.iter().chain(LAB_UNITS).copied());
+    let pattern = format!(
+        r"(?mx)
+        (?:^|[,;:]|\.\s)[\ \t]*
+        (?P<analyte> [^\W\d_]\w* (?:[\ \t]+[^\W\d_]\w*){{0,2}} )
+        [\ \t]* :? [\ \t]*
+        (?P<comparator> <=|>=|<|>|≤|≥ )? [\ \t]*
+        (?P<value> {num} )
+        (?: [\ \t]* (?P<unit> (?i:{units}) ) )?
+        (?:
+            [\ \t]* [(\[] [\ \t]*
+            (?: (?i:VR|V\.R\.|ref\.?|rango|valores?\ de\ referencia) [\ \t]* :? [\ \t]* )?
+            (?P<low> {num} ) [\ \t]* (?:-|–|a) [\ \t]* (?P<high> {num} )
+            (?: [\ \t]* (?i:{units}) )?
+            [\ \t]* [)\]]
+        )?",
+        num = NUMBER,
+        units = units,
+    );
+    Regex::new(&pattern).expect("Invalid lab value regex - this is a bug")
+});
+
+/// One lab result
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct LabValue {
+    pub analyte: String,
+    pub value: f64,
+    /// "<", ">", "<=" or ">=" for values beyond the assay's limits
+    pub comparator: Option<String>,
+    /// Normalized unit, "" when none is written
+    pub unit: String,
+    pub range: Option<(f64, f64)>,
+    pub raw: String,
+    /// Character offsets from the analyte to the end of the result
+    pub start: usize,
+    pub end: usize,
+}
+
+impl LabValue {
+    /// "high", "low" or "normal" against the reference range, "" without one
+    ///
+    /// "<0,01" is low if the limit is at or below ref_low and normal if it
+    /// is within the range; ">250" mirrors it. Otherwise it is "".
+    pub(crate) fn flag(&self) -> &'static str {
+        let Some((low, high)) = self.range else { return "" };
+        match self.comparator.as_deref() {
+            Some("<" | "<=" | "≤") if self.value <= low => "low",
+            Some("<" | "<=" | "≤") if self.value <= high => "normal",
+            Some(">" | ">=" | "≥") if self.value >= high => "high",
+            Some(">" | ">=" | "≥") if self.value >= low => "normal",
+            Some(_) => "",
+            None if self.value > high => "high",
+            None if self.value < low => "low",
+            None => "normal",
+        }
+    }
+
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("analyte", &self.analyte)?;
+        dict.set_item("value", self.value)?;
+        dict.set_item("comparator", &self.comparator)?;
+        dict.set_item("unit", &self.unit)?;
+        dict.set_item("ref_low", self.range.map(|(low, _)| low))?;
+        dict.set_item("ref_high", self.range.map(|(_, high)| high))?;
+        let flag = self.flag();
+        dict.set_item("flag", (!flag.is_empty()).then_some(flag))?;
+        dict.set_item("raw", &self.raw)?;
+        dict.set_item("span", (self.start, self.end))?;
+        Ok(dict)
+    }
+}
+
+/// Extract lab results and compare them with their reference ranges
+///
+/// The analyte is the word(s) before the value ("Glucosa", "Colesterol
+/// total", "T4 libre"). A range in parentheses or brackets after the
+/// value, optionally labelled ("VR:", "ref.") or with a repeated unit,
+/// gives ref_low/ref_high. Numbers follow Spanish conventions ("11,2",
+/// "150.000").
+///
+/// Args:
+///     text: The input text
+///
+/// Returns:
+///     List of dicts with "analyte", "value" (decimal point), "unit"
+///     (normalized, "" if none), "ref_low" and "ref_high" ("" without a
+///     range) and "flag" ("high", "low", "normal", or "" without a range)
+#[pyfunction]
+pub fn extract_lab_values(py: Python<'_>, text: &str) -> PyResult<Vec<HashMap<String, String>>> {
+    Ok(crate::allow_threads_if_large(py, text.len(), || {
+        extract_lab_values_internal(text).iter().map(lab_fields).collect()
+    }))
+}
+
+/// Extract lab results as typed, structured data
+///
+/// Same matching as extract_lab_values ("Hemoglobina: 12,3 g/dL (12-16)",
+/// "Glucemia 110 mg/dl [70-110]", "Troponina <0,01 ng/mL (0-0,04)"), with
+/// numbers as floats, the comparator kept apart and the source position.
+///
+/// Args:
+///     text: The input text
+///
+/// Returns:
+///     List of dicts with "analyte", "value" (float), "comparator" ("<",
+///     ">", "<=", ">=" or None), "unit" (normalized, "" if none),
+///     "ref_low" and "ref_high" (float or None), "flag" ("high", "low",
+///     "normal" or None), "raw" and "span" (start_char, end_char)
+#[pyfunction]
+pub fn extract_lab_results<'py>(py: Python<'py>, text: &str) -> PyResult<Vec<Bound<'py, PyDict>>> {
+    extract_lab_values_internal(text).iter().map(|lab| lab.to_dict(py)).collect()
+}
+
+pub(crate) fn extract_lab_values_internal(text: &str) -> Vec<LabValue> {
+    let mut cursor = CharCursor::new(text);
+    LAB_RE
+        .captures_iter(text)
+        .filter_map(|caps| {
+            let range = match (caps.name("low"), caps.name("high")) {
+                (Some(low), Some(high)) => Some((parse_number(low.as_str())?, parse_number(high.as_str())?)),
+                _ => None,
+            };
+            let unit = caps.name("unit").map(|unit| measurements::normalize_unit(unit.as_str()));
+            if unit.is_none() && range.is_none() {
+                return None;
+            }
+            let analyte = caps.name("analyte")?;
+            let end = caps.get(0)?.end();
+            Some(LabValue {
+                analyte: analyte.as_str().to_string(),
+                value: parse_number(caps.name("value")?.as_str())?,
+                comparator: caps.name("comparator").map(|comparator| comparator.as_str().to_string()),
+                unit: unit.unwrap_or_default(),
+                range,
+                raw: text[analyte.start()..end].to_string(),
+                start: cursor.char_offset(analyte.start()),
+                end: cursor.char_offset(end),
+            })
+        })
+        .collect()
+}
+
+fn lab_fields(lab: &LabValue) -> HashMap<String, String> {
+    let bound = |pick: fn((f64, f64)) -> f64| lab.range.map(|range| pick(range).to_string()).unwrap_or_default();
+    HashMap::from([
+        ("analyte".to_string(), lab.analyte.clone()),
+        ("value".to_string(), lab.value.to_string()),
+        ("unit".to_string(), lab.unit.clone()),
+        ("ref_low".to_string(), bound(|(low, _)| low)),
+        ("ref_high".to_string(), bound(|(_, high)| high)),
+        ("flag".to_string(), lab.flag().to_string()),
+    ])
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(text: &str) -> Vec<(String, f64, String, &'static str)> {
+        extract_lab_values_internal(text)
+            .iter()
+            .map(|lab| (lab.analyte.clone(), lab.value, lab.unit.clone(), lab.flag()))
+            .collect()
+    }
+
+    #[test]
+    fn test_flags_against_reference_range() {
+        let text = "Glucosa 140 mg/dL (70-110)\nUrea: 35 mg/dL (10 - 50)\nHemoglobina 11,2 g/dL [12 a 16 g/dL]\n\
+                    Plaquetas 150.000 /mm3 (VR: 150.000-450.000)";
+        let expected = [
+            ("Glucosa", 140.0, "mg/dL", "high"),
+            ("Urea", 35.0, "mg/dL", "normal"),
+            ("Hemoglobina", 11.2, "g/dL", "low"),
+            ("Plaquetas", 150_000.0, "/mm3", "normal"),
+        ];
+        assert_eq!(
+            summary(text),
+            expected.iter().map(|&(a, v, u, f)| (a.to_string(), v, u.to_string(), f)).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_without_range_flag_is_empty() {
+        let found = summary("Laboratorio: Colesterol total 185 mg/dL, T4 libre 1,3 ng/dL; TSH 2,5 µUI/mL (VR: 0,4 a 4).");
+        assert_eq!(
+            found,
+            vec![
+                ("Colesterol total".to_string(), 185.0, "mg/dL".to_string(), ""),
+                ("T4 libre".to_string(), 1.3, "ng/dL".to_string(), ""),
+                ("TSH".to_string(), 2.5, "µUI/mL".to_string(), "normal"),
+            ]
+        );
+        crate::with_py(|py| {
+            let labs = extract_lab_values(py, "Creatinina 1,1 mg/dL").unwrap();
+            assert_eq!(labs[0]["value"], "1.1");
+            assert_eq!(labs[0]["ref_low"], "");
+            assert_eq!(labs[0]["flag"], "");
+        });
+    }
+
+    #[test]
+    fn test_report_fixture() {
+        let report = include_str!("../tests/fixtures/lab_report.txt");
+        let labs = extract_lab_values_internal(report);
+        let found: Vec<_> = labs
+            .iter()
+            .map(|lab| (lab.analyte.as_str(), lab.value, lab.comparator.as_deref(), lab.unit.as_str(), lab.range, lab.flag()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("Hemoglobina", 12.3, None, "g/dL", Some((12.0, 16.0)), "normal"),
+                ("Hematocrito", 36.8, None, "%", Some((36.0, 46.0)), "normal"),
+                ("Leucocitos", 13_500.0, None, "/mm3", Some((4_000.0, 10_000.0)), "high"),
+                ("Plaquetas", 98_000.0, None, "/mm3", Some((150_000.0, 450_000.0)), "low"),
+                ("Glucemia", 110.0, None, "mg/dL", Some((70.0, 110.0)), "normal"),
+                ("Urea", 48.0, None, "mg/dL", Some((10.0, 50.0)), "normal"),
+                ("Creatinina", 1.45, None, "mg/dL", Some((0.6, 1.2)), "high"),
+                ("Sodio", 131.0, None, "mEq/L", Some((135.0, 145.0)), "low"),
+                ("Potasio", 4.2, None, "mEq/L", Some((3.5, 5.0)), "normal"),
+                ("Troponina", 0.01, Some("<"), "ng/mL", Some((0.0, 0.04)), "normal"),
+                ("Glucosa", 250.0, Some(">"), "mg/dL", Some((70.0, 110.0)), "high"),
+                ("PCR", 0.3, Some("<"), "mg/dL", None, ""),
+                ("TSH", 2.1, None, "µUI/mL", Some((0.4, 4.0)), "normal"),
+                ("Colesterol total", 212.0, None, "mg/dL", None, ""),
+            ]
+        );
+        // Spans cover the analyte through the range, in characters
+        let chars: Vec<char> = report.chars().collect();
+        for lab in &labs {
+            assert_eq!(chars[lab.start..lab.end].iter().collect::<String>(), lab.raw);
+            assert!(lab.raw.starts_with(&lab.analyte));
+        }
+        assert_eq!(labs[0].raw, "Hemoglobina: 12,3 g/dL (12-16)");
+    }
+
+    #[test]
+    fn test_lab_results_dicts() {
+        crate::with_py(|py| {
+            let labs = extract_lab_results(py, "Troponina <0,01 ng/mL (0-0,04). Ferritina 40 ng/mL").unwrap();
+            assert_eq!(labs.len(), 2);
+            let get = |i: usize, key: &str| labs[i].get_item(key).unwrap().unwrap();
+            assert_eq!(get(0, "value").extract::<f64>().unwrap(), 0.01);
+            assert_eq!(get(0, "comparator").extract::<String>().unwrap(), "<");
+            assert_eq!(get(0, "ref_high").extract::<f64>().unwrap(), 0.04);
+            assert_eq!(get(0, "flag").extract::<String>().unwrap(), "normal");
+            assert_eq!(get(0, "span").extract::<(usize, usize)>().unwrap(), (0, 30));
+            assert!(get(1, "comparator").is_none() && get(1, "ref_low").is_none() && get(1, "flag").is_none());
+        });
+    }
+
+    #[test]
+    fn test_values_without_unit_or_range_are_skipped() {
+        assert!(summary("FC 80, control en 2 días. Cama 12").is_empty());
+        // A range alone is enough
+        assert_eq!(summary("INR 2,8 (2-3)"), vec![("INR".to_string(), 2.8, String::new(), "normal")]);
+    }
+}
@@ -0,0 +1,222 @@
+//! Lightweight language identification
+//!
+//! Routes Spanish and Catalan notes and English (or Portuguese) abstracts
+//! to their pipelines without a model: each language has a list of very
+//! frequent function words, and the text votes with its tokens. Letters
+//! that only one language uses ("ñ" in Spanish, "ã", "õ", "ç" in
+//! Portuguese, the "·" of Catalan "l·l") add a vote each. Shared words
+//! ("de", "que", "para") vote for every language that has them, so the
+//! distinctive ones decide; they also leave the confidence alone, which
+//! only counts votes a single language could have cast.
+
+use std::collections::HashSet;
+
+use once_cell::sync::Lazy;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+
+/// Texts shorter than this (in characters, trimmed) are not classified
+const MIN_CHARS: usize = 20;
+
+/// Code returned when the language cannot be determined
+const UNDETERMINED: &str = "und";
+
+struct Profile {
+    code: &'static str,
+    words: HashSet<&'static str>,
+    letters: &'static [char],
+}
+
+static PROFILES: Lazy<Vec<Profile>> = Lazy::new(|| {
+    let profile = |code, words: &[&'static str], letters| Profile { code, words: words.iter().copied().collect(), letters };
+    vec![
+        profile(
+            "es",
+            &[
+                "el", "la", "los", "las", "de", "del", "que", "y", "en", "un", "una", "por", "con", "para",
+                "es", "se", "no", "al", "lo", "su", "sus", "como", "más", "pero", "le", "les", "ya", "fue",
+                "este", "esta", "estos", "ha", "han", "hay", "sin", "sobre", "también", "muy", "hasta",
+                "desde", "donde", "cuando", "entre", "presenta", "refiere", "paciente", "años", "dolor",
+            ],
+            &['ñ', '¿', '¡'],
+        ),
+        profile(
+            "en",
+            &[
+                "the", "of", "and", "to", "in", "is", "was", "for", "that", "with", "as", "on", "by", "at",
+                "are", "be", "this", "from", "were", "an", "or", "which", "we", "had", "has", "have", "not",
+                "it", "their", "these", "been", "than", "after", "patients", "patient", "study", "years",
+            ],
+            &[],
+        ),
+        profile(
+            "pt",
+            &[
+                "o", "a", "os", "as", "de", "do", "da", "dos", "das", "que", "e", "em", "um", "uma", "para",
+                "com", "não", "por", "mais", "se", "no", "na", "nos", "nas", "ao", "aos", "à", "como", "mas",
+                "foi", "ele", "ela", "são", "seu", "sua", "ou", "quando", "muito", "já", "também", "pelo",
+                "pela", "até", "isso", "sem", "está", "tem", "há", "paciente", "anos", "dor",
+            ],
+            &['ã', 'õ', 'ç'],
+        ),
+        profile(
+            "ca",
+            &[
+                "el", "la", "els", "les", "de", "del", "dels", "que", "i", "en", "un", "una", "per", "amb",
+                "és", "es", "no", "al", "als", "més", "però", "ja", "va", "ha", "han", "hi", "sense", "sobre",
+                "també", "molt", "fins", "des", "quan", "entre", "aquest", "aquesta", "seu", "seva", "fa",
+                "té", "pacient", "anys", "dolor",
+            ],
+            &['·'],
+        ),
+    ]
+});
+
+/// Detect the language of a text
+///
+/// Supports "es" (Spanish), "en" (English), "pt" (Portuguese) and "ca"
+/// (Catalan).
+///
+/// Args:
+///     text: The input text
+///
+/// Returns:
+///     (language code, confidence between 0.0 and 1.0). The confidence is
+///     the winner's share of the words and letters only one language
+///     uses, or of all votes when the text has none. Texts under 20
+///     characters, or without any known word, give ("und", 0.0).
+#[pyfunction]
+pub fn detect_language(py: Python<'_>, text: &str) -> PyResult<(String, f64)> {
+    Ok(crate::allow_threads_if_large(py, text.len(), || detect_language_internal(text)))
+}
+
+/// detect_language over many texts, in parallel without the GIL
+///
+/// Args:
+///     texts: List of texts
+///
+/// Returns:
+///     One (language code, confidence) per text
+#[pyfunction]
+pub fn detect_language_batch(py: Python<'_>, texts: Vec<String>) -> PyResult<Vec<(String, f64)>> {
+    Ok(py.allow_threads(|| texts.par_iter().map(|text| detect_language_internal(text)).collect()))
+}
+
+/// Votes of one language: all of them, and those no other language shares
+#[derive(Default)]
+struct Votes {
+    all: usize,
+    distinctive: usize,
+}
+
+/// Best language and its share of the distinctive votes
+pub(crate) fn detect_language_internal(text: &str) -> (String, f64) {
+    let undetermined = || (UNDETERMINED.to_string(), 0.0);
+    if text.trim().chars().count() < MIN_CHARS {
+        return undetermined();
+    }
+    let tokens = crate::word_tokens(text, true, false);
+    let lower = text.to_lowercase();
+    let mut votes: Vec<Votes> = PROFILES.iter().map(|_| Votes::default()).collect();
+    for token in &tokens {
+        let voters: Vec<usize> = (0..PROFILES.len()).filter(|&i| PROFILES[i].words.contains(token.as_str())).collect();
+        for &i in &voters {
+            votes[i].all += 1;
+            votes[i].distinctive += usize::from(voters.len() == 1);
+        }
+    }
+    for (profile, votes) in PROFILES.iter().zip(votes.iter_mut()) {
+        let letters = lower.chars().filter(|c| profile.letters.contains(c)).count();
+        votes.all += letters;
+        votes.distinctive += letters;
+    }
+    // Ties go to the earlier profile
+    let best = (0..votes.len()).fold(0, |best, index| if votes[index].all > votes[best].all { index } else { best });
+    let total: usize = votes.iter().map(|votes| votes.all).sum();
+    if total == 0 {
+        return undetermined();
+    }
+    let distinctive: usize = votes.iter().map(|votes| votes.distinctive).sum();
+    let confidence = if distinctive == 0 {
+        votes[best].all as f64 / total as f64
+    } else {
+        votes[best].distinctive as f64 / distinctive as f64
+    };
+    (PROFILES[best].code.to_string(), confidence)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURES: &[(&str, &str)] = &[
+        ("es", "Paciente de 67 años que consulta por dolor torácico opresivo de dos horas de evolución."),
+        ("es", "Se realiza electrocardiograma que muestra ritmo sinusal sin alteraciones agudas."),
+        ("es", "Niega alergias. Antecedentes de hipertensión arterial en tratamiento con enalapril."),
+        ("es", "El niño presenta fiebre y tos desde hace tres días, sin dificultad respiratoria."),
+        ("es", "Se indica reposo, hidratación y control por consultorio externo en una semana."),
+        ("en", "We conducted a randomized controlled trial of patients with acute coronary syndrome."),
+        ("en", "The primary outcome was mortality at 30 days, which was lower in the treatment group."),
+        ("en", "These findings suggest that early intervention is associated with improved survival."),
+        ("en", "Patients were followed for a median of five years after the initial procedure."),
+        ("en", "Data from this cohort have been analyzed using multivariable regression models."),
+        ("pt", "Paciente de 54 anos com dor abdominal intensa há dois dias, sem febre."),
+        ("pt", "Foi realizada tomografia que não mostrou alterações significativas no abdômen."),
+        ("pt", "O tratamento com antibióticos foi iniciado e a evolução clínica é favorável."),
+        ("pt", "A paciente nega alergias e está em uso de medicação para pressão alta."),
+        ("pt", "Os resultados indicam que a intervenção precoce melhora a sobrevida dos pacientes."),
+        ("ca", "Pacient de 72 anys que consulta per dolor toràcic opressiu de dues hores d'evolució."),
+        ("ca", "Es realitza electrocardiograma que mostra ritme sinusal sense alteracions agudes."),
+        ("ca", "El nen presenta febre i tos des de fa tres dies, sense dificultat respiratòria."),
+        ("ca", "La pacient nega al·lèrgies i té antecedents d'hipertensió arterial en tractament amb enalapril."),
+        ("ca", "S'indica repòs, hidratació i control per consultori extern d'aquí una setmana."),
+    ];
+
+    #[test]
+    fn test_fixture_accuracy() {
+        let correct = FIXTURES
+            .iter()
+            .filter(|(expected, text)| detect_language_internal(text).0 == *expected)
+            .count();
+        assert_eq!(correct, FIXTURES.len());
+        for (_, text) in FIXTURES {
+            let (_, confidence) = detect_language_internal(text);
+            assert!(confidence > 0.6 && confidence <= 1.0, "{}: {}", text, confidence);
+        }
+    }
+
+    #[test]
+    fn test_paragraphs() {
+        let spanish = "Paciente de 45 años que ingresa por cuadro de fiebre y tos productiva de cinco días de \
+                       evolución. Se solicita radiografía de tórax que muestra una consolidación en la base \
+                       derecha, por lo que se inicia tratamiento antibiótico con buena respuesta.";
+        let english = "A 45-year-old patient was admitted with fever and productive cough for five days. \
+                       The chest radiograph showed a consolidation in the right lower lobe, and antibiotic \
+                       treatment was started with a good response.";
+        let (code, confidence) = detect_language_internal(spanish);
+        assert_eq!(code, "es");
+        assert!(confidence > 0.9, "{}", confidence);
+        let (code, confidence) = detect_language_internal(english);
+        assert_eq!(code, "en");
+        assert!(confidence > 0.75, "{}", confidence);
+    }
+
+    #[test]
+    fn test_short_and_unknown_texts() {
+        assert_eq!(detect_language_internal("Dolor abdominal"), ("und".to_string(), 0.0));
+        assert_eq!(detect_language_internal("   the patient    "), ("und".to_string(), 0.0));
+        assert_eq!(detect_language_internal("12345 67890 12345 67890 xyz"), ("und".to_string(), 0.0));
+    }
+
+    #[test]
+    fn test_batch_matches_single() {
+        let texts: Vec<String> = FIXTURES.iter().map(|(_, text)| text.to_string()).collect();
+        crate::with_py(|py| {
+            let batch = detect_language_batch(py, texts.clone()).unwrap();
+            for (text, result) in texts.iter().zip(batch) {
+                assert_eq!(result, detect_language(py, text).unwrap());
+            }
+        });
+    }
+}
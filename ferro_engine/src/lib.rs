@@ -10,43 +10,227 @@
 //!
 //! Functions:
 //! - chunk_text: Split text into chunks with overlap
-//! - tokenize: Fast tokenization for embeddings
+//! - chunk_text_with_offsets: chunk_text plus source character offsets
+//! - chunk_text_iter: chunk_text as a lazy iterator
+//! - chunk_file_iter: Lazy chunks of a file read with buffered IO
+//! - chunk_by_tokens: Split text into chunks bounded by token count
+//! - split_sentences: Sentence segmentation used by the chunkers
+//! - split_sentences_with_offsets: split_sentences plus character offsets
+//! - sentence_token_counts: Sentences with their token estimates
+//! - recursive_chunk_text: LangChain-style recursive separator splitting
+//! - split_sections: Clinical note headings with their bodies and offsets
+//! - chunk_by_sections: Chunks that never span two sections
+//! - tokenize: Fast tokenization for embeddings (optional stopword removal)
+//! - remove_stopwords: Drop Spanish or English stopwords from a token list
+//! - tokenize_filtered: tokenize and stopword removal in one pass
+//! - parallel_tokenize: Batch tokenize
+//! - stem_tokens: Snowball stemming (Spanish, English)
+//! - tokenize_and_stem: tokenize plus stem_tokens
+//! - tokenize_with_spans: tokenize plus character offsets
+//! - tokenize_with_offsets: Tokens with character or byte offsets, optional casing
+//! - truncate_to_tokens: Cut text to a token budget at a word boundary
+//! - truncate_to_chars: Cut text to a character budget at a grapheme boundary
+//! - ngrams: Word or character n-grams, optionally padded
+//! - parallel_ngrams: Batch ngrams
+//! - char_ngrams: Grapheme n-grams for fuzzy matching
+//! - similarity: Jaccard or cosine similarity of two texts
+//! - cosine_similarity: Term-frequency cosine of two texts
+//! - jaccard_similarity: Token-set overlap of two texts
+//! - pairwise_similarity: Parallel pairwise similarity above a threshold
+//! - dedup_chunks: Drop byte-identical chunks, with a mapping to rebuild them
+//! - minhash_signatures: MinHash signatures of character shingles
+//! - find_near_duplicates: Near-duplicate groups via banded LSH
+//! - near_dups: Near-duplicate chunk pairs over word shingles
+//! - levenshtein: Character edit distance
+//! - similarity_ratio: Edit distance normalized to 0-1
+//! - fuzzy_find: Approximate occurrences of a term, with spans and distances
+//! - fuzzy_match_terms: Parallel fuzzy_find over several terms
+//! - extract_keywords: Top TF-IDF terms per document of a corpus
+//! - extract_keywords_single: Top RAKE phrases of one text
+//! - detect_language: Spanish / Catalan / English / Portuguese identification
+//! - detect_language_batch: Parallel detect_language
+//! - Bm25Index: BM25 lexical search over a growing document list
+//! - DictionaryMatcher: Aho-Corasick gazetteer search, whole words, case/accent folded
+//! - expand_abbreviations: Replace whole-token clinical abbreviations (HTA, DM2, ...)
+//! - make_snippet: Window around the densest query-term cluster, highlighted
+//! - readability: Fernández-Huerta score with sentence, word and syllable averages
+//! - text_stats: Character, byte, word, sentence and distinct word counts in one call
 //! - count_tokens: Count approximate tokens in text
+//! - count_tokens_detailed: Word, character and averaged token estimates
+//! - parallel_count_tokens: Batch count_tokens
+//! - count_tokens_bpe: Exact tiktoken-compatible token count
+//! - load_tokenizer: Load a .tiktoken vocabulary for count_tokens_exact
+//! - count_tokens_exact: Exact token count with the loaded vocabulary
+//! - count_tokens_exact_batch: Parallel count_tokens_exact
 //! - clean_medical_text: Sanitize medical text for processing
+//! - fix_mojibake: Repair UTF-8/Latin-1 double-encoding artifacts
+//! - parallel_clean_texts: Batch clean_medical_text
 //! - parallel_chunk_texts: Batch process multiple texts
-//! - extract_entities: Extract dates, times, measurements
+//! - FerroProcessor: Picklable clean → chunk → count pipeline with fixed settings
+//! - extract_entities: Extract dates, times, measurements, ICD-10 codes, percentages, ratios
+//! - extract_entities_with_spans: extract_entities plus character offsets
+//! - extract_entities_typed: extract_entities as an Entities object
+//! - extract_entity_counts: Occurrences of each distinct entity, optionally merging spellings
+//! - parallel_extract_entities: Batch extract_entities
+//! - extract_icd10: ICD-10 codes with existing categories and code-like context
+//! - extract_custom: Extract entities with user-supplied regex patterns
+//! - extract_measurements: Measurements with a custom unit list
+//! - extract_measurements_structured: Measurements as value + normalized unit
+//! - extract_dosages: Extract drug + dose + frequency prescriptions
+//! - extract_medications: Prescriptions as name/dose/unit/normalized frequency
+//! - extract_vitals: Extract blood pressure, heart rate, temperature, etc.
+//! - extract_blood_pressure: Systolic/diastolic pairs with label or mmHg context
+//! - extract_lab_values: Lab results flagged against their reference ranges
+//! - extract_lab_results: extract_lab_values as typed values with comparators and spans
+//! - extract_ages: Patient ages in years or months, from age context only
+//! - extract_demographics: Ages, sex, weight and height with supporting spans
+//! - extract_key_values: "key: value" lines of semi-structured reports
+//! - redact_pii: Mask emails, phones, national IDs and medical record numbers, with an audit list
+//! - find_pii: Identifiers redact_pii would mask, with character offsets
+//! - shift_dates: Move every date by a fixed number of days, keeping its format
+//! - extract_relative_dates: "hace 3 días", "ayer", "el mes pasado" resolved to ISO dates
+
+// PyO3 0.22's #[pyfunction] expansion trips this lint on every PyResult return
+#![allow(clippy::useless_conversion)]
+
+use std::borrow::Cow;
 
 use pyo3::prelude::*;
 use pyo3::exceptions::PyValueError;
+use pyo3::marker::Ungil;
+use pyo3::types::PyDict;
 use rayon::prelude::*;
 use regex::Regex;
+use unicode_normalization::UnicodeNormalization;
 use unicode_segmentation::UnicodeSegmentation;
 use once_cell::sync::Lazy;
 
+mod abbreviations;
+mod bm25;
+mod bpe;
+mod chunk_iter;
+mod custom;
+mod date_shift;
+mod dedup;
+mod demographics;
+mod dictionary;
+mod dosage;
+mod fuzzy;
+mod html;
+mod icd10;
+mod keyvalue;
+mod keywords;
+mod labs;
+mod language;
+mod measurements;
+mod minhash;
+mod pii;
+mod postprocess;
+mod processor;
+mod readability;
+mod relative_dates;
+mod sections;
+mod similarity;
+mod snippet;
+mod stats;
+mod stem;
+mod stopwords;
+mod vitals;
+
+use postprocess::EntityOptions;
+
 // Pre-compiled regex patterns (compiled once, never panic)
-static HTML_RE: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r"<[^>]+>").expect("Invalid HTML regex - this is a bug")
+static PARAGRAPH_RE: Lazy<Regex> = Lazy::new(|| {
+    // A blank line (possibly holding spaces or tabs) separates paragraphs
+    Regex::new(r"\n[ \t\r]*\n").expect("Invalid paragraph regex - this is a bug")
 });
 
-static CONTROL_RE: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r"[\x00-\x08\x0B\x0C\x0E-\x1F]").expect("Invalid control char regex - this is a bug")
+static DATE_RE: Lazy<Regex> = Lazy::new(|| {
+    // Numeric dates, or a day plus a Spanish month name ("15 de enero de
+    // 2026", "3 mar. 2025"); a month on its own is not a date
+    Regex::new(
+        r"(?xi)
+        \d{1,2}[/\-]\d{1,2}[/\-]\d{2,4}
+      | \b\d{1,2}[°º]? \s+ (?:de\s+)?
+        (?:(?:enero|febrero|marzo|abril|mayo|junio|julio|agosto|septiembre|setiembre|octubre|noviembre|diciembre)\b
+          |(?:ene|feb|mar|abr|may|jun|jul|ago|sept|sep|set|oct|nov|dic)\b\.?)
+        (?:(?:\s*,\s*|\s+del?\s+|\s+)\d{4}\b)?",
+    )
+    .expect("Invalid date regex - this is a bug")
 });
 
-static WHITESPACE_RE: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r"\s+").expect("Invalid whitespace regex - this is a bug")
+static TIME_RE: Lazy<Regex> = Lazy::new(|| {
+    // "14:30", "14:30:15", "14:30 hs", "2:30 p.m."; the dot separator
+    // ("14.30") needs a suffix so decimals like "37.50" are not times
+    Regex::new(
+        r"(?xi)
+        \b\d{1,2}
+        (?:
+            :\d{2} (?::\d{2})? (?: \s*(?:hs|h|horas)\b | \s*[ap]\.?\s?m\b\.? | \b )
+          | \.\d{2} (?: \s*(?:hs|h|horas)\b | \s*[ap]\.?\s?m\b\.? )
+        )",
+    )
+    .expect("Invalid time regex - this is a bug")
 });
 
-static DATE_RE: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r"\d{1,2}[/\-]\d{1,2}[/\-]\d{2,4}").expect("Invalid date regex - this is a bug")
+static ICD10_RE: Lazy<Regex> = Lazy::new(|| {
+    // Chapter letter, two digits, then an optional dot and up to four
+    // characters of subcategory ("I21", "I21.0", "J45.909", "S52.5A").
+    // Uppercase only: "e11" in running text is not a code
+    Regex::new(r"\b[A-Z]\d{2}(?:\.[0-9A-Z]{1,4})?\b").expect("Invalid ICD-10 regex - this is a bug")
 });
 
-static TIME_RE: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r"\d{1,2}:\d{2}(?::\d{2})?").expect("Invalid time regex - this is a bug")
+static PERCENT_RE: Lazy<Regex> = Lazy::new(|| {
+    // "95%", "12,5 %", "30 por ciento"
+    Regex::new(r"(?i)\b\d+(?:[.,]\d+)?(?:\s*%|\s+por\s+ciento\b)").expect("Invalid percentage regex - this is a bug")
 });
 
-static MEASURE_RE: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r"\d+(?:\.\d+)?\s*(?:mg|ml|g|kg|mm|cm|mmHg|bpm|°C|%|mcg|UI)")
-        .expect("Invalid measurement regex - this is a bug")
+static RATIO_RE: Lazy<Regex> = Lazy::new(|| {
+    // A value shortly after a ratio keyword ("INR 2.5", "RIN: 1,8");
+    // only the value is reported. "relación", "razón" and "proporción"
+    // also mean "regarding" ("en relación a 2 días"), so after them the
+    // value must follow the two sides compared ("relación A/G de 1,2") or
+    // be written N:M ("relación 3:1"), which is reported as `pair`. A
+    // slash never separates the two sides, so "01/02" stays a date
+    Regex::new(
+        r"(?xi)
+        \b(?:
+            (?:INR|RIN|ratio|cociente|diluci[oó]n)\b
+          | (?:relaci[oó]n|raz[oó]n|proporci[oó]n) \s+ \p{L}+ \s*/\s* \p{L}+ \b
+        )
+        [^\d\n]{0,20}?
+        \b(?P<value> \d+(?:[.,]\d+)? (?:\s*:\s*\d+(?:[.,]\d+)?)? )
+        |
+        \b(?:relaci[oó]n|raz[oó]n|proporci[oó]n)\b
+        [^\d\n]{0,20}?
+        \b(?P<pair> \d+(?:[.,]\d+)? \s*:\s* \d+(?:[.,]\d+)? )",
+    )
+    .expect("Invalid ratio regex - this is a bug")
+});
+
+static URL_RE: Lazy<Regex> = Lazy::new(|| {
+    // Trailing sentence punctuation is left out: "ver https://x.org." keeps the dot
+    Regex::new(r#"(?i)\b(?:https?://|www\.)[^\s<>"']*[^\s<>"'.,;:!?)\]]"#).expect("Invalid URL regex - this is a bug")
+});
+
+/// UTF-8 text that was decoded as Latin-1/Windows-1252 and re-encoded,
+/// mapped back to the intended characters. Only these exact sequences are
+/// rewritten, so correctly encoded text passes through untouched.
+const MOJIBAKE: &[(&str, &str)] = &[
+    ("Ã¡", "á"), ("Ã©", "é"), ("Ã\u{AD}", "í"), ("Ã³", "ó"), ("Ãº", "ú"),
+    ("Ã\u{81}", "Á"), ("Ã‰", "É"), ("Ã\u{8D}", "Í"), ("Ã“", "Ó"), ("Ãš", "Ú"),
+    ("Ã±", "ñ"), ("Ã‘", "Ñ"), ("Ã¼", "ü"), ("Ãœ", "Ü"),
+    ("Â°", "°"), ("Âº", "º"), ("Âª", "ª"), ("Â¿", "¿"), ("Â¡", "¡"),
+    ("Â±", "±"), ("Âµ", "µ"), ("Â²", "²"), ("Â³", "³"), ("Â\u{A0}", "\u{A0}"),
+];
+
+static MOJIBAKE_RE: Lazy<Regex> = Lazy::new(|| {
+    let pattern = MOJIBAKE
+        .iter()
+        .map(|(broken, _)| regex::escape(broken))
+        .collect::<Vec<_>>()
+        .join("|");
+    Regex::new(&pattern).expect("Invalid mojibake regex - this is a bug")
 });
 
 
@@ -54,293 +238,3459 @@ static MEASURE_RE: Lazy<Regex> = Lazy::new(|| {
 /// 
 /// Args:
 ///     text: The input text to chunk
-///     chunk_size: Maximum characters per chunk (default: 1000)
-///     overlap: Characters to overlap between chunks (default: 200)
+///     chunk_size: Maximum characters (or tokens) per chunk (default: 1000)
+///     overlap: Characters (or tokens) to overlap between chunks (default: 200)
+///     unit: "chars" to measure in Unicode characters, "tokens" to measure
+///           in words as produced by tokenize (default: "chars")
+///     delimiters: Sentence terminators, e.g. [".", "\n", ";"]
+///                 (default: [".", "\n"]). Terminators other than "." and
+///                 whitespace stay in the chunk ("¿Dolor? Sí").
+///     keep_decimals: Don't split on a period between digits, so "0.5 mg"
+///                    stays whole (default: True)
+///     respect_paragraphs: Chunk each blank-line separated paragraph on its
+///                         own, so no chunk (or overlap) spans two of them
+///                         (default: False)
+///     strict: Raise instead of hard-splitting a sentence longer than
+///             chunk_size (default: False)
+/// 
+/// Lengths are counted in Unicode characters, not UTF-8 bytes. Unless
+/// strict is set, sentences longer than chunk_size are hard-split at word
+/// boundaries, so no chunk ever exceeds chunk_size.
 /// 
 /// Returns:
 ///     List of text chunks
 /// 
 /// Raises:
-///     ValueError: If chunk_size is 0 or overlap >= chunk_size
+///     ValueError: If chunk_size is 0, overlap >= chunk_size, unit is unknown,
+///                 delimiters is empty, or (with strict) a sentence is
+///                 longer than chunk_size
 #[pyfunction]
-#[pyo3(signature = (text, chunk_size=1000, overlap=200))]
-fn chunk_text(text: &str, chunk_size: usize, overlap: usize) -> PyResult<Vec<String>> {
-    // Validate parameters
-    if chunk_size == 0 {
-        return Err(PyValueError::new_err("chunk_size must be greater than 0"));
+#[pyo3(signature = (
+    text, chunk_size=1000, overlap=200, unit="chars", delimiters=None, keep_decimals=true,
+    respect_paragraphs=false, strict=false
+))]
+#[allow(clippy::too_many_arguments)]
+fn chunk_text(
+    text: &str,
+    chunk_size: usize,
+    overlap: usize,
+    unit: &str,
+    delimiters: Option<Vec<String>>,
+    keep_decimals: bool,
+    respect_paragraphs: bool,
+    strict: bool,
+) -> PyResult<Vec<String>> {
+    let unit = ChunkUnit::parse(unit)?;
+    validate_chunk_params(chunk_size, overlap)?;
+    let splitter = SentenceSplitter::for_chunks(delimiters, keep_decimals)?;
+    
+    let sections: Vec<&str> = if respect_paragraphs {
+        PARAGRAPH_RE.split(text).collect()
+    } else {
+        vec![text]
+    };
+    if strict {
+        for section in &sections {
+            check_sentence_lengths(section, chunk_size, unit, &splitter)?;
+        }
     }
-    if overlap >= chunk_size {
-        return Err(PyValueError::new_err("overlap must be less than chunk_size"));
+    Ok(sections
+        .into_iter()
+        .flat_map(|section| chunk_segments(section, chunk_size, overlap, unit, &splitter))
+        .map(|chunk| chunk.text)
+        .collect())
+}
+
+
+/// Fail on the first sentence that does not fit in a chunk on its own
+fn check_sentence_lengths(text: &str, chunk_size: usize, unit: ChunkUnit, splitter: &SentenceSplitter) -> PyResult<()> {
+    for sentence in splitter.split(text) {
+        let len = unit.measure(sentence);
+        if len > chunk_size {
+            let preview: String = sentence.chars().take(40).collect();
+            return Err(PyValueError::new_err(format!(
+                "sentence of {} {} exceeds chunk_size ({}): '{}...'",
+                len, unit.name(), chunk_size, preview
+            )));
+        }
     }
-    
-    if text.is_empty() {
-        return Ok(vec![]);
+    Ok(())
+}
+
+
+/// Unit in which chunk_size and overlap are measured
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ChunkUnit {
+    /// Unicode characters
+    Chars,
+    /// Words, using the same segmentation as tokenize
+    Tokens,
+}
+
+impl ChunkUnit {
+    fn parse(unit: &str) -> PyResult<Self> {
+        match unit {
+            "chars" => Ok(ChunkUnit::Chars),
+            "tokens" => Ok(ChunkUnit::Tokens),
+            other => Err(PyValueError::new_err(format!(
+                "unit must be 'chars' or 'tokens', got '{}'", other
+            ))),
+        }
     }
-    
-    let sentences: Vec<&str> = text.split(|c| c == '.' || c == '\n').collect();
-    let mut chunks: Vec<String> = Vec::new();
-    let mut current_chunk = String::new();
-    
-    for sentence in sentences {
-        let sentence = sentence.trim();
-        if sentence.is_empty() {
-            continue;
+
+    fn name(self) -> &'static str {
+        match self {
+            ChunkUnit::Chars => "chars",
+            ChunkUnit::Tokens => "tokens",
         }
-        
-        // Check if adding this sentence exceeds chunk size
-        if current_chunk.len() + sentence.len() + 2 > chunk_size && !current_chunk.is_empty() {
-            chunks.push(current_chunk.clone());
-            
-            // Create overlap from end of current chunk
-            let overlap_start = if current_chunk.len() > overlap {
-                current_chunk.len() - overlap
-            } else {
-                0
-            };
-            current_chunk = current_chunk[overlap_start..].to_string();
+    }
+
+    /// Length of `text` in this unit
+    fn measure(self, text: &str) -> usize {
+        match self {
+            ChunkUnit::Chars => text.chars().count(),
+            ChunkUnit::Tokens => text.unicode_words().count(),
         }
-        
-        if !current_chunk.is_empty() {
-            current_chunk.push_str(". ");
+    }
+
+    /// Length of the separator joining `sentence` to the next one
+    fn separator_len(self, sentence: &str) -> usize {
+        match self {
+            ChunkUnit::Chars => sentence_separator(sentence).len(),
+            ChunkUnit::Tokens => 0,
         }
-        current_chunk.push_str(sentence);
     }
-    
-    // Don't forget the last chunk
-    if !current_chunk.is_empty() {
-        chunks.push(current_chunk);
+
+    /// Byte index where the last `overlap` units of `chunk` start
+    fn overlap_start(self, chunk: &str, overlap: usize) -> usize {
+        match self {
+            ChunkUnit::Chars => overlap_start_index(chunk, overlap),
+            ChunkUnit::Tokens => {
+                if overlap == 0 {
+                    return chunk.len();
+                }
+                chunk
+                    .unicode_word_indices()
+                    .rev()
+                    .nth(overlap - 1)
+                    .map_or(0, |(idx, _)| idx)
+            }
+        }
     }
-    
-    Ok(chunks)
 }
 
 
-/// Fast tokenization for embeddings (whitespace + punctuation split)
+/// Split text into sentences with the rules chunk_text uses
+/// 
+/// Only the default delimiters differ: chunk_text splits on "." and line
+/// breaks alone. Periods inside decimals ("37.5"), after common abbreviations ("Dr.",
+/// "Sra.", "etc.") and after list numbers at the start of a line ("1.",
+/// "2.") do not end a sentence. Each line of a newline-separated list is
+/// its own sentence. Delimiters are removed from the returned sentences.
 /// 
 /// Args:
-///     text: The input text to tokenize
+///     text: The input text
+///     delimiters: Sentence terminators (default: [".", "\n", "?", "!"])
+///     keep_decimals: Don't split on a period between digits (default: True)
+/// 
+/// Returns:
+///     List of trimmed, non-empty sentences in document order
+/// 
+/// Raises:
+///     ValueError: If delimiters is empty or contains an empty string
+#[pyfunction]
+#[pyo3(signature = (text, delimiters=None, keep_decimals=true))]
+fn split_sentences(text: &str, delimiters: Option<Vec<String>>, keep_decimals: bool) -> PyResult<Vec<String>> {
+    let splitter = SentenceSplitter::new(delimiters, keep_decimals)?;
+    Ok(splitter.split(text).into_iter().map(String::from).collect())
+}
+
+/// split_sentences plus each sentence's position in the source
+/// 
+/// Args:
+///     text: The input text
+///     delimiters: As in split_sentences
+///     keep_decimals: As in split_sentences
 /// 
 /// Returns:
-///     List of tokens (lowercase)
+///     List of (sentence, start_char, end_char); `text[start:end]` gives
+///     the sentence
+/// 
+/// Raises:
+///     ValueError: If delimiters is empty or contains an empty string
 #[pyfunction]
-fn tokenize(text: &str) -> PyResult<Vec<String>> {
-    Ok(text.unicode_words()
-        .map(|w| w.to_lowercase())
+#[pyo3(signature = (text, delimiters=None, keep_decimals=true))]
+fn split_sentences_with_offsets(text: &str, delimiters: Option<Vec<String>>, keep_decimals: bool) -> PyResult<Spans> {
+    let splitter = SentenceSplitter::new(delimiters, keep_decimals)?;
+    let mut cursor = CharCursor::new(text);
+    Ok(splitter
+        .split(text)
+        .into_iter()
+        .map(|sentence| {
+            let start = byte_offset(text, sentence);
+            (sentence.to_string(), cursor.char_offset(start), cursor.char_offset(start + sentence.len()))
+        })
         .collect())
 }
 
 
-/// Count approximate tokens in text (for context length estimation)
+/// Split text into sentences and estimate each one's token count
+/// 
+/// Sentences are split as in split_sentences and counted as in count_tokens,
+/// so Python code can greedily pack sentences into token budgets without
+/// a second pass over the document. Per-sentence estimates round down
+/// separately, so their sum can be slightly below count_tokens(text).
 /// 
 /// Args:
 ///     text: The input text
+///     chars_per_token: Average characters per token (default: 4)
 /// 
 /// Returns:
-///     Approximate token count
+///     List of (sentence, token_count) in document order
+/// 
+/// Raises:
+///     ValueError: If chars_per_token is not a positive number
 #[pyfunction]
-fn count_tokens(text: &str) -> PyResult<usize> {
-    // Rough approximation: ~4 characters per token for Spanish medical text
-    let word_count = text.unicode_words().count();
-    let char_factor = text.len() / 4;
-    
-    // Average of word count and character-based estimate
-    Ok((word_count + char_factor) / 2)
+#[pyo3(signature = (text, chars_per_token=4.0))]
+fn sentence_token_counts(text: &str, chars_per_token: f64) -> PyResult<Vec<(String, usize)>> {
+    validate_chars_per_token(chars_per_token)?;
+    Ok(SentenceSplitter::default()
+        .split(text)
+        .into_iter()
+        .map(|sentence| (sentence.to_string(), count_tokens_internal(sentence, chars_per_token)))
+        .collect())
 }
 
 
-/// Clean medical text for processing
+/// Chunk text by token budget instead of characters
 /// 
-/// Removes:
-/// - Extra whitespace
-/// - Special characters (preserving medical notation)
-/// - HTML tags
-/// - Control characters
+/// Shorthand for chunk_text(text, max_tokens, overlap_tokens, unit="tokens"):
+/// tokens are words as produced by tokenize, and sentences are split exactly
+/// as in chunk_text.
 /// 
 /// Args:
-///     text: The input text to clean
+///     text: The input text to chunk
+///     max_tokens: Maximum tokens per chunk (default: 512)
+///     overlap_tokens: Tokens to overlap between chunks (default: 64)
 /// 
 /// Returns:
-///     Cleaned text
+///     List of text chunks
+/// 
+/// Raises:
+///     ValueError: If max_tokens is 0 or overlap_tokens >= max_tokens
 #[pyfunction]
-fn clean_medical_text(text: &str) -> PyResult<String> {
-    // Remove HTML tags (using pre-compiled regex)
-    let text = HTML_RE.replace_all(text, "");
-    
-    // Remove control characters except newlines and tabs
-    let text = CONTROL_RE.replace_all(&text, "");
-    
-    // Normalize whitespace
-    let text = WHITESPACE_RE.replace_all(&text, " ");
+#[pyo3(signature = (text, max_tokens=512, overlap_tokens=64))]
+fn chunk_by_tokens(text: &str, max_tokens: usize, overlap_tokens: usize) -> PyResult<Vec<String>> {
+    validate_chunk_params(max_tokens, overlap_tokens)?;
+    Ok(chunk_text_internal(text, max_tokens, overlap_tokens, ChunkUnit::Tokens))
+}
+
+
+/// Chunk text and report where each chunk came from in the original
+/// 
+/// Chunks are identical to chunk_text's. Offsets are character (not byte)
+/// positions: `text[start:end]` in Python covers the source sentences of the
+/// chunk, including the original periods and line breaks that chunk_text
+/// replaces with ". " (or " " after a sentence that already ends in ".",
+/// "?", "!", ";" or "…"). Overlapping chunks have overlapping ranges.
+/// 
+/// Args:
+///     text: The input text to chunk
+///     chunk_size: Maximum characters (or tokens) per chunk (default: 1000)
+///     overlap: Characters (or tokens) to overlap between chunks (default: 200)
+///     unit: "chars" or "tokens", as in chunk_text (default: "chars")
+///     delimiters: Sentence terminators, as in chunk_text
+///                 (default: [".", "\n"])
+///     keep_decimals: As in chunk_text (default: True)
+/// 
+/// Returns:
+///     List of (chunk, start_char, end_char) tuples
+/// 
+/// Raises:
+///     ValueError: If chunk_size is 0, overlap >= chunk_size, unit is unknown
+///                 or delimiters is empty
+#[pyfunction]
+#[pyo3(signature = (text, chunk_size=1000, overlap=200, unit="chars", delimiters=None, keep_decimals=true))]
+fn chunk_text_with_offsets(
+    text: &str,
+    chunk_size: usize,
+    overlap: usize,
+    unit: &str,
+    delimiters: Option<Vec<String>>,
+    keep_decimals: bool,
+) -> PyResult<Vec<(String, usize, usize)>> {
+    let unit = ChunkUnit::parse(unit)?;
+    validate_chunk_params(chunk_size, overlap)?;
+    let splitter = SentenceSplitter::for_chunks(delimiters, keep_decimals)?;
     
-    Ok(text.trim().to_string())
+    // Starts and ends each ascend on their own, so give each a cursor
+    let mut starts = CharCursor::new(text);
+    let mut ends = CharCursor::new(text);
+    Ok(chunk_segments(text, chunk_size, overlap, unit, &splitter)
+        .into_iter()
+        .map(|chunk| {
+            let (start, end) = chunk.byte_span(text);
+            (chunk.text, starts.char_offset(start), ends.char_offset(end))
+        })
+        .collect())
 }
 
 
-/// Parallel chunk processing for large documents
+/// Recursively split text on a hierarchy of separators
+/// 
+/// Mirrors LangChain's RecursiveCharacterTextSplitter: the text is split on
+/// the first separator it contains, pieces that are still longer than
+/// chunk_size are split again with the next separator, and the resulting
+/// pieces are merged back into chunks of at most chunk_size characters with
+/// about `overlap` characters shared between neighbours. An empty separator
+/// splits between graphemes and is used as the last resort anyway.
+/// 
+/// Separators stay attached to the end of the piece they terminate, so
+/// every chunk is a (trimmed) substring of the original text.
 /// 
 /// Args:
-///     texts: List of texts to process
-///     chunk_size: Maximum characters per chunk
-///     overlap: Characters to overlap
+///     text: The input text to chunk
+///     chunk_size: Maximum characters per chunk (default: 1000)
+///     overlap: Characters to overlap between chunks (default: 200)
+///     separators: Separators to try in order
+///                 (default: ["\n\n", "\n", ". ", " ", ""])
 /// 
 /// Returns:
-///     List of (original_index, chunks) tuples
+///     List of text chunks
 /// 
 /// Raises:
 ///     ValueError: If chunk_size is 0 or overlap >= chunk_size
 #[pyfunction]
-#[pyo3(signature = (texts, chunk_size=1000, overlap=200))]
-fn parallel_chunk_texts(texts: Vec<String>, chunk_size: usize, overlap: usize) -> PyResult<Vec<(usize, Vec<String>)>> {
-    // Validate parameters once
+#[pyo3(signature = (text, chunk_size=1000, overlap=200, separators=None))]
+fn recursive_chunk_text(
+    text: &str,
+    chunk_size: usize,
+    overlap: usize,
+    separators: Option<Vec<String>>,
+) -> PyResult<Vec<String>> {
+    validate_chunk_params(chunk_size, overlap)?;
+    let separators = separators.unwrap_or_else(|| {
+        ["\n\n", "\n", ". ", " ", ""].iter().map(|s| s.to_string()).collect()
+    });
+    
+    let pieces = recursive_pieces(text, &separators, chunk_size);
+    Ok(merge_pieces(text, &pieces, chunk_size, overlap)
+        .into_iter()
+        .map(String::from)
+        .collect())
+}
+
+
+/// Validate chunking parameters shared by all chunking entry points
+fn validate_chunk_params(chunk_size: usize, overlap: usize) -> PyResult<()> {
     if chunk_size == 0 {
         return Err(PyValueError::new_err("chunk_size must be greater than 0"));
     }
     if overlap >= chunk_size {
         return Err(PyValueError::new_err("overlap must be less than chunk_size"));
     }
+    Ok(())
+}
+
+
+/// Fast tokenization for embeddings (whitespace + punctuation split)
+/// 
+/// Args:
+///     text: The input text to tokenize
+///     remove_stopwords: Drop stopwords, compared case-insensitively (default: False)
+///     stopwords: Custom stopword list used instead of the built-in Spanish
+///                medical list when remove_stopwords is set. Negations
+///                ("no", "sin") are not in the built-in list.
+///     lowercase: Lowercase the tokens (default: True). Set to False to
+///                tell acronyms like "TAC" apart from words like "tac".
+///     keep_punctuation: Emit punctuation marks and symbols ("." "," "%")
+///                       as standalone tokens, in order (default: False)
+/// 
+/// Returns:
+///     List of tokens
+#[pyfunction]
+#[pyo3(signature = (text, remove_stopwords=false, stopwords=None, lowercase=true, keep_punctuation=false))]
+fn tokenize(
+    text: &str,
+    remove_stopwords: bool,
+    stopwords: Option<Vec<String>>,
+    lowercase: bool,
+    keep_punctuation: bool,
+) -> PyResult<Vec<String>> {
+    let custom: Option<std::collections::HashSet<String>> =
+        stopwords.map(|list| list.iter().map(|w| w.to_lowercase()).collect());
+    let is_stopword = |lower: &str| match &custom {
+        Some(custom) => custom.contains(lower),
+        None => stopwords::SPANISH_SET.contains(lower),
+    };
     
-    // Process in parallel - chunk_text now returns PyResult, so we unwrap safely
-    // since we already validated parameters
-    let results: Vec<(usize, Vec<String>)> = texts
-        .par_iter()
-        .enumerate()
-        .map(|(idx, text)| {
-            // Safe to unwrap here because we validated params above
-            let chunks = chunk_text_internal(text, chunk_size, overlap);
-            (idx, chunks)
+    Ok(raw_tokens(text, keep_punctuation)
+        .filter_map(|w| {
+            let lower = w.to_lowercase();
+            if remove_stopwords && is_stopword(&lower) {
+                return None;
+            }
+            Some(if lowercase { lower } else { w.to_string() })
         })
-        .collect();
-    
-    Ok(results)
+        .collect())
 }
 
-/// Internal chunk_text that doesn't return PyResult (for parallel processing)
-fn chunk_text_internal(text: &str, chunk_size: usize, overlap: usize) -> Vec<String> {
-    if text.is_empty() {
-        return vec![];
-    }
-    
-    let sentences: Vec<&str> = text.split(|c| c == '.' || c == '\n').collect();
-    let mut chunks: Vec<String> = Vec::new();
-    let mut current_chunk = String::new();
-    
-    for sentence in sentences {
-        let sentence = sentence.trim();
-        if sentence.is_empty() {
-            continue;
-        }
-        
-        if current_chunk.len() + sentence.len() + 2 > chunk_size && !current_chunk.is_empty() {
-            chunks.push(current_chunk.clone());
-            
-            let overlap_start = if current_chunk.len() > overlap {
-                current_chunk.len() - overlap
-            } else {
-                0
-            };
-            current_chunk = current_chunk[overlap_start..].to_string();
-        }
-        
-        if !current_chunk.is_empty() {
-            current_chunk.push_str(". ");
-        }
-        current_chunk.push_str(sentence);
-    }
-    
-    if !current_chunk.is_empty() {
-        chunks.push(current_chunk);
-    }
-    
-    chunks
+/// Words of `text` (as unicode_words), plus punctuation when asked
+///
+/// UAX #29 puts every punctuation mark in its own segment, so "..." comes
+/// out as three tokens and "PCR-us" as "PCR", "-", "us".
+fn raw_tokens(text: &str, keep_punctuation: bool) -> impl Iterator<Item = &str> {
+    text.split_word_bounds().filter(move |segment| {
+        segment.chars().any(char::is_alphanumeric)
+            || keep_punctuation && segment.chars().all(|c| !c.is_whitespace() && !c.is_control())
+    })
 }
 
 
-/// Extract medical entities (regex-based)
+/// Drop stopwords from an already tokenized text
 /// 
-/// Extracts:
-/// - Dates (DD/MM/YYYY, DD-MM-YYYY)
-/// - Times (HH:MM, HH:MM:SS)
-/// - Measurements (numbers with units: mg, ml, g, kg, mmHg, etc.)
+/// Comparison is case-insensitive but accent-sensitive: "Más" is removed,
+/// "mas" is kept. Tokens that survive keep their original form.
+/// 
+/// Args:
+///     tokens: List of tokens
+///     extra: Additional words to treat as stopwords (default: None)
+///     lang: Built-in list to use, "es" (Spanish, clinical) or "en"
+///           (default: "es")
+/// 
+/// Returns:
+///     The tokens that are not stopwords, in order
+/// 
+/// Raises:
+///     ValueError: If lang is unknown
+#[pyfunction]
+#[pyo3(signature = (tokens, extra=None, lang="es"))]
+fn remove_stopwords(tokens: Vec<String>, extra: Option<Vec<String>>, lang: &str) -> PyResult<Vec<String>> {
+    let filter = stopwords::StopwordFilter::new(lang, extra)?;
+    Ok(tokens.into_iter().filter(|token| !filter.contains(&token.to_lowercase())).collect())
+}
+
+/// Tokenize and drop stopwords in one pass
+/// 
+/// Equivalent to remove_stopwords(tokenize(text, lowercase=lowercase), ...)
+/// without building the intermediate list.
 /// 
 /// Args:
 ///     text: The input text
+///     extra: Additional words to treat as stopwords (default: None)
+///     lang: "es" or "en", as in remove_stopwords (default: "es")
+///     lowercase: Lowercase the tokens (default: True)
 /// 
 /// Returns:
-///     Dict with extracted entities: {"dates": [...], "times": [...], "measurements": [...]}
+///     List of tokens that are not stopwords
+/// 
+/// Raises:
+///     ValueError: If lang is unknown
 #[pyfunction]
-fn extract_entities(text: &str) -> PyResult<std::collections::HashMap<String, Vec<String>>> {
-    let mut entities: std::collections::HashMap<String, Vec<String>> = std::collections::HashMap::new();
-    
-    // Dates (using pre-compiled regex)
-    entities.insert(
-        "dates".to_string(),
-        DATE_RE.find_iter(text).map(|m| m.as_str().to_string()).collect()
-    );
-    
-    // Times
-    entities.insert(
-        "times".to_string(),
-        TIME_RE.find_iter(text).map(|m| m.as_str().to_string()).collect()
-    );
-    
-    // Measurements (number + unit)
-    entities.insert(
-        "measurements".to_string(),
-        MEASURE_RE.find_iter(text).map(|m| m.as_str().to_string()).collect()
-    );
-    
-    Ok(entities)
+#[pyo3(signature = (text, extra=None, lang="es", lowercase=true))]
+fn tokenize_filtered(text: &str, extra: Option<Vec<String>>, lang: &str, lowercase: bool) -> PyResult<Vec<String>> {
+    let filter = stopwords::StopwordFilter::new(lang, extra)?;
+    Ok(text
+        .unicode_words()
+        .filter_map(|w| {
+            let lower = w.to_lowercase();
+            if filter.contains(&lower) {
+                return None;
+            }
+            Some(if lowercase { lower } else { w.to_string() })
+        })
+        .collect())
 }
 
 
-/// Python module definition
-#[pymodule]
-fn ferro_engine(m: &Bound<'_, PyModule>) -> PyResult<()> {
-    m.add_function(wrap_pyfunction!(chunk_text, m)?)?;
-    m.add_function(wrap_pyfunction!(tokenize, m)?)?;
-    m.add_function(wrap_pyfunction!(count_tokens, m)?)?;
-    m.add_function(wrap_pyfunction!(clean_medical_text, m)?)?;
-    m.add_function(wrap_pyfunction!(parallel_chunk_texts, m)?)?;
-    m.add_function(wrap_pyfunction!(extract_entities, m)?)?;
-    
-    // Module metadata - v3.0.0 FERRO compliant
-    m.add("__version__", "3.0.0")?;
-    m.add("__doc__", "FERRO Protocol v3.0.0 - Rust CPU-bound engine for text processing")?;
-    
-    Ok(())
+/// Batches smaller than this are tokenized on the calling thread
+const PARALLEL_TOKENIZE_MIN_BATCH: usize = 64;
+
+/// Tokenize many texts at once
+/// 
+/// Same tokens as tokenize without stopword removal. Large batches run on
+/// all cores; batches under 64 texts are tokenized in order on the calling
+/// thread, where the thread-pool overhead would outweigh the work. The GIL
+/// is released either way.
+/// 
+/// Args:
+///     texts: List of texts
+///     lowercase: Same as in tokenize (default: True)
+///     keep_punctuation: Same as in tokenize (default: False)
+/// 
+/// Returns:
+///     List of token lists, in input order
+#[pyfunction]
+#[pyo3(signature = (texts, lowercase=true, keep_punctuation=false))]
+fn parallel_tokenize(
+    py: Python<'_>,
+    texts: Vec<String>,
+    lowercase: bool,
+    keep_punctuation: bool,
+) -> PyResult<Vec<Vec<String>>> {
+    let tokenize_one = |text: &String| word_tokens(text, lowercase, keep_punctuation);
+    Ok(py.allow_threads(|| {
+        if texts.len() < PARALLEL_TOKENIZE_MIN_BATCH {
+            texts.iter().map(tokenize_one).collect()
+        } else {
+            texts.par_iter().map(tokenize_one).collect()
+        }
+    }))
 }
 
+fn word_tokens(text: &str, lowercase: bool, keep_punctuation: bool) -> Vec<String> {
+    raw_tokens(text, keep_punctuation)
+        .map(|w| if lowercase { w.to_lowercase() } else { w.to_string() })
+        .collect()
+}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
 
-    #[test]
-    fn test_chunk_text_empty() {
-        let result = chunk_text_internal("", 100, 20);
-        assert!(result.is_empty());
-    }
+/// Tokenize like tokenize, keeping each token's position in the source
+/// 
+/// Offsets are character (not byte) offsets into the original `text`, so
+/// `text[start:end]` in Python gives the token with its original casing.
+/// 
+/// Args:
+///     text: The input text to tokenize
+/// 
+/// Returns:
+///     List of (token, start_char, end_char) with lowercase tokens
+#[pyfunction]
+fn tokenize_with_spans(text: &str) -> PyResult<Spans> {
+    Ok(token_offsets(text, true, false))
+}
 
-    #[test]
-    fn test_chunk_text_basic() {
-        let text = "First sentence. Second sentence. Third sentence.";
-        let result = chunk_text_internal(text, 50, 10);
-        assert!(!result.is_empty());
+/// Tokens with their offsets, for aligning NER annotations
+/// 
+/// Offsets always refer to the original `text`, whatever the casing of the
+/// returned tokens. Character offsets index Python strings directly; byte
+/// offsets index the UTF-8 encoding (`text.encode()[start:end]`) and differ
+/// from character offsets after any non-ASCII character.
+/// 
+/// Args:
+///     text: The input text to tokenize
+///     lowercase: Lowercase the tokens (default: True)
+///     byte_offsets: Return UTF-8 byte offsets instead of character
+///                   offsets (default: False)
+/// 
+/// Returns:
+///     List of (token, start, end)
+#[pyfunction]
+#[pyo3(signature = (text, lowercase=true, byte_offsets=false))]
+fn tokenize_with_offsets(text: &str, lowercase: bool, byte_offsets: bool) -> PyResult<Spans> {
+    Ok(token_offsets(text, lowercase, byte_offsets))
+}
+
+fn token_offsets(text: &str, lowercase: bool, byte_offsets: bool) -> Spans {
+    let mut cursor = CharCursor::new(text);
+    text.unicode_word_indices()
+        .map(|(start, word)| {
+            let token = if lowercase { word.to_lowercase() } else { word.to_string() };
+            let end = start + word.len();
+            if byte_offsets {
+                (token, start, end)
+            } else {
+                (token, cursor.char_offset(start), cursor.char_offset(end))
+            }
+        })
+        .collect()
+}
+
+
+/// Appended by truncate_to_tokens(..., ellipsis=True) when text is cut
+const ELLIPSIS: &str = "…";
+
+/// Cut text to a token budget
+/// 
+/// The cut falls right after the last word that fits, so no partial word
+/// is kept; the ellipsis, when requested, counts towards the budget.
+/// 
+/// Args:
+///     text: The input text
+///     max_tokens: Maximum number of tokens to keep
+///     ellipsis: Append "…" when the text is cut (default: False)
+///     tokenizer: How tokens are counted (default: "auto"):
+///                - "auto": the vocabulary from load_tokenizer if one is
+///                  loaded, otherwise the count_tokens estimate at 4
+///                  characters per token
+///                - "estimate": always the count_tokens estimate
+///                - "loaded": always the load_tokenizer vocabulary
+///                - an encoding or model name, as in count_tokens_bpe
+/// 
+/// Returns:
+///     The longest word-aligned prefix of text that fits, or the whole text
+///     unchanged if it already fits
+/// 
+/// Raises:
+///     ValueError: If max_tokens is 0 or the tokenizer is unknown
+///     RuntimeError: If tokenizer is "loaded" and none has been loaded
+#[pyfunction]
+#[pyo3(signature = (text, max_tokens, ellipsis=false, tokenizer="auto"))]
+fn truncate_to_tokens(text: &str, max_tokens: usize, ellipsis: bool, tokenizer: &str) -> PyResult<String> {
+    if max_tokens == 0 {
+        return Err(PyValueError::new_err("max_tokens must be at least 1"));
     }
+    let loaded = match tokenizer {
+        "auto" => bpe::loaded_encoding(),
+        "loaded" => Some(bpe::loaded()?),
+        _ => None,
+    };
+    let exact = match tokenizer {
+        "auto" | "loaded" => loaded.as_deref(),
+        "estimate" => None,
+        model => Some(bpe::encoding_for(model)?),
+    };
+    let count = |candidate: &str| match exact {
+        Some(bpe) => bpe::count_with(bpe, candidate),
+        None => count_tokens_internal(candidate, DEFAULT_CHARS_PER_TOKEN),
+    };
+    Ok(truncate_to_budget(text, max_tokens, ellipsis, count))
+}
 
-    #[test]
-    fn test_clean_medical_text() {
-        let text = "<b>Test</b>  multiple   spaces";
-        let result = clean_medical_text(text).unwrap();
-        assert_eq!(result, "Test multiple spaces");
+/// Longest word-aligned prefix (plus ellipsis) whose `count` fits `budget`
+fn truncate_to_budget(text: &str, budget: usize, ellipsis: bool, count: impl Fn(&str) -> usize) -> String {
+    if count(text) <= budget {
+        return text.to_string();
     }
+    let with_ellipsis = |prefix: &str| if ellipsis { format!("{}{}", prefix, ELLIPSIS) } else { prefix.to_string() };
+    let word_ends: Vec<usize> = text.unicode_word_indices().map(|(idx, word)| idx + word.len()).collect();
+    // Counts grow with the prefix, so binary search the last word end that fits
+    let fitting = word_ends.partition_point(|&end| count(&with_ellipsis(&text[..end])) <= budget);
+    match fitting {
+        0 => String::new(),
+        n => with_ellipsis(&text[..word_ends[n - 1]]),
+    }
+}
 
-    #[test]
-    fn test_extract_entities() {
-        let text = "Fecha: 15/01/2026 a las 14:30. Dosis: 500mg";
-        let result = extract_entities(text).unwrap();
-        assert_eq!(result.get("dates").unwrap().len(), 1);
-        assert_eq!(result.get("times").unwrap().len(), 1);
-        assert_eq!(result.get("measurements").unwrap().len(), 1);
+/// Cut text to at most `max_chars` characters without splitting a grapheme
+/// 
+/// An accented letter written as letter + combining mark, or an emoji
+/// sequence, is kept or dropped as a whole.
+/// 
+/// Args:
+///     text: The input text
+///     max_chars: Maximum number of Unicode characters to keep
+/// 
+/// Returns:
+///     The longest grapheme-aligned prefix with at most max_chars
+///     characters (the whole text if it already fits)
+/// 
+/// Raises:
+///     ValueError: If max_chars is 0
+#[pyfunction]
+fn truncate_to_chars(text: &str, max_chars: usize) -> PyResult<String> {
+    if max_chars == 0 {
+        return Err(PyValueError::new_err("max_chars must be at least 1"));
+    }
+    let mut chars = 0;
+    let end = text
+        .grapheme_indices(true)
+        .find_map(|(idx, grapheme)| {
+            chars += grapheme.chars().count();
+            (chars > max_chars).then_some(idx)
+        })
+        .unwrap_or(text.len());
+    Ok(text[..end].to_string())
+}
+
+
+/// What ngrams slides its window over
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum NgramKind {
+    /// Lowercase words, using the same segmentation as tokenize
+    Word,
+    /// Lowercase grapheme clusters
+    Char,
+}
+
+impl NgramKind {
+    fn parse(kind: &str) -> PyResult<Self> {
+        match kind {
+            "word" => Ok(NgramKind::Word),
+            "char" => Ok(NgramKind::Char),
+            other => Err(PyValueError::new_err(format!(
+                "kind must be 'word' or 'char', got '{}'", other
+            ))),
+        }
+    }
+}
+
+/// Boundary marker added around the text by ngrams(..., pad=True)
+const NGRAM_PAD: &str = "#";
+
+/// Word or character n-grams for phrase matching and fuzzy indexing
+/// 
+/// Word n-grams come from the same lowercase word split as tokenize and
+/// are joined with spaces. Char n-grams are taken over lowercase grapheme
+/// clusters, so "ñ" or a letter + combining accent is one unit; with pad,
+/// a "#" marks the start and end of the text ("amo" -> "#am", "amo", "mo#").
+/// 
+/// Args:
+///     text: The input text
+///     n: Number of words or graphemes per n-gram
+///     kind: "word" or "char" (default: "word")
+///     pad: Add boundary markers, char n-grams only (default: False)
+/// 
+/// Returns:
+///     List of n-grams, empty if the text is shorter than n units
+/// 
+/// Raises:
+///     ValueError: If n is 0, kind is unknown, or pad is set for word n-grams
+#[pyfunction]
+#[pyo3(signature = (text, n, kind="word", pad=false))]
+fn ngrams(text: &str, n: usize, kind: &str, pad: bool) -> PyResult<Vec<String>> {
+    let kind = validate_ngram_params(n, kind, pad)?;
+    Ok(ngrams_internal(text, n, kind, pad))
+}
+
+/// Parallel ngrams for large corpora
+/// 
+/// Args:
+///     texts: List of texts
+///     n, kind, pad: As in ngrams
+/// 
+/// Returns:
+///     List of n-gram lists, in input order
+/// 
+/// Raises:
+///     ValueError: As in ngrams
+#[pyfunction]
+#[pyo3(signature = (texts, n, kind="word", pad=false))]
+fn parallel_ngrams(py: Python<'_>, texts: Vec<String>, n: usize, kind: &str, pad: bool) -> PyResult<Vec<Vec<String>>> {
+    let kind = validate_ngram_params(n, kind, pad)?;
+    Ok(py.allow_threads(|| texts.par_iter().map(|text| ngrams_internal(text, n, kind, pad)).collect()))
+}
+
+fn validate_ngram_params(n: usize, kind: &str, pad: bool) -> PyResult<NgramKind> {
+    if n == 0 {
+        return Err(PyValueError::new_err("n must be at least 1"));
+    }
+    let kind = NgramKind::parse(kind)?;
+    if pad && kind == NgramKind::Word {
+        return Err(PyValueError::new_err("pad is only supported for char n-grams"));
+    }
+    Ok(kind)
+}
+
+fn ngrams_internal(text: &str, n: usize, kind: NgramKind, pad: bool) -> Vec<String> {
+    match kind {
+        NgramKind::Word => {
+            let tokens: Vec<String> = text.unicode_words().map(|w| w.to_lowercase()).collect();
+            tokens.windows(n).map(|window| window.join(" ")).collect()
+        }
+        NgramKind::Char => char_ngrams_internal(text, n, true, pad),
+    }
+}
+
+
+/// Character n-grams for typo-tolerant matching
+/// 
+/// Windows are taken over grapheme clusters, so accented letters (even
+/// when written as letter + combining accent) are never split.
+/// 
+/// Args:
+///     text: The input text
+///     n: Number of graphemes per n-gram
+///     lowercase: Lowercase the text first (default: True)
+/// 
+/// Returns:
+///     List of n-grams, empty if the text has fewer than n graphemes
+/// 
+/// Raises:
+///     ValueError: If n is 0
+#[pyfunction]
+#[pyo3(signature = (text, n, lowercase=true))]
+fn char_ngrams(text: &str, n: usize, lowercase: bool) -> PyResult<Vec<String>> {
+    if n == 0 {
+        return Err(PyValueError::new_err("n must be at least 1"));
+    }
+    Ok(char_ngrams_internal(text, n, lowercase, false))
+}
+
+fn char_ngrams_internal(text: &str, n: usize, lowercase: bool, pad: bool) -> Vec<String> {
+    let text = if lowercase { text.to_lowercase() } else { text.to_string() };
+    let mut graphemes: Vec<&str> = text.graphemes(true).collect();
+    if pad && !graphemes.is_empty() {
+        graphemes.insert(0, NGRAM_PAD);
+        graphemes.push(NGRAM_PAD);
+    }
+    graphemes.windows(n).map(|window| window.concat()).collect()
+}
+
+
+/// Default chars_per_token of count_tokens
+const DEFAULT_CHARS_PER_TOKEN: f64 = 4.0;
+
+/// Count approximate tokens in text (for context length estimation)
+/// 
+/// Args:
+///     text: The input text
+///     chars_per_token: Average characters per token (default: 4)
+/// 
+/// Returns:
+///     Approximate token count
+/// 
+/// Raises:
+///     ValueError: If chars_per_token is not a positive number
+#[pyfunction]
+#[pyo3(signature = (text, chars_per_token=4.0))]
+fn count_tokens(text: &str, chars_per_token: f64) -> PyResult<usize> {
+    validate_chars_per_token(chars_per_token)?;
+    Ok(count_tokens_internal(text, chars_per_token))
+}
+
+/// Components of the count_tokens estimate
+/// 
+/// Useful to pick one estimate or recalibrate chars_per_token for a corpus.
+/// 
+/// Args:
+///     text: The input text
+///     chars_per_token: Average characters per token (default: 4)
+/// 
+/// Returns:
+///     (word_count, char_based_estimate, averaged_estimate); the last one
+///     is what count_tokens returns
+/// 
+/// Raises:
+///     ValueError: If chars_per_token is not a positive number
+#[pyfunction]
+#[pyo3(signature = (text, chars_per_token=4.0))]
+fn count_tokens_detailed(text: &str, chars_per_token: f64) -> PyResult<(usize, usize, usize)> {
+    validate_chars_per_token(chars_per_token)?;
+    Ok(token_estimates(text, chars_per_token))
+}
+
+/// Parallel count_tokens for large batches
+/// 
+/// Uses the same estimate as count_tokens, computed on all cores with the
+/// GIL released.
+/// 
+/// Args:
+///     texts: List of texts
+///     chars_per_token: Average characters per token (default: 4)
+/// 
+/// Returns:
+///     List of approximate token counts, in input order
+/// 
+/// Raises:
+///     ValueError: If chars_per_token is not a positive number
+#[pyfunction]
+#[pyo3(signature = (texts, chars_per_token=4.0))]
+fn parallel_count_tokens(py: Python<'_>, texts: Vec<String>, chars_per_token: f64) -> PyResult<Vec<usize>> {
+    validate_chars_per_token(chars_per_token)?;
+    Ok(py.allow_threads(|| {
+        texts
+            .par_iter()
+            .map(|text| count_tokens_internal(text, chars_per_token))
+            .collect()
+    }))
+}
+
+fn validate_chars_per_token(chars_per_token: f64) -> PyResult<()> {
+    if !chars_per_token.is_finite() || chars_per_token <= 0.0 {
+        return Err(PyValueError::new_err("chars_per_token must be positive"));
+    }
+    Ok(())
+}
+
+fn count_tokens_internal(text: &str, chars_per_token: f64) -> usize {
+    token_estimates(text, chars_per_token).2
+}
+
+/// (word count, character-based estimate, their average)
+fn token_estimates(text: &str, chars_per_token: f64) -> (usize, usize, usize) {
+    // Characters, not bytes: accented Spanish letters are 2 bytes each
+    let word_count = text.unicode_words().count();
+    let char_factor = (text.chars().count() as f64 / chars_per_token) as usize;
+    
+    // Average of word count and character-based estimate
+    (word_count, char_factor, (word_count + char_factor) / 2)
+}
+
+
+/// Clean medical text for processing
+/// 
+/// Removes:
+/// - Extra whitespace
+/// - Special characters (preserving medical notation)
+/// - HTML tags (`<br>` and `<p>` become line breaks; bare comparisons
+///   like "FC <60" are kept)
+/// - Control characters
+/// 
+/// Named and numeric HTML entities ("&aacute;", "&#243;", "&#xF3;") left
+/// after tag removal are decoded; unknown entities are kept verbatim.
+/// Non-breaking spaces ("&nbsp;", U+00A0) collapse like any other space.
+/// 
+/// Inputs of 64 KiB or more are cleaned with the GIL released. Every
+/// option is keyword-only.
+/// 
+/// Args:
+///     text: The input text to clean
+///     preserve_newlines: Keep line structure (default: False). Spaces and
+///                        tabs are still collapsed, single newlines are kept
+///                        and 3+ consecutive newlines become exactly two.
+///     normalize_unicode: Apply NFC normalization so precomposed and
+///                        decomposed accents ("á" vs "a" + U+0301) come
+///                        out identical (default: False)
+///     strip_accents: Remove diacritics for accent-insensitive indexes,
+///                    "presión" -> "presion" (default: False)
+///     keep_enie: With strip_accents, keep "ñ" as is instead of folding it
+///                to "n" ("año" and "ano" differ) (default: True)
+///     lowercase: Lowercase the output, Unicode-aware ("ÚLCERA" -> "úlcera")
+///                (default: False)
+///     remove_urls: Delete http(s):// and www. URLs (default: False)
+///     remove_emails: Delete email addresses (default: False)
+/// 
+/// Returns:
+///     Cleaned text
+#[pyfunction]
+#[pyo3(signature = (
+    text,
+    *,
+    preserve_newlines=false,
+    normalize_unicode=false,
+    strip_accents=false,
+    keep_enie=true,
+    lowercase=false,
+    remove_urls=false,
+    remove_emails=false,
+))]
+#[allow(clippy::too_many_arguments)]
+fn clean_medical_text(
+    py: Python<'_>,
+    text: &str,
+    preserve_newlines: bool,
+    normalize_unicode: bool,
+    strip_accents: bool,
+    keep_enie: bool,
+    lowercase: bool,
+    remove_urls: bool,
+    remove_emails: bool,
+) -> PyResult<String> {
+    let options = CleanOptions {
+        preserve_newlines,
+        normalize_unicode,
+        strip_accents,
+        keep_enie,
+        lowercase,
+        remove_urls,
+        remove_emails,
+    };
+    Ok(allow_threads_if_large(py, text.len(), || clean_medical_text_internal(text, options)))
+}
+
+/// Optional behaviours of clean_medical_text
+#[derive(Debug, Clone, Copy)]
+struct CleanOptions {
+    preserve_newlines: bool,
+    normalize_unicode: bool,
+    strip_accents: bool,
+    keep_enie: bool,
+    lowercase: bool,
+    remove_urls: bool,
+    remove_emails: bool,
+}
+
+impl Default for CleanOptions {
+    fn default() -> Self {
+        Self {
+            preserve_newlines: false,
+            normalize_unicode: false,
+            strip_accents: false,
+            keep_enie: true,
+            lowercase: false,
+            remove_urls: false,
+            remove_emails: false,
+        }
+    }
+}
+
+/// Cleaning core shared by clean_medical_text and its batch variants
+///
+/// Single pass with one output buffer: HTML tags are skipped by the html
+/// scanner, and the text between them is entity-decoded and copied with
+/// control characters (except newlines and tabs) dropped, whitespace runs
+/// collapsed and both ends trimmed. Decoding after stripping keeps
+/// "&lt;b&gt;" as literal text. Unicode normalization runs on the result,
+/// since a combining accent may be separated from its letter by a tag.
+fn clean_medical_text_internal(text: &str, options: CleanOptions) -> String {
+    let mut cleaned = String::with_capacity(text.len());
+    let mut whitespace = WhitespaceCollapser::new(options.preserve_newlines);
+    
+    for piece in html::Pieces::new(text) {
+        match piece {
+            html::Piece::Text(segment) => {
+                let mut decoded = html_escape::decode_html_entities(segment);
+                if options.remove_urls {
+                    decoded = Cow::Owned(URL_RE.replace_all(&decoded, "").into_owned());
+                }
+                if options.remove_emails {
+                    decoded = Cow::Owned(pii::EMAIL_RE.replace_all(&decoded, "").into_owned());
+                }
+                whitespace.push(&mut cleaned, &decoded)
+            }
+            html::Piece::LineBreak => whitespace.push(&mut cleaned, "\n"),
+        }
+    }
+    
+    if options.strip_accents {
+        cleaned = strip_accents(&cleaned, options.keep_enie);
+    } else if options.normalize_unicode && !is_nfc_quick(cleaned.chars()) {
+        cleaned = cleaned.nfc().collect();
+    }
+    if options.lowercase {
+        cleaned = cleaned.to_lowercase();
+    }
+    cleaned
+}
+
+/// Drop combining marks after NFD decomposition ("á" -> "a", "ü" -> "u")
+///
+/// With `keep_enie` the tilde of "ñ"/"Ñ" survives. The result is NFC.
+fn strip_accents(text: &str, keep_enie: bool) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut base = None;
+    for c in text.nfd() {
+        if unicode_normalization::char::is_combining_mark(c) {
+            if keep_enie && c == '\u{303}' && matches!(base, Some('n' | 'N')) {
+                stripped.push(c);
+            }
+            continue;
+        }
+        base = Some(c);
+        stripped.push(c);
+    }
+    if keep_enie {
+        stripped.nfc().collect()
+    } else {
+        stripped
+    }
+}
+
+/// Fold one character to one character, so offsets survive folding
+///
+/// `lowercase` takes the first lowercase letter, `strip_accents` the base
+/// letter of the decomposition; as in strip_accents, with `keep_enie` "ñ"
+/// keeps its tilde ("año" and "ano" stay apart).
+pub(crate) fn fold_char(c: char, lowercase: bool, strip_accents: bool, keep_enie: bool) -> char {
+    let c = if lowercase { c.to_lowercase().next().unwrap_or(c) } else { c };
+    if !strip_accents || (keep_enie && matches!(c, 'ñ' | 'Ñ')) {
+        return c;
+    }
+    c.nfd().next().unwrap_or(c)
+}
+
+/// Cheap check that skips the NFC pass for text already in NFC
+fn is_nfc_quick(chars: impl Iterator<Item = char>) -> bool {
+    unicode_normalization::is_nfc_quick(chars) == unicode_normalization::IsNormalized::Yes
+}
+
+/// Collapses whitespace runs while text is appended segment by segment
+///
+/// A run becomes a single space, or - when newlines are preserved and the
+/// run contains any - one or two newlines. Runs carry across segments;
+/// leading and trailing whitespace of the whole output is never written.
+struct WhitespaceCollapser {
+    preserve_newlines: bool,
+    pending_space: bool,
+    pending_newlines: usize,
+}
+
+impl WhitespaceCollapser {
+    fn new(preserve_newlines: bool) -> Self {
+        Self { preserve_newlines, pending_space: false, pending_newlines: 0 }
+    }
+
+    fn push(&mut self, out: &mut String, segment: &str) {
+        for c in segment.chars() {
+            if is_stripped_control(c) {
+                continue;
+            }
+            if c == '\n' && self.preserve_newlines {
+                self.pending_newlines += 1;
+            } else if c.is_whitespace() {
+                self.pending_space = true;
+            } else {
+                if !out.is_empty() {
+                    match self.pending_newlines {
+                        0 if self.pending_space => out.push(' '),
+                        0 => {}
+                        1 => out.push('\n'),
+                        _ => out.push_str("\n\n"),
+                    }
+                }
+                self.pending_space = false;
+                self.pending_newlines = 0;
+                out.push(c);
+            }
+        }
+    }
+}
+
+
+/// Control characters deleted outright (newline, tab and CR are whitespace)
+fn is_stripped_control(c: char) -> bool {
+    matches!(c, '\x00'..='\x08' | '\x0B' | '\x0C' | '\x0E'..='\x1F')
+}
+
+
+/// Parallel clean_medical_text for large batches
+/// 
+/// Cleans every text on all cores with the GIL released. Every option is
+/// keyword-only.
+/// 
+/// Args:
+///     texts: List of texts to clean
+///     preserve_newlines: Same as in clean_medical_text (default: False)
+///     normalize_unicode: Same as in clean_medical_text (default: False)
+///     strip_accents: Same as in clean_medical_text (default: False)
+///     keep_enie: Same as in clean_medical_text (default: True)
+///     lowercase: Same as in clean_medical_text (default: False)
+///     remove_urls: Same as in clean_medical_text (default: False)
+///     remove_emails: Same as in clean_medical_text (default: False)
+/// 
+/// Returns:
+///     List of cleaned texts, in input order
+#[pyfunction]
+#[pyo3(signature = (
+    texts,
+    *,
+    preserve_newlines=false,
+    normalize_unicode=false,
+    strip_accents=false,
+    keep_enie=true,
+    lowercase=false,
+    remove_urls=false,
+    remove_emails=false,
+))]
+#[allow(clippy::too_many_arguments)]
+fn parallel_clean_texts(
+    py: Python<'_>,
+    texts: Vec<String>,
+    preserve_newlines: bool,
+    normalize_unicode: bool,
+    strip_accents: bool,
+    keep_enie: bool,
+    lowercase: bool,
+    remove_urls: bool,
+    remove_emails: bool,
+) -> PyResult<Vec<String>> {
+    let options = CleanOptions {
+        preserve_newlines,
+        normalize_unicode,
+        strip_accents,
+        keep_enie,
+        lowercase,
+        remove_urls,
+        remove_emails,
+    };
+    Ok(py.allow_threads(|| {
+        texts
+            .par_iter()
+            .map(|text| clean_medical_text_internal(text, options))
+            .collect()
+    }))
+}
+
+
+/// Repair common UTF-8-read-as-Latin-1 artifacts ("mojibake")
+/// 
+/// OCR and legacy exports often turn "presión" into "presiÃ³n" and "°C"
+/// into "Â°C". Only known double-encoded sequences for Spanish letters and
+/// common medical symbols are rewritten; everything else is left as is.
+/// 
+/// Args:
+///     text: The input text
+/// 
+/// Returns:
+///     Text with known mojibake sequences repaired
+#[pyfunction]
+fn fix_mojibake(text: &str) -> PyResult<String> {
+    Ok(fix_mojibake_internal(text))
+}
+
+/// Replace each Latin-1 misreading of UTF-8 listed in MOJIBAKE ("Ã³") with the intended character ("ó")
+fn fix_mojibake_internal(text: &str) -> String {
+    MOJIBAKE_RE
+        .replace_all(text, |caps: &regex::Captures| {
+            let broken = &caps[0];
+            MOJIBAKE
+                .iter()
+                .find(|(b, _)| *b == broken)
+                .map_or(broken, |(_, fixed)| *fixed)
+                .to_string()
+        })
+        .into_owned()
+}
+
+
+/// Parallel chunk processing for large documents
+/// 
+/// The GIL is released while Rayon chunks, so other Python threads keep
+/// running for the duration of the batch.
+/// 
+/// Args:
+///     texts: List of texts to process
+///     chunk_size: Maximum characters (or tokens) per chunk
+///     overlap: Characters (or tokens) to overlap
+///     unit: "chars" or "tokens", as in chunk_text (default: "chars")
+///     delimiters: Sentence terminators, as in chunk_text
+///                 (default: [".", "\n"])
+///     keep_decimals: As in chunk_text (default: True)
+/// 
+/// Returns:
+///     List of (original_index, chunks) tuples
+/// 
+/// Raises:
+///     ValueError: If chunk_size is 0, overlap >= chunk_size, unit is unknown
+///                 or delimiters is empty
+#[pyfunction]
+#[pyo3(signature = (texts, chunk_size=1000, overlap=200, unit="chars", delimiters=None, keep_decimals=true))]
+fn parallel_chunk_texts(
+    py: Python<'_>,
+    texts: Vec<String>,
+    chunk_size: usize,
+    overlap: usize,
+    unit: &str,
+    delimiters: Option<Vec<String>>,
+    keep_decimals: bool,
+) -> PyResult<Vec<(usize, Vec<String>)>> {
+    // Validate parameters once
+    let unit = ChunkUnit::parse(unit)?;
+    validate_chunk_params(chunk_size, overlap)?;
+    let splitter = SentenceSplitter::for_chunks(delimiters, keep_decimals)?;
+    
+    // Process in parallel with the same code path as chunk_text, with the
+    // GIL released so other Python threads keep running meanwhile
+    let results: Vec<(usize, Vec<String>)> = py.allow_threads(|| {
+        texts
+            .par_iter()
+            .enumerate()
+            .map(|(idx, text)| {
+                let chunks = chunk_segments(text, chunk_size, overlap, unit, &splitter)
+                    .into_iter()
+                    .map(|chunk| chunk.text)
+                    .collect();
+                (idx, chunks)
+            })
+            .collect()
+    });
+    
+    Ok(results)
+}
+
+/// Inputs at least this many bytes long are processed with the GIL released
+const GIL_RELEASE_THRESHOLD: usize = 64 * 1024;
+
+/// Run `f` without the GIL when the input is large enough to be worth it
+///
+/// Releasing and re-acquiring the GIL has a fixed cost, so short inputs
+/// (the common case for single notes) keep it.
+fn allow_threads_if_large<T, F>(py: Python<'_>, input_len: usize, f: F) -> T
+where
+    F: Ungil + FnOnce() -> T,
+    T: Ungil,
+{
+    if input_len >= GIL_RELEASE_THRESHOLD {
+        py.allow_threads(f)
+    } else {
+        f()
+    }
+}
+
+/// Byte index where the last `overlap` characters of a chunk start
+///
+/// Snaps forward to the next grapheme boundary so slicing never lands inside
+/// a multi-byte UTF-8 sequence (e.g. "ó" in "presión") or splits a
+/// combining sequence / emoji away from its base.
+fn overlap_start_index(chunk: &str, overlap: usize) -> usize {
+    if overlap == 0 {
+        return chunk.len();
+    }
+    let target = match chunk.char_indices().rev().nth(overlap - 1) {
+        Some((idx, _)) => idx,
+        None => return 0,
+    };
+    chunk
+        .grapheme_indices(true)
+        .map(|(idx, _)| idx)
+        .find(|&idx| idx >= target)
+        .unwrap_or(chunk.len())
+}
+
+/// Abbreviations whose trailing period does not end a sentence (lowercase)
+const ABBREVIATIONS: &[&str] = &[
+    "dr", "dra", "sr", "sra", "srta", "lic", "prof", "ing",
+    "p.ej", "ej", "aprox", "vs", "nro", "núm", "pág", "hosp", "dto", "etc",
+];
+
+/// Default terminators of split_sentences and the sentence counts
+const SENTENCE_DELIMITERS: &[&str] = &[".", "\n", "?", "!"];
+
+/// Default terminators of chunk_text and the other chunkers
+const CHUNK_DELIMITERS: &[&str] = &[".", "\n"];
+
+/// Sentence segmentation rules shared by the chunkers
+#[derive(Clone, Debug)]
+struct SentenceSplitter {
+    /// Sentence terminators (longest match wins)
+    delimiters: Vec<String>,
+    /// Never split on a period between two digits ("37.5")
+    keep_decimals: bool,
+    /// Leave terminators other than "." and whitespace on their sentence,
+    /// so chunks keep "?" and "!" instead of getting ". "
+    keep_terminators: bool,
+}
+
+impl Default for SentenceSplitter {
+    fn default() -> Self {
+        SentenceSplitter {
+            delimiters: SENTENCE_DELIMITERS.iter().map(|d| d.to_string()).collect(),
+            keep_decimals: true,
+            keep_terminators: false,
+        }
+    }
+}
+
+impl SentenceSplitter {
+    /// Build a splitter from user options, falling back to the defaults
+    fn new(delimiters: Option<Vec<String>>, keep_decimals: bool) -> PyResult<Self> {
+        let delimiters = match delimiters {
+            Some(delimiters) => delimiters,
+            None => SentenceSplitter::default().delimiters,
+        };
+        if delimiters.is_empty() || delimiters.iter().any(|d| d.is_empty()) {
+            return Err(PyValueError::new_err("delimiters must be a non-empty list of non-empty strings"));
+        }
+        Ok(SentenceSplitter { delimiters, keep_decimals, keep_terminators: false })
+    }
+
+    /// The splitter of chunk_text with its default options
+    fn chunking() -> Self {
+        SentenceSplitter {
+            delimiters: CHUNK_DELIMITERS.iter().map(|d| d.to_string()).collect(),
+            keep_decimals: true,
+            keep_terminators: true,
+        }
+    }
+
+    /// Build a chunking splitter from user options, falling back to the
+    /// chunk_text defaults
+    fn for_chunks(delimiters: Option<Vec<String>>, keep_decimals: bool) -> PyResult<Self> {
+        let delimiters = delimiters.unwrap_or_else(|| SentenceSplitter::chunking().delimiters);
+        Ok(SentenceSplitter { keep_terminators: true, ..SentenceSplitter::new(Some(delimiters), keep_decimals)? })
+    }
+
+    /// Split text into trimmed, non-empty sentences (see boundary_at)
+    fn split<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        let mut sentences = Vec::new();
+        let mut start = 0;
+        let mut prev: Option<char> = None;
+        let mut chars = text.char_indices().peekable();
+        
+        while let Some((idx, c)) = chars.next() {
+            if let Some(len) = self.boundary_at(text, idx, &text[start..idx], prev) {
+                let d = &text[idx..idx + len];
+                let kept = self.keep_terminators && d != "." && !d.trim().is_empty();
+                sentences.push(&text[start..if kept { idx + d.len() } else { idx }]);
+                start = idx + d.len();
+                // Skip the rest of a multi-char delimiter
+                while chars.peek().is_some_and(|&(i, _)| i < start) {
+                    chars.next();
+                }
+            }
+            prev = Some(c);
+        }
+        sentences.push(&text[start..]);
+        
+        sentences
+            .into_iter()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .collect()
+    }
+
+    /// Byte length of the delimiter that ends a sentence at byte `idx`
+    ///
+    /// `sentence` is the text since the previous boundary and `prev` the
+    /// character before `idx`. A "." delimiter does not end a sentence
+    /// when it is:
+    /// - followed directly by a letter or digit ("p.ej", "I21.0"), including
+    ///   decimals ("37.5") unless keep_decimals is off
+    /// - closing a known abbreviation ("Dr.", "Sra.", "p.ej.")
+    /// - closing a list number that starts the sentence ("1.", "12.")
+    fn boundary_at(&self, text: &str, idx: usize, sentence: &str, prev: Option<char>) -> Option<usize> {
+        let delimiter = self.delimiters
+            .iter()
+            .filter(|d| text[idx..].starts_with(d.as_str()))
+            .max_by_key(|d| d.len())?;
+        let next = text[idx..].chars().nth(1);
+        if delimiter == "." && self.is_period_inside_sentence(prev, next, sentence) {
+            return None;
+        }
+        Some(delimiter.len())
+    }
+
+    /// Whether a "." between `prev` and `next` belongs to the sentence
+    fn is_period_inside_sentence(&self, prev: Option<char>, next: Option<char>, sentence: &str) -> bool {
+        let is_digit = |c: Option<char>| c.is_some_and(|c| c.is_ascii_digit());
+        if is_digit(prev) && is_digit(next) {
+            return self.keep_decimals;
+        }
+        next.is_some_and(char::is_alphanumeric) || ends_with_abbreviation(sentence) || is_list_marker(sentence)
+    }
+}
+
+/// Whether the last word of `prefix` is a known abbreviation
+fn ends_with_abbreviation(prefix: &str) -> bool {
+    let word = prefix
+        .rsplit(|c: char| c.is_whitespace() || c == '(')
+        .next()
+        .unwrap_or("");
+    !word.is_empty() && ABBREVIATIONS.contains(&word.to_lowercase().as_str())
+}
+
+/// Whether `sentence` so far is only a list number ("1", " 12")
+fn is_list_marker(sentence: &str) -> bool {
+    let marker = sentence.trim();
+    (1..=2).contains(&marker.len()) && marker.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Hard-split a sentence longer than `chunk_size` into pieces that fit
+///
+/// Token mode cuts every `chunk_size` words. Char mode cuts at the last
+/// whitespace that keeps the piece within the limit, falling back to a
+/// grapheme boundary for runs with no whitespace at all.
+fn split_oversized(sentence: &str, chunk_size: usize, unit: ChunkUnit) -> Vec<&str> {
+    if unit.measure(sentence) <= chunk_size {
+        return vec![sentence];
+    }
+    
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    match unit {
+        ChunkUnit::Tokens => {
+            for (idx, _) in sentence.unicode_word_indices().step_by(chunk_size).skip(1) {
+                pieces.push(&sentence[start..idx]);
+                start = idx;
+            }
+        }
+        ChunkUnit::Chars => {
+            // Chars in sentence[start..idx], and the last (byte, chars) cut after whitespace
+            let mut len = 0;
+            let mut last_break: Option<(usize, usize)> = None;
+            for (idx, grapheme) in sentence.grapheme_indices(true) {
+                let grapheme_len = grapheme.chars().count();
+                while len + grapheme_len > chunk_size && len > 0 {
+                    let (cut, cut_len) = last_break.take().unwrap_or((idx, len));
+                    pieces.push(&sentence[start..cut]);
+                    start = cut;
+                    len -= cut_len;
+                }
+                len += grapheme_len;
+                if grapheme.chars().all(char::is_whitespace) {
+                    last_break = Some((idx + grapheme.len(), len));
+                }
+            }
+        }
+    }
+    pieces.push(&sentence[start..]);
+    
+    pieces
+        .into_iter()
+        .map(str::trim)
+        .filter(|piece| !piece.is_empty())
+        .collect()
+}
+
+/// Chunking core shared by chunk_text and parallel_chunk_texts
+///
+/// Parameters must already be validated with validate_chunk_params.
+fn chunk_text_internal(text: &str, chunk_size: usize, overlap: usize, unit: ChunkUnit) -> Vec<String> {
+    chunk_segments(text, chunk_size, overlap, unit, &SentenceSplitter::chunking())
+        .into_iter()
+        .map(|chunk| chunk.text)
+        .collect()
+}
+
+/// A chunk together with the source slices it was joined from
+struct Chunk<'a> {
+    /// Segments joined by join_sentences
+    text: String,
+    /// Slices of the original text, in order
+    segments: Vec<&'a str>,
+}
+
+impl Chunk<'_> {
+    /// Byte range of the chunk within `source`
+    fn byte_span(&self, source: &str) -> (usize, usize) {
+        match (self.segments.first(), self.segments.last()) {
+            (Some(first), Some(last)) => (
+                byte_offset(source, first),
+                byte_offset(source, last) + last.len(),
+            ),
+            _ => (0, 0),
+        }
+    }
+}
+
+/// Pack sentences into chunks, remembering which source slices each uses
+fn chunk_segments<'a>(
+    text: &'a str,
+    chunk_size: usize,
+    overlap: usize,
+    unit: ChunkUnit,
+    splitter: &SentenceSplitter,
+) -> Vec<Chunk<'a>> {
+    if text.is_empty() {
+        return vec![];
+    }
+    
+    let sentences = splitter.split(text)
+        .into_iter()
+        .flat_map(|sentence| split_oversized(sentence, chunk_size, unit));
+    let mut packer = Packer::new(chunk_size, overlap, unit);
+    let mut chunks: Vec<Chunk> = Vec::new();
+    for sentence in sentences {
+        if let Some((text, segments)) = packer.push(sentence) {
+            chunks.push(Chunk { text, segments });
+        }
+    }
+    if let Some((text, segments)) = packer.finish() {
+        chunks.push(Chunk { text, segments });
+    }
+    
+    chunks
+}
+
+/// A sentence held by Packer: a slice of the source, or an owned copy
+/// when the source is streamed
+trait Segment: std::borrow::Borrow<str> + Clone {
+    /// The segment from byte `from` on
+    fn tail(&self, from: usize) -> Self;
+}
+
+impl Segment for &str {
+    fn tail(&self, from: usize) -> Self {
+        &self[from..]
+    }
+}
+
+impl Segment for String {
+    fn tail(&self, from: usize) -> Self {
+        self[from..].to_string()
+    }
+}
+
+/// Sentence-to-chunk packing, one sentence at a time
+///
+/// Sentences must already fit in a chunk (see split_oversized). A chunk is
+/// closed when the next sentence would overflow it, and the next chunk
+/// starts with as much of its tail as the overlap allows.
+struct Packer<S> {
+    chunk_size: usize,
+    overlap: usize,
+    unit: ChunkUnit,
+    segments: Vec<S>,
+    /// Length of the joined segments in the requested unit (chars, never bytes)
+    current_len: usize,
+}
+
+impl<S: Segment> Packer<S> {
+    fn new(chunk_size: usize, overlap: usize, unit: ChunkUnit) -> Self {
+        Packer { chunk_size, overlap, unit, segments: Vec::new(), current_len: 0 }
+    }
+
+    /// Add a sentence; returns the chunk it closed, joined and as segments
+    fn push(&mut self, sentence: S) -> Option<(String, Vec<S>)> {
+        let unit = self.unit;
+        let sentence_len = unit.measure(sentence.borrow());
+        let mut closed = None;
+        
+        if let Some(last) = self.segments.last() {
+            if self.current_len + unit.separator_len(last.borrow()) + sentence_len > self.chunk_size {
+                let joined = join_sentences(&self.segments);
+
+                // Carry only as much overlap as still leaves room for this
+                // sentence and the longest separator (". ")
+                let carry = self.overlap.min(self.chunk_size.saturating_sub(sentence_len + unit.separator_len("")));
+                let overlap_start = unit.overlap_start(&joined, carry);
+                let carried = carry_segments(&self.segments, overlap_start);
+                self.current_len = unit.measure(&join_sentences(&carried));
+
+                closed = Some((joined, std::mem::replace(&mut self.segments, carried)));
+            }
+        }
+        
+        if let Some(last) = self.segments.last() {
+            self.current_len += unit.separator_len(last.borrow());
+        }
+        self.segments.push(sentence);
+        self.current_len += sentence_len;
+        closed
+    }
+
+    /// The last, partially filled chunk, if any
+    fn finish(&mut self) -> Option<(String, Vec<S>)> {
+        if self.segments.is_empty() {
+            return None;
+        }
+        self.current_len = 0;
+        let segments = std::mem::take(&mut self.segments);
+        Some((join_sentences(&segments), segments))
+    }
+}
+
+/// What joins a sentence to the next one in a chunk: ". ", or only " "
+/// when the sentence kept its own terminator ("etc.", "¿Dolor?", "HTA;")
+fn sentence_separator(sentence: &str) -> &'static str {
+    if sentence.ends_with(['.', '?', '!', ';', '…']) { " " } else { ". " }
+}
+
+/// Chunk text of packed sentences
+fn join_sentences<S: Segment>(segments: &[S]) -> String {
+    let mut joined = String::new();
+    for (i, segment) in segments.iter().enumerate() {
+        if i > 0 {
+            joined.push_str(sentence_separator(segments[i - 1].borrow()));
+        }
+        joined.push_str(segment.borrow());
+    }
+    joined
+}
+
+/// Segments covering `join_sentences(segments)[overlap_start..]`
+///
+/// A start that falls inside a separator moves to the next segment.
+fn carry_segments<S: Segment>(segments: &[S], overlap_start: usize) -> Vec<S> {
+    let mut pos = 0;
+    for (i, seg) in segments.iter().enumerate() {
+        let len = seg.borrow().len();
+        if overlap_start < pos + len {
+            let mut carried = vec![seg.tail(overlap_start.saturating_sub(pos))];
+            carried.extend_from_slice(&segments[i + 1..]);
+            return carried;
+        }
+        pos += len + sentence_separator(seg.borrow()).len();
+    }
+    Vec::new()
+}
+
+/// Byte offset of `slice` within `source` (slice must borrow from source)
+fn byte_offset(source: &str, slice: &str) -> usize {
+    slice.as_ptr() as usize - source.as_ptr() as usize
+}
+
+/// Converts ascending byte offsets into char offsets in a single forward pass
+struct CharCursor<'a> {
+    text: &'a str,
+    byte: usize,
+    chars: usize,
+}
+
+impl<'a> CharCursor<'a> {
+    fn new(text: &'a str) -> Self {
+        CharCursor { text, byte: 0, chars: 0 }
+    }
+
+    fn char_offset(&mut self, byte: usize) -> usize {
+        if byte < self.byte {
+            // Out-of-order offset: restart from the beginning
+            self.byte = 0;
+            self.chars = 0;
+        }
+        self.chars += self.text[self.byte..byte].chars().count();
+        self.byte = byte;
+        self.chars
+    }
+}
+
+
+/// Split `text` into contiguous pieces of at most `chunk_size` chars
+///
+/// Uses the first separator found in the text, recursing with the
+/// remaining separators on pieces that are still too long.
+fn recursive_pieces<'a>(text: &'a str, separators: &[String], chunk_size: usize) -> Vec<&'a str> {
+    if text.chars().count() <= chunk_size {
+        return vec![text];
+    }
+    
+    let found = separators
+        .iter()
+        .position(|sep| !sep.is_empty() && text.contains(sep.as_str()));
+    let Some(i) = found else {
+        return grapheme_pieces(text, chunk_size);
+    };
+    
+    let mut pieces = Vec::new();
+    for piece in text.split_inclusive(separators[i].as_str()) {
+        if piece.chars().count() <= chunk_size {
+            pieces.push(piece);
+        } else {
+            pieces.extend(recursive_pieces(piece, &separators[i + 1..], chunk_size));
+        }
+    }
+    pieces
+}
+
+/// Split `text` into pieces of at most `limit` chars on grapheme boundaries
+fn grapheme_pieces(text: &str, limit: usize) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    let mut len = 0;
+    for (idx, grapheme) in text.grapheme_indices(true) {
+        let grapheme_len = grapheme.chars().count();
+        if len + grapheme_len > limit && len > 0 {
+            pieces.push(&text[start..idx]);
+            start = idx;
+            len = 0;
+        }
+        len += grapheme_len;
+    }
+    pieces.push(&text[start..]);
+    pieces
+}
+
+/// Greedily merge contiguous pieces into chunks, LangChain style
+///
+/// When a chunk is full, pieces are dropped from its front until at most
+/// `overlap` chars remain (and the next piece fits); those carry over.
+fn merge_pieces<'a>(text: &'a str, pieces: &[&'a str], chunk_size: usize, overlap: usize) -> Vec<&'a str> {
+    let mut chunks = Vec::new();
+    let mut window: std::collections::VecDeque<(&str, usize)> = std::collections::VecDeque::new();
+    let mut total = 0;
+    
+    let emit = |window: &std::collections::VecDeque<(&'a str, usize)>, chunks: &mut Vec<&'a str>| {
+        if let (Some((first, _)), Some((last, _))) = (window.front(), window.back()) {
+            let start = byte_offset(text, first);
+            let end = byte_offset(text, last) + last.len();
+            let chunk = text[start..end].trim();
+            if !chunk.is_empty() {
+                chunks.push(chunk);
+            }
+        }
+    };
+    
+    for &piece in pieces {
+        let len = piece.chars().count();
+        if total + len > chunk_size && !window.is_empty() {
+            emit(&window, &mut chunks);
+            while total > overlap || (total + len > chunk_size && total > 0) {
+                let Some((_, dropped)) = window.pop_front() else { break };
+                total -= dropped;
+            }
+        }
+        window.push_back((piece, len));
+        total += len;
+    }
+    emit(&window, &mut chunks);
+    
+    chunks
+}
+
+
+/// Extract medical entities (regex-based)
+/// 
+/// Extracts:
+/// - Dates (DD/MM/YYYY, DD-MM-YYYY, "15 de enero de 2026", "3 mar 2025")
+/// - Times (HH:MM, HH:MM:SS, "14:30 hs", "14.30 hs", "2:30 pm")
+/// - Measurements (numbers with units: mg, ml, g/dL, mEq/L, mmHg, °C, etc.,
+///   ranges such as "10-20 mg" and per-dose units such as "mg/kg/día")
+/// - ICD-10 codes ("I21.0", "J45.909", "E11"), in document order
+/// 
+/// Dates are checked against the calendar: impossible days or months
+/// ("45/23/0000", "31/04/2025") and four-digit years outside
+/// min_year..=max_year are dropped. February 29 needs a leap year when the
+/// year has four digits; two-digit years are not range-checked. Times
+/// need hours 0-23 (1-12 with am/pm) and minutes/seconds 0-59, and are
+/// skipped after a ratio word ("dilución 1:10", "relación 1:30"). A 12-hour
+/// time keeps its am/pm suffix ("2:30 p.m.") unless normalize_times is set.
+/// 
+/// Inputs of 64 KiB or more are scanned with the GIL released.
+/// 
+/// Args:
+///     text: The input text
+///     min_year: Earliest accepted year (default: 1900)
+///     max_year: Latest accepted year (default: 2100)
+///     normalize_times: Report times as HH:MM[:SS] in 24-hour format
+///                      instead of the matched text (default: False)
+///     extra_units: Measurement units to recognize on top of the default
+///                  list (case-insensitive)
+///     normalize_dates: Report dates with a four-digit year as ISO
+///                      YYYY-MM-DD ("15 de enero de 2026" -> "2026-01-15");
+///                      dates without one are kept as written (default: False)
+/// 
+/// Returns:
+///     Dict with extracted entities: {"dates": [...], "times": [...],
+///     "measurements": [...], "icd10": [...], "percentages": [...],
+///     "ratios": [...]}. Percentages are also measurements ("%" is a
+///     unit); ratios are the value after a keyword such as "INR" or
+///     "dilución" ("INR 2.5" -> "2.5"), or after "relación" when it names
+///     the two sides or is written N:M ("relación A/G 1,2", "relación 3:1").
+/// 
+/// Raises:
+///     ValueError: If min_year is greater than max_year
+#[pyfunction]
+#[pyo3(signature = (text, min_year=1900, max_year=2100, normalize_times=false, extra_units=None, normalize_dates=false))]
+fn extract_entities(
+    py: Python<'_>,
+    text: &str,
+    min_year: i32,
+    max_year: i32,
+    normalize_times: bool,
+    extra_units: Option<Vec<String>>,
+    normalize_dates: bool,
+) -> PyResult<std::collections::HashMap<String, Vec<String>>> {
+    let options = EntityOptions {
+        normalize_times,
+        normalize_dates,
+        extra_units: extra_units.unwrap_or_default(),
+        ..entity_options(min_year, max_year)?
+    };
+    Ok(allow_threads_if_large(py, text.len(), || extract_entities_internal(text, &options)))
+}
+
+/// extract_entities with the categories as attributes instead of dict keys
+/// 
+/// Same arguments and matches as extract_entities; a misspelled attribute
+/// raises AttributeError where a misspelled key would go unnoticed.
+/// 
+/// Returns:
+///     An Entities object with dates, times, measurements, icd10,
+///     percentages and ratios lists
+/// 
+/// Raises:
+///     ValueError: If min_year is greater than max_year
+#[pyfunction]
+#[pyo3(signature = (text, min_year=1900, max_year=2100, normalize_times=false, extra_units=None, normalize_dates=false))]
+fn extract_entities_typed(
+    py: Python<'_>,
+    text: &str,
+    min_year: i32,
+    max_year: i32,
+    normalize_times: bool,
+    extra_units: Option<Vec<String>>,
+    normalize_dates: bool,
+) -> PyResult<Entities> {
+    extract_entities(py, text, min_year, max_year, normalize_times, extra_units, normalize_dates).map(Entities::from)
+}
+
+/// Count each distinct entity per category
+/// 
+/// Same matches as extract_entities, aggregated: "500mg" found 12 times is
+/// one entry with count 12. With normalize, different spellings of the
+/// same entity count together under one canonical form: "500 mg", "500mg"
+/// and "500 MG" are "500 mg"; "15/01/2026" and "15 de enero de 2026" are
+/// "2026-01-15"; "14.30 hs" and "14:30" are "14:30"; other categories are
+/// compared without case or repeated spaces.
+/// 
+/// Args:
+///     text: The input text
+///     normalize: Merge spellings of the same entity (default: False)
+///     min_year: Earliest accepted year (default: 1900)
+///     max_year: Latest accepted year (default: 2100)
+///     extra_units: Measurement units to recognize on top of the default list
+/// 
+/// Returns:
+///     Dict of category -> dict of entity -> count, with every category
+///     present and entities in first-seen order. Entities are the first
+///     spelling seen, or the canonical form with normalize.
+/// 
+/// Raises:
+///     ValueError: If min_year is greater than max_year
+#[pyfunction]
+#[pyo3(signature = (text, normalize=false, min_year=1900, max_year=2100, extra_units=None))]
+fn extract_entity_counts<'py>(
+    py: Python<'py>,
+    text: &str,
+    normalize: bool,
+    min_year: i32,
+    max_year: i32,
+    extra_units: Option<Vec<String>>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let options = EntityOptions { extra_units: extra_units.unwrap_or_default(), ..entity_options(min_year, max_year)? };
+    let counts = allow_threads_if_large(py, text.len(), || entity_counts(text, &options, normalize));
+    let result = PyDict::new_bound(py);
+    for (category, found) in counts {
+        let entities = PyDict::new_bound(py);
+        for (entity, count) in found {
+            entities.set_item(entity, count)?;
+        }
+        result.set_item(category, entities)?;
+    }
+    Ok(result)
+}
+
+/// (entity, count) per category, entities in first-seen order
+type EntityCounts = Vec<(&'static str, Vec<(String, usize)>)>;
+
+fn entity_counts(text: &str, options: &EntityOptions, normalize: bool) -> EntityCounts {
+    entity_matches(text, options)
+        .into_iter()
+        .map(|(category, found)| {
+            let mut counts: Vec<(String, usize)> = Vec::new();
+            let mut index_of: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+            for m in found {
+                let entity = if normalize {
+                    postprocess::canonical(category, m.as_str())
+                } else {
+                    postprocess::normalize(category, m.as_str(), options)
+                };
+                match index_of.get(&entity) {
+                    Some(&index) => counts[index].1 += 1,
+                    None => {
+                        index_of.insert(entity.clone(), counts.len());
+                        counts.push((entity, 1));
+                    }
+                }
+            }
+            (category, counts)
+        })
+        .collect()
+}
+
+/// Entities found by extract_entities_typed, one list per category
+#[pyclass(module = "ferro_engine", get_all)]
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Entities {
+    dates: Vec<String>,
+    times: Vec<String>,
+    measurements: Vec<String>,
+    icd10: Vec<String>,
+    percentages: Vec<String>,
+    ratios: Vec<String>,
+}
+
+#[pymethods]
+impl Entities {
+    /// The same dict extract_entities returns
+    fn to_dict(&self) -> std::collections::HashMap<String, Vec<String>> {
+        [
+            ("dates", &self.dates),
+            ("times", &self.times),
+            ("measurements", &self.measurements),
+            ("icd10", &self.icd10),
+            ("percentages", &self.percentages),
+            ("ratios", &self.ratios),
+        ]
+        .into_iter()
+        .map(|(category, found)| (category.to_string(), found.clone()))
+        .collect()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Entities(dates={:?}, times={:?}, measurements={:?}, icd10={:?}, percentages={:?}, ratios={:?})",
+            self.dates, self.times, self.measurements, self.icd10, self.percentages, self.ratios
+        )
+    }
+}
+
+impl From<std::collections::HashMap<String, Vec<String>>> for Entities {
+    fn from(mut found: std::collections::HashMap<String, Vec<String>>) -> Self {
+        let mut take = |category: &str| found.remove(category).unwrap_or_default();
+        Entities {
+            dates: take("dates"),
+            times: take("times"),
+            measurements: take("measurements"),
+            icd10: take("icd10"),
+            percentages: take("percentages"),
+            ratios: take("ratios"),
+        }
+    }
+}
+
+/// Batch extract_entities over many documents
+/// 
+/// Texts are processed in parallel with the GIL released, all sharing the
+/// pre-compiled patterns.
+/// 
+/// Args:
+///     texts: List of documents
+///     min_year: Earliest accepted year (default: 1900)
+///     max_year: Latest accepted year (default: 2100)
+///     normalize_times: Same as in extract_entities (default: False)
+///     extra_units: Same as in extract_entities
+///     normalize_dates: Same as in extract_entities (default: False)
+/// 
+/// Returns:
+///     One extract_entities dict per text, in input order
+/// 
+/// Raises:
+///     ValueError: If min_year is greater than max_year
+#[pyfunction]
+#[pyo3(signature = (texts, min_year=1900, max_year=2100, normalize_times=false, extra_units=None, normalize_dates=false))]
+fn parallel_extract_entities(
+    py: Python<'_>,
+    texts: Vec<String>,
+    min_year: i32,
+    max_year: i32,
+    normalize_times: bool,
+    extra_units: Option<Vec<String>>,
+    normalize_dates: bool,
+) -> PyResult<Vec<std::collections::HashMap<String, Vec<String>>>> {
+    let options = EntityOptions {
+        normalize_times,
+        normalize_dates,
+        extra_units: extra_units.unwrap_or_default(),
+        ..entity_options(min_year, max_year)?
+    };
+    Ok(py.allow_threads(|| texts.par_iter().map(|text| extract_entities_internal(text, &options)).collect()))
+}
+
+/// Entity extraction core shared by extract_entities and its batch variants
+fn extract_entities_internal(text: &str, options: &EntityOptions) -> std::collections::HashMap<String, Vec<String>> {
+    entity_matches(text, options)
+        .into_iter()
+        .map(|(category, found)| {
+            let found = found.iter().map(|m| postprocess::normalize(category, m.as_str(), options)).collect();
+            (category.to_string(), found)
+        })
+        .collect()
+}
+
+/// Built-in entity categories and their pre-compiled patterns
+/// 
+/// The measurement pattern depends on the unit list, so it is passed in.
+/// A pattern with a `value` group reports that group instead of the whole
+/// match.
+fn entity_patterns(measure_re: &Regex) -> [(&'static str, &Regex); 6] {
+    [
+        ("dates", &*DATE_RE),
+        ("times", &*TIME_RE),
+        // Measurements (number + unit)
+        ("measurements", measure_re),
+        ("icd10", &*ICD10_RE),
+        ("percentages", &*PERCENT_RE),
+        ("ratios", &*RATIO_RE),
+    ]
+}
+
+/// Matches of every category that pass semantic validation
+fn entity_matches<'t>(text: &'t str, options: &EntityOptions) -> Vec<(&'static str, Vec<regex::Match<'t>>)> {
+    let measure_re = measurements::regex_with_extra_units(&options.extra_units);
+    entity_patterns(&measure_re)
+        .into_iter()
+        .map(|(category, re)| {
+            let candidates: Vec<regex::Match<'t>> = if re.capture_names().any(|name| name == Some("value")) {
+                re.captures_iter(text).filter_map(|caps| caps.name("value").or_else(|| caps.name("pair"))).collect()
+            } else {
+                re.find_iter(text).collect()
+            };
+            let found = candidates
+                .into_iter()
+                .filter(|m| postprocess::is_valid(category, m.as_str(), options))
+                .filter(|m| category != "icd10" || icd10::reads_as_code(text, m.start(), m.end()))
+                .filter(|m| category != "times" || !postprocess::follows_ratio_word(&text[..m.start()]))
+                .filter(|m| category != "ratios" || !postprocess::continues_as_date(&text[m.end()..]))
+                .collect();
+            (category, found)
+        })
+        .collect()
+}
+
+fn entity_options(min_year: i32, max_year: i32) -> PyResult<EntityOptions> {
+    if min_year > max_year {
+        return Err(PyValueError::new_err("min_year must not be greater than max_year"));
+    }
+    Ok(EntityOptions { years: min_year..=max_year, ..EntityOptions::default() })
+}
+
+/// Matched text with its character (not byte) start and end offsets
+type Spans = Vec<(String, usize, usize)>;
+
+
+/// Extract medical entities together with their positions
+/// 
+/// Same categories and matches as extract_entities, but each match comes
+/// with character offsets into `text` (not UTF-8 byte offsets, which drift
+/// after every accented letter). Categories are matched independently, so
+/// overlapping matches from different categories are all reported.
+/// 
+/// Args:
+///     text: The input text
+///     min_year: Earliest accepted year (default: 1900)
+///     max_year: Latest accepted year (default: 2100)
+///     extra_units: Measurement units to recognize on top of the default list
+/// 
+/// Returns:
+///     Dict of category -> list of (match, start_char, end_char)
+/// 
+/// Raises:
+///     ValueError: If min_year is greater than max_year
+#[pyfunction]
+#[pyo3(signature = (text, min_year=1900, max_year=2100, extra_units=None))]
+fn extract_entities_with_spans(
+    py: Python<'_>,
+    text: &str,
+    min_year: i32,
+    max_year: i32,
+    extra_units: Option<Vec<String>>,
+) -> PyResult<std::collections::HashMap<String, Spans>> {
+    let options = EntityOptions { extra_units: extra_units.unwrap_or_default(), ..entity_options(min_year, max_year)? };
+    Ok(allow_threads_if_large(py, text.len(), || extract_entities_with_spans_internal(text, &options)))
+}
+
+fn extract_entities_with_spans_internal(text: &str, options: &EntityOptions) -> std::collections::HashMap<String, Spans> {
+    entity_matches(text, options)
+        .into_iter()
+        .map(|(category, found)| {
+            // Matches come in ascending order, so one cursor covers them all
+            let mut cursor = CharCursor::new(text);
+            let spans = found
+                .iter()
+                .map(|m| {
+                    let start = cursor.char_offset(m.start());
+                    let end = cursor.char_offset(m.end());
+                    (m.as_str().to_string(), start, end)
+                })
+                .collect();
+            (category.to_string(), spans)
+        })
+        .collect()
+}
+
+
+/// Python module definition
+#[pymodule]
+fn ferro_engine(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(chunk_text, m)?)?;
+    m.add_function(wrap_pyfunction!(chunk_iter::chunk_text_iter, m)?)?;
+    m.add_function(wrap_pyfunction!(chunk_iter::chunk_file_iter, m)?)?;
+    m.add_class::<chunk_iter::ChunkIterator>()?;
+    m.add_function(wrap_pyfunction!(chunk_text_with_offsets, m)?)?;
+    m.add_function(wrap_pyfunction!(chunk_by_tokens, m)?)?;
+    m.add_function(wrap_pyfunction!(split_sentences, m)?)?;
+    m.add_function(wrap_pyfunction!(split_sentences_with_offsets, m)?)?;
+    m.add_function(wrap_pyfunction!(sentence_token_counts, m)?)?;
+    m.add_function(wrap_pyfunction!(recursive_chunk_text, m)?)?;
+    m.add_function(wrap_pyfunction!(sections::split_sections, m)?)?;
+    m.add_function(wrap_pyfunction!(sections::chunk_by_sections, m)?)?;
+    m.add_function(wrap_pyfunction!(tokenize, m)?)?;
+    m.add_function(wrap_pyfunction!(remove_stopwords, m)?)?;
+    m.add_function(wrap_pyfunction!(tokenize_filtered, m)?)?;
+    m.add_function(wrap_pyfunction!(parallel_tokenize, m)?)?;
+    m.add_function(wrap_pyfunction!(stem::stem_tokens, m)?)?;
+    m.add_function(wrap_pyfunction!(stem::tokenize_and_stem, m)?)?;
+    m.add_function(wrap_pyfunction!(tokenize_with_spans, m)?)?;
+    m.add_function(wrap_pyfunction!(tokenize_with_offsets, m)?)?;
+    m.add_function(wrap_pyfunction!(truncate_to_tokens, m)?)?;
+    m.add_function(wrap_pyfunction!(truncate_to_chars, m)?)?;
+    m.add_function(wrap_pyfunction!(ngrams, m)?)?;
+    m.add_function(wrap_pyfunction!(parallel_ngrams, m)?)?;
+    m.add_function(wrap_pyfunction!(char_ngrams, m)?)?;
+    m.add_function(wrap_pyfunction!(similarity::similarity, m)?)?;
+    m.add_function(wrap_pyfunction!(similarity::cosine_similarity, m)?)?;
+    m.add_function(wrap_pyfunction!(similarity::jaccard_similarity, m)?)?;
+    m.add_function(wrap_pyfunction!(similarity::pairwise_similarity, m)?)?;
+    m.add_function(wrap_pyfunction!(dedup::dedup_chunks, m)?)?;
+    m.add_function(wrap_pyfunction!(minhash::minhash_signatures, m)?)?;
+    m.add_function(wrap_pyfunction!(minhash::find_near_duplicates, m)?)?;
+    m.add_function(wrap_pyfunction!(minhash::near_dups, m)?)?;
+    m.add_function(wrap_pyfunction!(fuzzy::levenshtein, m)?)?;
+    m.add_function(wrap_pyfunction!(fuzzy::similarity_ratio, m)?)?;
+    m.add_function(wrap_pyfunction!(fuzzy::fuzzy_find, m)?)?;
+    m.add_function(wrap_pyfunction!(fuzzy::fuzzy_match_terms, m)?)?;
+    m.add_function(wrap_pyfunction!(keywords::extract_keywords, m)?)?;
+    m.add_function(wrap_pyfunction!(keywords::extract_keywords_single, m)?)?;
+    m.add_function(wrap_pyfunction!(language::detect_language, m)?)?;
+    m.add_function(wrap_pyfunction!(language::detect_language_batch, m)?)?;
+    m.add_class::<bm25::Bm25Index>()?;
+    m.add_class::<dictionary::DictionaryMatcher>()?;
+    m.add_class::<processor::FerroProcessor>()?;
+    m.add_function(wrap_pyfunction!(abbreviations::expand_abbreviations, m)?)?;
+    m.add_function(wrap_pyfunction!(snippet::make_snippet, m)?)?;
+    m.add_function(wrap_pyfunction!(readability::readability, m)?)?;
+    m.add_function(wrap_pyfunction!(stats::text_stats, m)?)?;
+    m.add_function(wrap_pyfunction!(count_tokens, m)?)?;
+    m.add_function(wrap_pyfunction!(count_tokens_detailed, m)?)?;
+    m.add_function(wrap_pyfunction!(parallel_count_tokens, m)?)?;
+    m.add_function(wrap_pyfunction!(bpe::count_tokens_bpe, m)?)?;
+    m.add_function(wrap_pyfunction!(bpe::load_tokenizer, m)?)?;
+    m.add_function(wrap_pyfunction!(bpe::count_tokens_exact, m)?)?;
+    m.add_function(wrap_pyfunction!(bpe::count_tokens_exact_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(clean_medical_text, m)?)?;
+    m.add_function(wrap_pyfunction!(fix_mojibake, m)?)?;
+    m.add_function(wrap_pyfunction!(parallel_clean_texts, m)?)?;
+    m.add_function(wrap_pyfunction!(parallel_chunk_texts, m)?)?;
+    m.add_function(wrap_pyfunction!(extract_entities, m)?)?;
+    m.add_function(wrap_pyfunction!(extract_entities_with_spans, m)?)?;
+    m.add_function(wrap_pyfunction!(extract_entities_typed, m)?)?;
+    m.add_function(wrap_pyfunction!(extract_entity_counts, m)?)?;
+    m.add_function(wrap_pyfunction!(icd10::extract_icd10, m)?)?;
+    m.add_class::<Entities>()?;
+    m.add_function(wrap_pyfunction!(parallel_extract_entities, m)?)?;
+    m.add_function(wrap_pyfunction!(custom::extract_custom, m)?)?;
+    m.add_function(wrap_pyfunction!(measurements::extract_measurements, m)?)?;
+    m.add_function(wrap_pyfunction!(measurements::extract_measurements_structured, m)?)?;
+    m.add_function(wrap_pyfunction!(dosage::extract_dosages, m)?)?;
+    m.add_function(wrap_pyfunction!(dosage::extract_medications, m)?)?;
+    m.add_function(wrap_pyfunction!(vitals::extract_vitals, m)?)?;
+    m.add_function(wrap_pyfunction!(vitals::extract_blood_pressure, m)?)?;
+    m.add_function(wrap_pyfunction!(labs::extract_lab_values, m)?)?;
+    m.add_function(wrap_pyfunction!(labs::extract_lab_results, m)?)?;
+    m.add_function(wrap_pyfunction!(demographics::extract_ages, m)?)?;
+    m.add_function(wrap_pyfunction!(demographics::extract_demographics, m)?)?;
+    m.add_function(wrap_pyfunction!(keyvalue::extract_key_values, m)?)?;
+    m.add_function(wrap_pyfunction!(pii::redact_pii, m)?)?;
+    m.add_function(wrap_pyfunction!(pii::find_pii, m)?)?;
+    m.add_function(wrap_pyfunction!(date_shift::shift_dates, m)?)?;
+    m.add_function(wrap_pyfunction!(relative_dates::extract_relative_dates, m)?)?;
+    
+    // Module metadata - v3.0.0 FERRO compliant
+    m.add("__version__", "3.0.0")?;
+    m.add("__doc__", "FERRO Protocol v3.0.0 - Rust CPU-bound engine for text processing")?;
+    
+    Ok(())
+}
+
+
+/// Run `f` with the GIL held, initializing the interpreter on first use
+#[cfg(test)]
+pub(crate) fn with_py<R>(f: impl FnOnce(Python<'_>) -> R) -> R {
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(f)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::types::IntoPyDict;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_chunk_text_empty() {
+        let result = chunk_text_internal("", 100, 20, ChunkUnit::Chars);
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn test_chunk_text_basic() {
+        let text = "First sentence. Second sentence. Third sentence.";
+        let result = chunk_text_internal(text, 50, 10, ChunkUnit::Chars);
+        assert!(!result.is_empty());
+    }
+
+    #[test]
+    fn test_chunk_text_overlap_on_multibyte_boundary() {
+        // "presión" puts the 2-byte "ó" inside the 2-char overlap window
+        let text = "Control de presión. Paciente estable";
+        let result = chunk_text_internal(text, 20, 2, ChunkUnit::Chars);
+        assert_eq!(result, vec!["Control de presión", "ón. Paciente estable"]);
+        assert_eq!(chunk_text(text, 20, 2, "chars", None, true, false, false).unwrap(), result);
+    }
+
+    #[test]
+    fn test_chunk_text_overlap_never_splits_chars() {
+        let text = "Niño con fiebre de 39°C. Náuseas y vómitos. Dolor abdominal difuso. \
+                    Se indica hidratación. Control en 24 horas 👨‍⚕️. Alta médica";
+        for chunk_size in 10..40 {
+            for overlap in 0..chunk_size {
+                // Panics on a bad slice; every chunk must also be valid UTF-8
+                for chunk in chunk_text_internal(text, chunk_size, overlap, ChunkUnit::Chars) {
+                    assert!(!chunk.is_empty());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_chunk_size_counts_chars_not_bytes() {
+        let accented = "Evolución clínica estable. Presión arterial normal. \
+                        Auscultación pulmonar sin ruidos agregados. Diagnóstico pendiente";
+        let plain = "Evolucion clinica estable. Presion arterial normal. \
+                     Auscultacion pulmonar sin ruidos agregados. Diagnostico pendiente";
+        let accented_chunks = chunk_text_internal(accented, 60, 0, ChunkUnit::Chars);
+        let plain_chunks = chunk_text_internal(plain, 60, 0, ChunkUnit::Chars);
+        assert_eq!(accented_chunks.len(), plain_chunks.len());
+        for (a, p) in accented_chunks.iter().zip(&plain_chunks) {
+            assert_eq!(a.chars().count(), p.chars().count());
+            assert!(a.chars().count() <= 60);
+        }
+        // Overlap is measured in chars as well; carrying it must not push a
+        // chunk past chunk_size, so with no room left it is dropped
+        let chunks = chunk_text_internal("Niño sano. Niño enfermo", 12, 4, ChunkUnit::Chars);
+        assert_eq!(chunks, vec!["Niño sano", "Niño enfermo"]);
+        let chunks = chunk_text_internal("Niño sano. Niño enfermo", 20, 4, ChunkUnit::Chars);
+        assert_eq!(chunks, vec!["Niño sano", "sano. Niño enfermo"]);
+    }
+
+    #[test]
+    fn test_split_sentences_keeps_decimals_and_abbreviations() {
+        let text = "Temperatura 37.5 °C. Dosis 1.5 mg cada 8 hs. pH 7.35\n\
+                    Visto por Dr. Pérez y la Sra. Gómez, p.ej. en guardia. Alta";
+        assert_eq!(
+            SentenceSplitter::default().split(text),
+            vec![
+                "Temperatura 37.5 °C",
+                "Dosis 1.5 mg cada 8 hs",
+                "pH 7.35",
+                "Visto por Dr. Pérez y la Sra. Gómez, p.ej. en guardia",
+                "Alta",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_sentences_lists_abbreviations_and_offsets() {
+        let text = "Control con la Dra. Núñez, etc. en 7 días. Indicaciones:\n\
+                    1. Paracetamol 0.5 g c/8 hs.\n\
+                    2. Enalapril 10 mg/día\n\
+                    3. Dieta hiposódica, etc.\n\
+                    ¿Consulta? Sí.";
+        let expected = vec![
+            "Control con la Dra. Núñez, etc. en 7 días",
+            "Indicaciones:",
+            "1. Paracetamol 0.5 g c/8 hs",
+            "2. Enalapril 10 mg/día",
+            "3. Dieta hiposódica, etc.",
+            "¿Consulta",
+            "Sí",
+        ];
+        assert_eq!(split_sentences(text, None, true).unwrap(), expected);
+
+        let with_offsets = split_sentences_with_offsets(text, None, true).unwrap();
+        let chars: Vec<char> = text.chars().collect();
+        for (sentence, start, end) in &with_offsets {
+            assert_eq!(&chars[*start..*end].iter().collect::<String>(), sentence);
+        }
+        assert_eq!(with_offsets.iter().map(|(s, _, _)| s.as_str()).collect::<Vec<_>>(), expected);
+
+        // chunk_text splits on "." and line breaks only, without doubling the
+        // period of "etc."; a "?" delimiter stays on its sentence
+        let expected = vec![
+            "Control con la Dra. Núñez, etc. en 7 días. Indicaciones:. 1. Paracetamol 0.5 g c/8 hs. \
+             2. Enalapril 10 mg/día. 3. Dieta hiposódica, etc. ¿Consulta? Sí",
+        ];
+        assert_eq!(chunk_text(text, 1000, 0, "chars", None, true, false, false).unwrap(), expected);
+        let delimiters = Some(vec![".".to_string(), "\n".to_string(), "?".to_string()]);
+        assert_eq!(chunk_text(text, 1000, 0, "chars", delimiters, true, false, false).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_split_sentences_mixed_terminators() {
+        let text = "Paciente de 58 años, derivado por el Dr. Ruiz. ¿Dolor torácico? \
+                    Niega! Refiere fiebre de 38.2 °C desde ayer\n\
+                    Plan: control   ";
+        assert_eq!(
+            split_sentences(text, None, true).unwrap(),
+            vec![
+                "Paciente de 58 años, derivado por el Dr. Ruiz",
+                "¿Dolor torácico",
+                "Niega",
+                "Refiere fiebre de 38.2 °C desde ayer",
+                "Plan: control",
+            ]
+        );
+        assert!(split_sentences("  \n. \n", None, true).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_split_sentences_custom_delimiters() {
+        let splitter = SentenceSplitter::new(
+            Some(vec![".".into(), "\n".into(), "?".into(), "!".into(), ";".into(), "...".into()]),
+            true,
+        ).unwrap();
+        let text = "¿Tiene dolor? Sí, en epigastrio; irradiado a espalda... \
+                    Se indica omeprazol 0.5 mg. ¡Control en 48 hs!";
+        assert_eq!(
+            splitter.split(text),
+            vec![
+                "¿Tiene dolor",
+                "Sí, en epigastrio",
+                "irradiado a espalda",
+                "Se indica omeprazol 0.5 mg",
+                "¡Control en 48 hs",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_sentences_keep_decimals_flag() {
+        let text = "Dosis 0.5 mg. Temperatura 37.8";
+        let strict = SentenceSplitter::new(None, true).unwrap();
+        assert_eq!(strict.split(text), vec!["Dosis 0.5 mg", "Temperatura 37.8"]);
+        let loose = SentenceSplitter::new(None, false).unwrap();
+        assert_eq!(loose.split(text), vec!["Dosis 0", "5 mg", "Temperatura 37", "8"]);
+        assert!(SentenceSplitter::new(Some(vec![]), true).is_err());
+        assert!(SentenceSplitter::new(Some(vec!["".into()]), true).is_err());
+    }
+
+    #[test]
+    fn test_chunk_text_custom_delimiters() {
+        let text = "¿Fiebre? No. ¿Tos? Sí; productiva";
+        let delimiters = Some(vec!["?".to_string(), ";".to_string()]);
+        let chunks = chunk_text(text, 12, 0, "chars", delimiters, true, false, false).unwrap();
+        assert_eq!(chunks, vec!["¿Fiebre?", "No. ¿Tos?", "Sí;", "productiva"]);
+        let chunks = chunk_text(text, 1000, 0, "chars", Some(vec!["?".to_string(), ";".to_string()]), true, false, false).unwrap();
+        assert_eq!(chunks, vec![text]);
+    }
+
+    #[test]
+    fn test_chunk_text_keeps_numeric_values_intact() {
+        let text = "Temperatura 37.5 °C. Se indica paracetamol 1.5 g. pH 7.35 normal";
+        let chunks = chunk_text_internal(text, 1000, 0, ChunkUnit::Chars);
+        assert_eq!(chunks, vec!["Temperatura 37.5 °C. Se indica paracetamol 1.5 g. pH 7.35 normal"]);
+        assert_eq!(
+            chunk_text_internal(text, 30, 0, ChunkUnit::Chars),
+            vec!["Temperatura 37.5 °C", "Se indica paracetamol 1.5 g", "pH 7.35 normal"]
+        );
+    }
+
+    #[test]
+    fn test_chunk_text_token_unit() {
+        let text = "Paciente de 45 años con dolor torácico. Refiere disnea de esfuerzo. \
+                    Antecedentes de hipertensión arterial. Se solicita electrocardiograma. \
+                    Troponinas negativas. Alta con control ambulatorio";
+        let chunks = chunk_text(text, 12, 3, "tokens", None, true, false, false).unwrap();
+        assert!(chunks.len() > 1);
+        for chunk in &chunks {
+            assert!(tokenize(chunk, false, None, true, false).unwrap().len() <= 12, "chunk too long: {}", chunk);
+        }
+        // The last 3 tokens of a chunk open the next one
+        let tail: Vec<String> = tokenize(&chunks[0], false, None, true, false).unwrap().into_iter().rev().take(3).collect();
+        let head: Vec<String> = tokenize(&chunks[1], false, None, true, false).unwrap().into_iter().take(3).collect();
+        assert_eq!(tail.into_iter().rev().collect::<Vec<_>>(), head);
+    }
+
+    #[test]
+    fn test_split_oversized_prefers_word_boundaries() {
+        let sentence = "Hemograma completo con recuento diferencial normal";
+        let pieces = split_oversized(sentence, 20, ChunkUnit::Chars);
+        assert_eq!(pieces, vec!["Hemograma completo", "con recuento", "diferencial normal"]);
+        
+        // No whitespace to break on: fall back to grapheme boundaries
+        let pieces = split_oversized("hepatoesplenomegalia", 8, ChunkUnit::Chars);
+        assert_eq!(pieces, vec!["hepatoes", "plenomeg", "alia"]);
+        
+        let pieces = split_oversized(sentence, 3, ChunkUnit::Tokens);
+        assert_eq!(pieces, vec!["Hemograma completo con", "recuento diferencial normal"]);
+    }
+
+    #[test]
+    fn test_chunks_never_exceed_chunk_size() {
+        // Deterministic LCG so the property test needs no extra dependency
+        let mut seed: u64 = 0x5EED;
+        let mut next = |bound: usize| {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            ((seed >> 33) as usize) % bound
+        };
+        let alphabet: Vec<char> = "abcdefghijklmnñopqrstuvwxyzáéíóú°0123456789".chars().collect();
+        let separators = [" ", " ", " ", ". ", "\n", ", "];
+        
+        for _ in 0..300 {
+            let mut text = String::new();
+            for _ in 0..next(60) {
+                // Occasionally emit a very long word to force grapheme fallback
+                let word_len = if next(10) == 0 { 30 + next(60) } else { 1 + next(12) };
+                for _ in 0..word_len {
+                    text.push(alphabet[next(alphabet.len())]);
+                }
+                text.push_str(separators[next(separators.len())]);
+            }
+            let chunk_size = 1 + next(80);
+            let overlap = next(chunk_size);
+            for unit in [ChunkUnit::Chars, ChunkUnit::Tokens] {
+                for chunk in chunk_text_internal(&text, chunk_size, overlap, unit) {
+                    assert!(
+                        unit.measure(&chunk) <= chunk_size,
+                        "{:?} chunk of {} > {} (overlap {}): {:?}",
+                        unit, unit.measure(&chunk), chunk_size, overlap, chunk
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_chunk_text_with_offsets_slices_back_to_source() {
+        let text = "Ingresó por disnea.\nSaturación 91%. Se inicia oxigenoterapia. \
+                    Rx de tórax con infiltrado basal derecho. Buena evolución";
+        let chars: Vec<char> = text.chars().collect();
+        let with_offsets = chunk_text_with_offsets(text, 40, 10, "chars", None, true).unwrap();
+        let plain = chunk_text(text, 40, 10, "chars", None, true, false, false).unwrap();
+        assert_eq!(with_offsets.len(), plain.len());
+        
+        for ((chunk, start, end), expected) in with_offsets.iter().zip(&plain) {
+            assert_eq!(chunk, expected);
+            let source: String = chars[*start..*end].iter().collect();
+            // Same words in the same order; only the sentence joins differ
+            assert_eq!(tokenize(&source, false, None, true, false).unwrap(), tokenize(chunk, false, None, true, false).unwrap());
+        }
+        assert_eq!(with_offsets[0].1, 0);
+        assert_eq!(with_offsets.last().unwrap().2, chars.len());
+
+        // Custom delimiters give the same chunks as chunk_text
+        let text = "Antecedentes: HTA; DBT2; EPOC. Sin alergias";
+        let delimiters = Some(vec![";".to_string()]);
+        let with_offsets = chunk_text_with_offsets(text, 20, 0, "chars", delimiters.clone(), true).unwrap();
+        let plain = chunk_text(text, 20, 0, "chars", delimiters, true, false, false).unwrap();
+        assert_eq!(with_offsets.iter().map(|(chunk, _, _)| chunk.clone()).collect::<Vec<_>>(), plain);
+        assert_eq!(with_offsets[0], ("Antecedentes: HTA;".to_string(), 0, 18));
+    }
+
+    #[test]
+    fn test_chunk_text_with_offsets_overlapping_ranges() {
+        let text = "Primera evolución sin cambios. Segunda evolución con fiebre. \
+                    Tercera evolución afebril. Cuarta evolución, alta";
+        let chars: Vec<char> = text.chars().collect();
+        let chunks = chunk_text_with_offsets(text, 40, 12, "chars", None, true).unwrap();
+        assert!(chunks.len() > 2);
+        
+        // Rebuild the document prefix from the ranges: consecutive chunks must
+        // overlap, and whatever is not covered may only be sentence separators
+        let mut rebuilt = String::new();
+        let mut covered = 0;
+        for (i, (chunk, start, end)) in chunks.iter().enumerate() {
+            if i > 0 {
+                assert!(*start < covered, "chunk {} does not overlap the previous one", i);
+            }
+            let carried: String = chars[*start..covered.max(*start)].iter().collect();
+            assert!(chunk.starts_with(carried.as_str()));
+            rebuilt.extend(&chars[covered.max(*start)..*end]);
+            covered = *end;
+        }
+        let prefix: String = chars[..covered].iter().collect();
+        assert_eq!(rebuilt, prefix);
+    }
+
+    #[test]
+    fn test_chunk_by_tokens_matches_token_unit() {
+        let text = "Paciente de 72 años. Antecedentes de EPOC y DBT tipo 2. \
+                    Consulta por disnea progresiva de 3 días de evolución. \
+                    Se indica nebulización con salbutamol 2.5 mg";
+        let chunks = chunk_by_tokens(text, 10, 2).unwrap();
+        assert_eq!(chunks, chunk_text(text, 10, 2, "tokens", None, true, false, false).unwrap());
+        for chunk in &chunks {
+            assert!(tokenize(chunk, false, None, true, false).unwrap().len() <= 10);
+        }
+        assert!(chunk_by_tokens(text, 10, 10).is_err());
+    }
+
+    #[test]
+    fn test_recursive_chunk_text_structured_document() {
+        let long_line = "ABCDEFGHIJ".repeat(15);
+        let text = format!(
+            "ANTECEDENTES\nHipertensión arterial. Diabetes tipo 2.\n\n\
+             EXAMEN FÍSICO\nLúcido, afebril. Abdomen blando, depresible, no doloroso.\n\n\
+             {}\n\nPLAN\nControl en 7 días.",
+            long_line
+        );
+        let chunks = recursive_chunk_text(&text, 60, 10, None).unwrap();
+        
+        for chunk in &chunks {
+            assert!(chunk.chars().count() <= 60, "chunk too long: {:?}", chunk);
+            assert!(text.contains(chunk.as_str()));
+        }
+        // Short sections stay whole, headings together with their body
+        assert!(chunks.contains(&"ANTECEDENTES\nHipertensión arterial. Diabetes tipo 2.".to_string()));
+        assert!(chunks.iter().any(|c| c.ends_with("\n\nPLAN\nControl en 7 días.")));
+        // The unbroken line falls back to grapheme splitting, losing nothing
+        let rebuilt: String = chunks
+            .iter()
+            .filter(|c| c.starts_with("ABCDE"))
+            .filter_map(|c| c.lines().next())
+            .collect();
+        assert_eq!(rebuilt, long_line);
+    }
+
+    #[test]
+    fn test_recursive_chunk_text_applies_overlap() {
+        let text = "uno dos tres cuatro cinco seis siete ocho nueve diez";
+        let chunks = recursive_chunk_text(text, 20, 8, Some(vec![" ".to_string()])).unwrap();
+        assert_eq!(
+            chunks,
+            vec!["uno dos tres cuatro", "cuatro cinco seis", "seis siete ocho", "ocho nueve diez"]
+        );
+    }
+
+    #[test]
+    fn test_chunk_text_respect_paragraphs() {
+        let text = "Antecedentes: HTA. DBT tipo 2.\n\n\
+                    Exploración física: lúcido, afebril. Abdomen blando.\n  \n\
+                    Plan: control en 7 días.";
+        let merged = chunk_text(text, 80, 10, "chars", None, true, false, false).unwrap();
+        assert!(merged.iter().any(|c| c.contains("DBT tipo 2. Exploración")));
+        
+        let chunks = chunk_text(text, 80, 10, "chars", None, true, true, false).unwrap();
+        assert_eq!(
+            chunks,
+            vec![
+                "Antecedentes: HTA. DBT tipo 2",
+                "Exploración física: lúcido, afebril. Abdomen blando",
+                "Plan: control en 7 días",
+            ]
+        );
+        
+        // A paragraph longer than chunk_size is still split, but never merged
+        let chunks = chunk_text(text, 30, 5, "chars", None, true, true, false).unwrap();
+        assert!(chunks.iter().all(|c| c.chars().count() <= 30));
+        assert!(!chunks.iter().any(|c| c.contains("2. Exploración") || c.contains("blando. Plan")));
+    }
+
+    #[test]
+    fn test_chunk_text_strict_mode() {
+        let long_sentence = "hallazgo ".repeat(555) + "final";
+        assert_eq!(long_sentence.chars().count(), 5000);
+        let text = format!("Inicio. {}. Cierre", long_sentence);
+        
+        let err = chunk_text(&text, 1000, 100, "chars", None, true, false, true).unwrap_err();
+        let message = with_py(|_| err.to_string());
+        assert!(message.contains("sentence of 5000 chars exceeds chunk_size (1000)"), "{}", message);
+        
+        let chunks = chunk_text(&text, 1000, 100, "chars", None, true, false, false).unwrap();
+        assert!(chunks.len() >= 5);
+        assert!(chunks.iter().all(|c| c.chars().count() <= 1000));
+        
+        // Strict mode is a no-op when every sentence fits
+        let ok = chunk_text("Uno. Dos. Tres", 10, 2, "chars", None, true, false, true).unwrap();
+        assert_eq!(ok, chunk_text("Uno. Dos. Tres", 10, 2, "chars", None, true, false, false).unwrap());
+    }
+
+    #[test]
+    fn test_chunk_text_rejects_unknown_unit() {
+        assert!(ChunkUnit::parse("words").is_err());
+        assert_eq!(ChunkUnit::parse("tokens").unwrap(), ChunkUnit::Tokens);
+    }
+
+    #[test]
+    fn test_overlap_start_keeps_emoji_whole() {
+        // The health-worker emoji is a single grapheme of 4 codepoints
+        let chunk = "Control 👨‍⚕️";
+        let start = overlap_start_index(chunk, 3);
+        assert_eq!(&chunk[start..], "");
+        let start = overlap_start_index(chunk, 4);
+        assert_eq!(&chunk[start..], "👨‍⚕️");
+    }
+
+    #[test]
+    fn test_parallel_chunk_texts_matches_chunk_text() {
+        let texts = vec![
+            "Presión arterial elevada. Se ajusta medicación".to_string(),
+            "Paciente con diagnóstico de neumonía. Evolución favorable".to_string(),
+        ];
+        let results = with_py(|py| parallel_chunk_texts(py, texts.clone(), 30, 5, "chars", None, true)).unwrap();
+        for (idx, chunks) in results {
+            assert_eq!(chunks, chunk_text(&texts[idx], 30, 5, "chars", None, true, false, false).unwrap());
+        }
+
+        // Custom delimiters reach every text
+        let texts = vec!["HTA; DBT2; EPOC".to_string(), "Sin alergias; tabaquista".to_string()];
+        let delimiters = Some(vec![";".to_string()]);
+        let results = with_py(|py| parallel_chunk_texts(py, texts.clone(), 12, 0, "chars", delimiters.clone(), true)).unwrap();
+        for (idx, chunks) in results {
+            assert_eq!(chunks, chunk_text(&texts[idx], 12, 0, "chars", delimiters.clone(), true, false, false).unwrap());
+        }
+        assert!(with_py(|py| parallel_chunk_texts(py, texts, 12, 0, "chars", Some(vec![]), true)).is_err());
+    }
+
+    #[test]
+    fn test_tokenize_stopwords() {
+        let text = "El paciente refiere dolor de la pierna, sin fiebre";
+        assert_eq!(
+            tokenize(text, false, None, true, false).unwrap(),
+            vec!["el", "paciente", "refiere", "dolor", "de", "la", "pierna", "sin", "fiebre"]
+        );
+        // Built-in list keeps negations
+        assert_eq!(tokenize(text, true, None, true, false).unwrap(), vec!["dolor", "pierna", "sin", "fiebre"]);
+        
+        let custom = Some(vec!["DOLOR".to_string(), "sin".to_string()]);
+        assert_eq!(
+            tokenize(text, true, custom, true, false).unwrap(),
+            vec!["el", "paciente", "refiere", "de", "la", "pierna", "fiebre"]
+        );
+        assert!(tokenize("De la y en el", true, None, true, false).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_tokenize_preserves_case_when_asked() {
+        let text = "Se solicita TAC y RMN; el tac previo y la PCR-us normales";
+        assert_eq!(
+            tokenize(text, false, None, false, false).unwrap(),
+            vec!["Se", "solicita", "TAC", "y", "RMN", "el", "tac", "previo", "y", "la", "PCR", "us", "normales"]
+        );
+        assert_eq!(tokenize(text, false, None, true, false).unwrap()[2], "tac");
+        // Stopwords still match regardless of case
+        assert_eq!(
+            tokenize("El TAC de Tórax", true, None, false, false).unwrap(),
+            vec!["TAC", "Tórax"]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_keeps_acronyms_and_punctuation() {
+        let text = "Pac. con EPOC, IAM previo (2019) y TAC: normal... ¿Alta?";
+        assert_eq!(
+            tokenize(text, false, None, false, true).unwrap(),
+            vec![
+                "Pac", ".", "con", "EPOC", ",", "IAM", "previo", "(", "2019", ")", "y", "TAC", ":",
+                "normal", ".", ".", ".", "¿", "Alta", "?",
+            ]
+        );
+        assert_eq!(
+            tokenize(text, false, None, true, true).unwrap()[..6],
+            ["pac", ".", "con", "epoc", ",", "iam"]
+        );
+        // Defaults match unicode_words exactly
+        let expected: Vec<String> = text.unicode_words().map(str::to_lowercase).collect();
+        assert_eq!(tokenize(text, false, None, true, false).unwrap(), expected);
+        assert_eq!(
+            tokenize(text, false, None, false, false).unwrap(),
+            text.unicode_words().collect::<Vec<_>>()
+        );
+        // Joining the case-preserving tokens round-trips the non-space text
+        let joined: String = tokenize(text, false, None, false, true).unwrap().concat();
+        assert_eq!(joined, text.split_whitespace().collect::<String>());
+    }
+
+    #[test]
+    fn test_remove_stopwords_and_tokenize_filtered() {
+        let text = "El paciente refiere MÁS dolor, mas no fiebre; Control en 48 hs";
+        let tokens = tokenize(text, false, None, false, false).unwrap();
+        assert_eq!(
+            remove_stopwords(tokens.clone(), None, "es").unwrap(),
+            vec!["MÁS", "dolor", "mas", "no", "fiebre", "Control", "48", "hs"]
+        );
+        let extra = Some(vec!["control".to_string(), "HS".to_string()]);
+        assert_eq!(
+            remove_stopwords(tokens, extra.clone(), "es").unwrap(),
+            vec!["MÁS", "dolor", "mas", "no", "fiebre", "48"]
+        );
+        assert_eq!(
+            tokenize_filtered(text, extra, "es", true).unwrap(),
+            vec!["más", "dolor", "mas", "no", "fiebre", "48"]
+        );
+        assert_eq!(
+            tokenize_filtered("The patient has a history of COPD", None, "en", false).unwrap(),
+            vec!["history", "COPD"]
+        );
+        assert!(with_py(|_| remove_stopwords(vec![], None, "xx")).is_err());
+    }
+
+    #[test]
+    fn test_parallel_tokenize_matches_tokenize() {
+        let small: Vec<String> = vec!["TA 120/80, sin EPOC.".to_string(), String::new(), "Dx: IAM".to_string()];
+        let large: Vec<String> = (0..300).map(|i| format!("Evolución {}: TAC {} normal.", i, "sin cambios; ".repeat(i % 5))).collect();
+        for texts in [small, large] {
+            for (lowercase, keep_punctuation) in [(true, false), (false, true)] {
+                let batch = with_py(|py| parallel_tokenize(py, texts.clone(), lowercase, keep_punctuation)).unwrap();
+                let serial: Vec<Vec<String>> = texts
+                    .iter()
+                    .map(|t| tokenize(t, false, None, lowercase, keep_punctuation).unwrap())
+                    .collect();
+                assert_eq!(batch, serial);
+            }
+        }
+    }
+
+    #[test]
+    fn test_tokenize_with_spans_slices_original_text() {
+        let text = "Ñandú: TAC de TÓRAX, 38,5°C 🙂 Ölfaktorisch";
+        let spans = tokenize_with_spans(text).unwrap();
+        let chars: Vec<char> = text.chars().collect();
+        for (token, start, end) in &spans {
+            let slice: String = chars[*start..*end].iter().collect();
+            assert_eq!(&slice.to_lowercase(), token);
+        }
+        let tokens: Vec<String> = spans.into_iter().map(|(t, _, _)| t).collect();
+        assert_eq!(tokens, tokenize(text, false, None, true, false).unwrap());
+    }
+
+    #[test]
+    fn test_truncate_to_tokens() {
+        let estimate = |s: &str| count_tokens_internal(s, DEFAULT_CHARS_PER_TOKEN);
+        let text = "Dolor torácico opresivo, irradiado a brazo izquierdo.";
+        let budget = estimate(text);
+        assert_eq!(truncate_to_tokens(text, 100, false, "auto").unwrap(), text);
+        assert_eq!(truncate_to_budget(text, budget, true, estimate), text);
+        assert_eq!(truncate_to_tokens("", 3, false, "auto").unwrap(), "");
+        assert!(with_py(|_| truncate_to_tokens(text, 0, false, "auto")).is_err());
+        // Other tests may load a vocabulary; an explicit tokenizer ignores it
+        assert_eq!(truncate_to_tokens(text, 3, false, "estimate").unwrap(), "Dolor torácico");
+        let exact = truncate_to_tokens(text, 3, false, "cl100k_base").unwrap();
+        assert!(bpe::count_with(bpe::encoding_for("cl100k_base").unwrap(), &exact) <= 3 && text.starts_with(&exact));
+        assert!(with_py(|_| truncate_to_tokens(text, 3, false, "llama-3")).is_err());
+        for max_tokens in 1..budget {
+            for ellipsis in [false, true] {
+                let truncated = truncate_to_budget(text, max_tokens, ellipsis, estimate);
+                assert!(estimate(&truncated) <= max_tokens, "{:?}", truncated);
+                let kept = truncated.strip_suffix(ELLIPSIS).unwrap_or(&truncated);
+                assert!(text.starts_with(kept));
+                // Cut right after a whole word
+                assert!(kept.is_empty() || text.unicode_word_indices().any(|(i, w)| i + w.len() == kept.len()));
+                assert_eq!(truncated.ends_with(ELLIPSIS), ellipsis && !kept.is_empty());
+            }
+        }
+        assert_eq!(truncate_to_budget(text, 3, false, estimate), "Dolor torácico");
+        assert_eq!(truncate_to_budget(text, 3, true, estimate), "Dolor torácico…");
+
+        let words = |s: &str| s.unicode_words().count();
+        assert_eq!(truncate_to_budget(text, 3, false, words), "Dolor torácico opresivo");
+        assert_eq!(truncate_to_budget(text, 7, false, words), text);
+        assert_eq!(truncate_to_budget(text, 1, false, words), "Dolor");
+    }
+
+    #[test]
+    fn test_truncate_to_chars_keeps_graphemes() {
+        let text = "Cafe\u{301}ína 👩‍⚕️ ok";
+        assert_eq!(truncate_to_chars(text, 100).unwrap(), text);
+        assert_eq!(truncate_to_chars(text, 3).unwrap(), "Caf");
+        // "e" + U+0301 is two chars; with room for only one, both are dropped
+        assert_eq!(truncate_to_chars(text, 4).unwrap(), "Caf");
+        assert_eq!(truncate_to_chars(text, 5).unwrap(), "Cafe\u{301}");
+        // The doctor emoji is 4 chars (woman + ZWJ + staff + VS16)
+        assert_eq!(truncate_to_chars(text, 11).unwrap(), "Cafe\u{301}ína ");
+        assert_eq!(truncate_to_chars(text, 13).unwrap(), "Cafe\u{301}ína 👩‍⚕️");
+        assert!(with_py(|_| truncate_to_chars(text, 0)).is_err());
+    }
+
+    #[test]
+    fn test_ngrams() {
+        let text = "Dolor torácico, opresivo.";
+        assert_eq!(ngrams(text, 1, "word", false).unwrap(), vec!["dolor", "torácico", "opresivo"]);
+        assert_eq!(ngrams(text, 2, "word", false).unwrap(), vec!["dolor torácico", "torácico opresivo"]);
+        assert_eq!(ngrams(text, 3, "word", false).unwrap(), vec!["dolor torácico opresivo"]);
+        assert!(ngrams(text, 4, "word", false).unwrap().is_empty());
+        assert!(ngrams("", 2, "word", false).unwrap().is_empty());
+        assert!(with_py(|_| ngrams(text, 0, "word", false).is_err()));
+    }
+
+    #[test]
+    fn test_char_ngrams_kind_and_padding() {
+        assert_eq!(ngrams("Amoxi", 3, "char", false).unwrap(), vec!["amo", "mox", "oxi"]);
+        assert_eq!(ngrams("Amoxi", 3, "char", true).unwrap(), vec!["#am", "amo", "mox", "oxi", "xi#"]);
+        assert_eq!(ngrams("Niño", 2, "char", false).unwrap(), vec!["ni", "iñ", "ño"]);
+        assert_eq!(ngrams("ab", 4, "char", true).unwrap(), vec!["#ab#"]);
+        assert!(ngrams("ab", 5, "char", true).unwrap().is_empty());
+        assert!(ngrams("", 1, "char", true).unwrap().is_empty());
+        assert_eq!(ngrams("Ácido", 3, "char", false).unwrap(), char_ngrams("Ácido", 3, true).unwrap());
+        with_py(|_| {
+            assert!(ngrams("abc", 2, "word", true).is_err());
+            assert!(ngrams("abc", 2, "bytes", false).is_err());
+        });
+    }
+
+    #[test]
+    fn test_parallel_ngrams_matches_ngrams() {
+        let texts: Vec<String> = (0..100).map(|i| format!("Amoxicilina {} mg cada {} horas", 250 * i, i % 12)).collect();
+        for (kind, pad) in [("word", false), ("char", true)] {
+            let batch = with_py(|py| parallel_ngrams(py, texts.clone(), 3, kind, pad)).unwrap();
+            let serial: Vec<Vec<String>> = texts.iter().map(|t| ngrams(t, 3, kind, pad).unwrap()).collect();
+            assert_eq!(batch, serial);
+        }
+        assert!(with_py(|py| parallel_ngrams(py, texts.clone(), 0, "char", false)).is_err());
+    }
+
+    #[test]
+    fn test_char_ngrams_over_graphemes() {
+        assert_eq!(
+            char_ngrams("Ácido", 3, true).unwrap(),
+            vec!["áci", "cid", "ido"]
+        );
+        assert_eq!(char_ngrams("Ácido", 3, false).unwrap()[0], "Áci");
+        // "e" + combining acute accent stays one grapheme
+        let decomposed = "Cafe\u{301}ína";
+        assert_eq!(
+            char_ngrams(decomposed, 2, true).unwrap(),
+            vec!["ca", "af", "fe\u{301}", "e\u{301}í", "ín", "na"]
+        );
+        assert_eq!(char_ngrams("AAS", 3, true).unwrap(), vec!["aas"]);
+        assert!(char_ngrams("AAS", 4, true).unwrap().is_empty());
+        assert!(with_py(|_| char_ngrams("AAS", 0, true).is_err()));
+    }
+
+    #[test]
+    fn test_sentence_token_counts_add_up() {
+        let text = "Paciente de 67 años con disnea de esfuerzo. Refiere edemas en miembros inferiores \
+                    desde hace dos semanas.\nTA 150/90 mmHg, FC 96 lpm. Se indica furosemida 40 mg.\n\
+                    Control por consultorio en 7 días con laboratorio completo y ecocardiograma.";
+        let counts = sentence_token_counts(text, 4.0).unwrap();
+        assert_eq!(counts.len(), 5);
+        assert_eq!(counts[0], ("Paciente de 67 años con disnea de esfuerzo".to_string(), 9));
+        
+        let sum: usize = counts.iter().map(|(_, n)| n).sum();
+        let whole = count_tokens(text, 4.0).unwrap();
+        // Separators and per-sentence rounding account for the difference
+        assert!(sum <= whole && whole - sum <= counts.len() + 1, "sum {} vs whole {}", sum, whole);
+        assert!(sentence_token_counts("", 4.0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_count_tokens_detailed_components() {
+        // 5 words, 34 characters
+        let text = "Dolor abdominal difuso, sin fiebre";
+        assert_eq!(count_tokens_detailed(text, 4.0).unwrap(), (5, 8, 6));
+        assert_eq!(count_tokens_detailed(text, 3.0).unwrap(), (5, 11, 8));
+        assert_eq!(count_tokens_detailed(text, 4.0).unwrap().2, count_tokens(text, 4.0).unwrap());
+        assert_eq!(count_tokens_detailed("", 4.0).unwrap(), (0, 0, 0));
+    }
+
+    #[test]
+    fn test_parallel_count_tokens_matches_serial() {
+        let texts: Vec<String> = (0..200)
+            .map(|i| format!("Evolución {}: presión {}/80 mmHg, {}", i, 100 + i, "sin cambios. ".repeat(i % 7)))
+            .chain(["".to_string(), "🙂".repeat(9)])
+            .collect();
+        for chars_per_token in [4.0, 3.5] {
+            let serial: Vec<usize> = texts.iter().map(|t| count_tokens(t, chars_per_token).unwrap()).collect();
+            let parallel = with_py(|py| parallel_count_tokens(py, texts.clone(), chars_per_token)).unwrap();
+            assert_eq!(parallel, serial);
+        }
+        assert!(with_py(|py| parallel_count_tokens(py, texts.clone(), -1.0).is_err()));
+    }
+
+    #[test]
+    fn test_count_tokens_counts_chars_not_bytes() {
+        let ascii = "cancion de la nina con dolor abdominal";
+        let accented = "canción de la niña con dólor abdominál";
+        assert_eq!(ascii.chars().count(), accented.chars().count());
+        assert!(accented.len() > ascii.len());
+        assert_eq!(count_tokens(ascii, 4.0).unwrap(), count_tokens(accented, 4.0).unwrap());
+        
+        // 40 chars, 1 word: (1 + 40 / 4) / 2 and (1 + 40 / 2) / 2
+        let long_word = "a".repeat(40);
+        assert_eq!(count_tokens(&long_word, 4.0).unwrap(), 5);
+        assert_eq!(count_tokens(&long_word, 2.0).unwrap(), 10);
+        assert!(with_py(|_| count_tokens("texto", 0.0).is_err()));
+    }
+
+    #[test]
+    fn test_tokenize_with_offsets_chars_and_bytes() {
+        let text = "Dx: ÚLCERA gástrica — Пациент 42 ✓ 腹痛 IAM";
+        let chars: Vec<char> = text.chars().collect();
+        let by_char = tokenize_with_offsets(text, false, false).unwrap();
+        let by_byte = tokenize_with_offsets(text, false, true).unwrap();
+        assert_eq!(by_char.len(), by_byte.len());
+        for ((token, start, end), (_, byte_start, byte_end)) in by_char.iter().zip(&by_byte) {
+            assert_eq!(&chars[*start..*end].iter().collect::<String>(), token);
+            assert_eq!(&text[*byte_start..*byte_end], token);
+        }
+        let ulcera = by_char.iter().find(|(t, _, _)| t == "ÚLCERA").unwrap();
+        assert_eq!((ulcera.1, ulcera.2), (4, 10));
+        // Byte and char offsets diverge after the first non-ASCII letter
+        assert_ne!(by_char.last().unwrap().1, by_byte.last().unwrap().1);
+        
+        let lowered = tokenize_with_offsets(text, true, false).unwrap();
+        assert_eq!(lowered[1], ("úlcera".to_string(), 4, 10));
+        assert_eq!(lowered, tokenize_with_spans(text).unwrap());
+    }
+
+    #[test]
+    fn test_clean_medical_text() {
+        let text = "<b>Test</b>  multiple   spaces";
+        with_py(|py| {
+            let clean = wrap_pyfunction_bound!(clean_medical_text, py).unwrap();
+            let result: String = clean.call1((text,)).unwrap().extract().unwrap();
+            assert_eq!(result, "Test multiple spaces");
+            let options = [("lowercase", true), ("keep_enie", true)].into_py_dict_bound(py);
+            let lowered: String = clean.call((text,), Some(&options)).unwrap().extract().unwrap();
+            assert_eq!(lowered, "test multiple spaces");
+            // Options are keyword-only
+            assert!(clean.call1((text, true)).is_err());
+        });
+    }
+
+    #[test]
+    fn test_clean_medical_text_keeps_inequalities() {
+        let text = "<p><b>Signos:</b> FC <60 lpm y TA >90</p><p>Glucemia &lt;70<br>Hb < 10 g/dl</p>";
+        assert_eq!(
+            clean_medical_text_internal(text, CleanOptions::default()),
+            "Signos: FC <60 lpm y TA >90 Glucemia <70 Hb < 10 g/dl"
+        );
+        assert_eq!(
+            clean_medical_text_internal(text, CleanOptions { preserve_newlines: true, ..CleanOptions::default() }),
+            "Signos: FC <60 lpm y TA >90\n\nGlucemia <70\nHb < 10 g/dl"
+        );
+    }
+
+    #[test]
+    fn test_clean_medical_text_decodes_entities() {
+        let cases = [
+            ("presi&oacute;n arterial", "presión arterial"),
+            ("Dr. P&eacute;rez &amp; Asoc.", "Dr. Pérez & Asoc."),
+            ("FC &lt;60 y TA &gt;90", "FC <60 y TA >90"),
+            ("f&#243;rmula &#8804; 5", "fórmula ≤ 5"),
+            ("f&#xF3;rmula &#xb0;C", "fórmula °C"),
+            ("<p>dolor&nbsp;&nbsp;tor&aacute;cico</p>", "dolor torácico"),
+            ("&lt;b&gt;literal&lt;/b&gt;", "<b>literal</b>"),
+            ("&noexiste; y &#xZZ; y & suelto", "&noexiste; y &#xZZ; y & suelto"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(clean_medical_text_internal(input, CleanOptions::default()), expected, "input: {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_clean_medical_text_nbsp_joins_whitespace_collapse() {
+        let text = "&nbsp;<td>Hb&nbsp;12,5&#160;g/dl</td>&nbsp;\u{A0}&#xA0;\n&nbsp;<td>f&#243;rmula &amp; recuento</td>&nbsp;";
+        for preserve_newlines in [false, true] {
+            let cleaned = clean_medical_text_internal(text, CleanOptions { preserve_newlines, ..CleanOptions::default() });
+            assert!(!cleaned.contains('\u{A0}'), "stray NBSP in {:?}", cleaned);
+            let expected = if preserve_newlines {
+                "Hb 12,5 g/dl\nfórmula & recuento"
+            } else {
+                "Hb 12,5 g/dl fórmula & recuento"
+            };
+            assert_eq!(cleaned, expected);
+        }
+    }
+
+    #[test]
+    fn test_clean_medical_text_normalize_unicode() {
+        let precomposed = "Presi\u{F3}n arterial, \u{E1}rea card\u{ED}aca";
+        let decomposed = "Presio\u{301}n arterial, a\u{301}rea cardi\u{301}aca";
+        let nfc = CleanOptions { normalize_unicode: true, ..CleanOptions::default() };
+        assert_eq!(clean_medical_text_internal(decomposed, nfc), precomposed);
+        assert_eq!(clean_medical_text_internal(precomposed, nfc), precomposed);
+        // Off by default: the decomposed form passes through
+        assert_eq!(clean_medical_text_internal(decomposed, CleanOptions::default()), decomposed);
+        // An accent split from its letter by a tag still composes
+        assert_eq!(clean_medical_text_internal("a<b>\u{301}</b>rea", nfc), "\u{E1}rea");
+    }
+
+    #[test]
+    fn test_clean_medical_text_removes_footer_urls_and_emails() {
+        let text = "Alta con amoxicilina 500 mg c/8 hs. Control en 7 días.<br>\
+                    --<br>Hospital Central | https://www.hospitalcentral.org.ar/turnos?id=3 | \
+                    www.hcentral.com.ar. Consultas: turnos.guardia@hcentral.com.ar";
+        let options = CleanOptions { remove_urls: true, remove_emails: true, ..CleanOptions::default() };
+        let cleaned = clean_medical_text_internal(text, options);
+        assert_eq!(cleaned, "Alta con amoxicilina 500 mg c/8 hs. Control en 7 días. -- Hospital Central | | . Consultas:");
+        assert!(!cleaned.contains("http") && !cleaned.contains('@') && !cleaned.contains("www"));
+
+        let urls_only = clean_medical_text_internal(text, CleanOptions { remove_urls: true, ..CleanOptions::default() });
+        assert!(urls_only.ends_with("turnos.guardia@hcentral.com.ar"));
+        assert!(clean_medical_text_internal(text, CleanOptions::default()).contains("https://www.hospitalcentral.org.ar/turnos?id=3"));
+        // Sentence punctuation after a URL stays
+        let inline = CleanOptions { remove_urls: true, ..CleanOptions::default() };
+        assert_eq!(clean_medical_text_internal("Ver guía (https://x.org/a).", inline), "Ver guía ().");
+    }
+
+    #[test]
+    fn test_clean_medical_text_lowercase() {
+        let text = "<b>ÚLCERA GÁSTRICA</b> en NIÑO; ÉPOCA de ÍNDICE Ñ";
+        let lower = CleanOptions { lowercase: true, ..CleanOptions::default() };
+        assert_eq!(clean_medical_text_internal(text, lower), "úlcera gástrica en niño; época de índice ñ");
+        assert_eq!(clean_medical_text_internal(text, CleanOptions::default()), "ÚLCERA GÁSTRICA en NIÑO; ÉPOCA de ÍNDICE Ñ");
+        let folded = CleanOptions { strip_accents: true, ..lower };
+        assert_eq!(clean_medical_text_internal(text, folded), "ulcera gastrica en niño; epoca de indice ñ");
+    }
+
+    #[test]
+    fn test_clean_medical_text_strip_accents() {
+        let text = "PRESIÓN arterial; úlcera en región plantar, pingüino, Niño de 3 años";
+        let folded = CleanOptions { strip_accents: true, ..CleanOptions::default() };
+        assert_eq!(
+            clean_medical_text_internal(text, folded),
+            "PRESION arterial; ulcera en region plantar, pinguino, Niño de 3 años"
+        );
+        let fold_enie = CleanOptions { keep_enie: false, ..folded };
+        assert_eq!(
+            clean_medical_text_internal(text, fold_enie),
+            "PRESION arterial; ulcera en region plantar, pinguino, Nino de 3 anos"
+        );
+        // Decomposed input: ñ is recomposed, other accents dropped
+        assert_eq!(clean_medical_text_internal("an\u{303}o cardi\u{301}aco", folded), "año cardiaco");
+        assert_eq!(clean_medical_text_internal("37,5 °C µg", folded), "37,5 °C µg");
+    }
+
+    #[test]
+    fn test_clean_medical_text_keeps_lab_report_rows() {
+        let report = "LABORATORIO  12/08/2025\r\n\
+                      Hemoglobina\t\t12,5 g/dL\t(12-16)\r\n\
+                      Leucocitos \t 8.200 /mm3\r\n\r\n\r\n\r\n\
+                      Glucemia   110 mg/dL\n";
+        let options = CleanOptions { preserve_newlines: true, ..CleanOptions::default() };
+        let cleaned = clean_medical_text_internal(report, options);
+        assert_eq!(
+            cleaned,
+            "LABORATORIO 12/08/2025\nHemoglobina 12,5 g/dL (12-16)\nLeucocitos 8.200 /mm3\n\nGlucemia 110 mg/dL"
+        );
+        assert_eq!(cleaned.lines().filter(|l| !l.is_empty()).count(), 4);
+    }
+
+    #[test]
+    fn test_clean_medical_text_preserve_newlines() {
+        let text = "  <h2>ANTECEDENTES</h2>\r\n\tHTA   en tratamiento\n\n\n\n\
+                    MEDICACIÓN:  \n- Enalapril 10 mg\n-  Aspirina\t100 mg  \n\n\n";
+        let options = CleanOptions { preserve_newlines: true, ..CleanOptions::default() };
+        assert_eq!(
+            clean_medical_text_internal(text, options),
+            "ANTECEDENTES\nHTA en tratamiento\n\nMEDICACIÓN:\n- Enalapril 10 mg\n- Aspirina 100 mg"
+        );
+        assert_eq!(
+            clean_medical_text_internal(text, CleanOptions::default()),
+            "ANTECEDENTES HTA en tratamiento MEDICACIÓN: - Enalapril 10 mg - Aspirina 100 mg"
+        );
+    }
+
+    #[test]
+    fn test_extract_icd10_codes() {
+        let text = "Dx: IAM (I21.0) y asma (J45.909); DBT2 E11.9, HTA I10. Antecedente C50, COVID U07.1. \
+                    Recibió 120 mg, K 4,1 mEq/L, B12 500 µg, lote A1234, e11.9 y X1.";
+        let found = extract_entities_internal(text, &EntityOptions::default());
+        assert_eq!(found["icd10"], vec!["I21.0", "J45.909", "E11.9", "I10", "C50", "U07.1"]);
+        assert_eq!(found["measurements"], vec!["120 mg", "4,1 mEq/L", "500 µg"]);
+        // Same rules as extract_icd10
+        let traps = "Peso en P50, T38.5° axilar. Dx: D95.1, B12";
+        assert!(extract_entities_internal(traps, &EntityOptions::default())["icd10"].is_empty());
+        assert_eq!(found["icd10"], icd10::extract_icd10_internal(text).into_iter().map(|(code, _, _)| code).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_extract_percentages() {
+        let text = "SatO2 95% aire ambiente, Hto 38,5 %, FEVI 30 por ciento. Control 01/02.";
+        let found = extract_entities_internal(text, &EntityOptions::default());
+        assert_eq!(found["percentages"], vec!["95%", "38,5 %", "30 por ciento"]);
+        assert!(found["ratios"].is_empty());
+    }
+
+    #[test]
+    fn test_extract_ratios() {
+        let text = "INR 2.5 en rango. RIN: 1,8; relación A/G de 1,2, dilución 1:10.";
+        let found = extract_entities_internal(text, &EntityOptions::default());
+        assert_eq!(found["ratios"], vec!["2.5", "1,8", "1,2", "1:10"]);
+        assert!(found["times"].is_empty());
+        let spans = extract_entities_with_spans_internal("Control INR 3,1", &EntityOptions::default());
+        assert_eq!(spans["ratios"], vec![("3,1".to_string(), 12, 15)]);
+    }
+
+    #[test]
+    fn test_dates_are_not_ratios() {
+        let text = "INR 01/02 sin dato. Relación del 01-02-2026 sin cambios; cociente 01/02; INR 2,3";
+        let found = extract_entities_internal(text, &EntityOptions::default());
+        assert_eq!(found["ratios"], vec!["2,3"]);
+        assert_eq!(found["dates"], vec!["01-02-2026"]);
+    }
+
+    #[test]
+    fn test_generic_ratio_words_need_a_ratio() {
+        let text = "Dolor en relación a 2 días de evolución, en razón de 3 caídas; proporción de 4 casos.";
+        let found = extract_entities_internal(text, &EntityOptions::default());
+        assert!(found["ratios"].is_empty(), "{:?}", found["ratios"]);
+        let text = "Relación albúmina/creatinina 30, relación I:E 1:2, razón 3:1, proporción 2 a 1.";
+        let found = extract_entities_internal(text, &EntityOptions::default());
+        assert_eq!(found["ratios"], vec!["30", "1:2", "3:1"]);
+    }
+
+    #[test]
+    fn test_extract_entities_typed() {
+        let text = "Fecha: 15/01/2026 a las 14:30. Dosis: 500mg, SatO2 95%, INR 2,5. Dx I10";
+        with_py(|py| {
+            let typed = extract_entities_typed(py, text, 1900, 2100, false, None, true).unwrap();
+            assert_eq!(typed.dates, vec!["2026-01-15"]);
+            assert_eq!(typed.times, vec!["14:30"]);
+            assert_eq!(typed.measurements, vec!["500mg", "95%"]);
+            assert_eq!((typed.icd10.len(), typed.percentages.len(), typed.ratios.len()), (1, 1, 1));
+            let plain = extract_entities(py, text, 1900, 2100, false, None, true).unwrap();
+            assert_eq!(typed.to_dict(), plain);
+
+            // Attribute access from Python; a typo is an error, not None
+            let object = Py::new(py, typed).unwrap().into_bound(py);
+            let dates: Vec<String> = object.getattr("dates").unwrap().extract().unwrap();
+            assert_eq!(dates, vec!["2026-01-15"]);
+            assert!(object.getattr("date").is_err());
+            assert!(extract_entities_typed(py, text, 2100, 1900, false, None, false).is_err());
+        });
+    }
+
+    #[test]
+    fn test_entity_counts_merge_spellings() {
+        let text = "500mg a las 8:00, 500 mg a las 08:00 hs, 500 MG. 15/01/2026 y 15 de Enero de 2026; \
+                    control 15-01-2026. Dosis 1 g. 30% y 30 %.";
+        let raw = entity_counts(text, &EntityOptions::default(), false);
+        let category = |counts: &EntityCounts, name: &str| counts.iter().find(|(c, _)| *c == name).unwrap().1.clone();
+        let pairs = |pairs: &[(&str, usize)]| pairs.iter().map(|&(e, n)| (e.to_string(), n)).collect::<Vec<_>>();
+        assert_eq!(category(&raw, "measurements"), pairs(&[("500mg", 1), ("500 mg", 1), ("500 MG", 1), ("1 g", 1), ("30%", 1), ("30 %", 1)]));
+        assert_eq!(category(&raw, "dates").len(), 3);
+
+        let merged = entity_counts(text, &EntityOptions::default(), true);
+        assert_eq!(category(&merged, "measurements"), pairs(&[("500 mg", 3), ("1 g", 1), ("30 %", 2)]));
+        assert_eq!(category(&merged, "dates"), pairs(&[("2026-01-15", 3)]));
+        assert_eq!(category(&merged, "times"), pairs(&[("08:00", 2)]));
+        assert_eq!(category(&merged, "percentages"), pairs(&[("30 %", 2)]));
+
+        with_py(|py| {
+            let counts = extract_entity_counts(py, text, true, 1900, 2100, None).unwrap();
+            let measurements = counts.get_item("measurements").unwrap().unwrap();
+            let keys: Vec<String> = measurements.call_method0("keys").unwrap().iter().unwrap().map(|k| k.unwrap().extract().unwrap()).collect();
+            assert_eq!(keys, vec!["500 mg", "1 g", "30 %"]);
+            let count: usize = measurements.get_item("500 mg").unwrap().extract().unwrap();
+            assert_eq!(count, 3);
+            assert_eq!(counts.get_item("icd10").unwrap().unwrap().len().unwrap(), 0);
+            assert!(extract_entity_counts(py, text, false, 2100, 1900, None).is_err());
+        });
+    }
+
+    #[test]
+    fn test_parallel_extract_entities_matches_serial() {
+        let texts: Vec<String> = (0..200)
+            .map(|i| match i % 4 {
+                0 => format!("Control {:02}/03/2025 a las 10:{:02}. Enalapril {} mg", i % 28 + 1, i % 60, i),
+                1 => format!("Dx J45.909, SatO2 {}%, INR 2,{}", 90 + i % 10, i % 10),
+                2 => "Sin hallazgos relevantes.".to_string(),
+                _ => format!("Fiebre 38,{} °C el 3 de marzo de 2024 a las 2:30 p.m.", i % 10),
+            })
+            .collect();
+        let options = EntityOptions { normalize_times: true, ..EntityOptions::default() };
+        let serial: Vec<_> = texts.iter().map(|text| extract_entities_internal(text, &options)).collect();
+        with_py(|py| {
+            let parallel = parallel_extract_entities(py, texts.clone(), 1900, 2100, true, None, false).unwrap();
+            assert_eq!(parallel, serial);
+            assert!(parallel_extract_entities(py, vec![], 1900, 2100, false, None, false).unwrap().is_empty());
+            assert!(parallel_extract_entities(py, texts, 2100, 1900, false, None, false).is_err());
+        });
+    }
+
+    #[test]
+    fn test_extract_entities() {
+        let text = "Fecha: 15/01/2026 a las 14:30. Dosis: 500mg";
+        let result = with_py(|py| extract_entities(py, text, 1900, 2100, false, None, false)).unwrap();
+        assert_eq!(result.get("dates").unwrap().len(), 1);
+        assert_eq!(result.get("times").unwrap().len(), 1);
+        assert_eq!(result.get("measurements").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_clean_single_pass_matches_regex_pipeline() {
+        let reference = |text: &str| {
+            // Equivalent to the scanner as long as tags are well-formed
+            let text = Regex::new(r"<[^>]+>").unwrap().replace_all(text, "");
+            let text = Regex::new(r"[\x00-\x08\x0B\x0C\x0E-\x1F]").unwrap().replace_all(&text, "");
+            let text = Regex::new(r"\s+").unwrap().replace_all(&text, " ").into_owned();
+            text.trim().to_string()
+        };
+        let samples = [
+            "",
+            "   ",
+            "<p>Paciente\x07 estable</p>\n\n<br/>  Control\ten 48 hs  ",
+            "a <b> c",
+            "\u{A0}presión\u{2003}arterial\r\n",
+            "<div>\x01</div>x",
+        ];
+        for sample in samples {
+            assert_eq!(clean_medical_text_internal(sample, CleanOptions::default()), reference(sample), "sample: {:?}", sample);
+        }
+    }
+
+    #[test]
+    fn test_parallel_clean_texts_preserves_order() {
+        let texts: Vec<String> = (0..200)
+            .map(|i| if i % 7 == 0 { String::new() } else { format!("<b>Nota {}</b>   estable", i) })
+            .collect();
+        let cleaned: Vec<String> = with_py(|py| {
+            let clean = wrap_pyfunction_bound!(parallel_clean_texts, py).unwrap();
+            assert!(clean.call1((texts.clone(), true)).is_err());
+            clean.call1((texts.clone(),)).unwrap().extract().unwrap()
+        });
+        let serial: Vec<String> = texts.iter().map(|t| clean_medical_text_internal(t, CleanOptions::default())).collect();
+        assert_eq!(cleaned, serial);
+        assert_eq!(cleaned[0], "");
+        assert_eq!(cleaned[1], "Nota 1 estable");
+    }
+
+    #[test]
+    fn test_fix_mojibake_table() {
+        let cases = [
+            ("presiÃ³n arterial", "presión arterial"),
+            ("Temperatura 38.5 Â°C", "Temperatura 38.5 °C"),
+            ("niÃ±o de 3 aÃ±os", "niño de 3 años"),
+            ("ESPAÃ‘A", "ESPAÑA"),
+            ("cardÃ\u{AD}aco", "cardíaco"),
+            ("Ã\u{81}REA", "ÁREA"),
+            ("Â¿dolor? Â¡no!", "¿dolor? ¡no!"),
+            ("10 Âµg/ml", "10 µg/ml"),
+            // Already-correct text and lone lookalike characters are untouched
+            ("presión 38 °C", "presión 38 °C"),
+            ("Ã sola y Â suelta", "Ã sola y Â suelta"),
+            ("", ""),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(fix_mojibake_internal(input), expected, "input: {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_measure_re_matches_degree_celsius() {
+        let found: Vec<&str> = measurements::MEASURE_RE
+            .find_iter("Temperatura: 38.5 °C, luego 37.2ºC")
+            .map(|m| m.as_str())
+            .collect();
+        assert_eq!(found, vec!["38.5 °C", "37.2ºC"]);
+    }
+
+    #[test]
+    fn test_extract_entities_single_temperature() {
+        let entities = extract_entities_internal("Temperatura: 38.5 °C", &EntityOptions::default());
+        assert_eq!(entities["measurements"], vec!["38.5 °C"]);
+        let spaced = extract_entities_internal("Temperatura: 38,5 ° C. Axilar 37 º C", &EntityOptions::default());
+        assert_eq!(spaced["measurements"], vec!["38,5 ° C", "37 º C"]);
+    }
+
+    #[test]
+    fn test_extract_entities_with_spans_char_offsets() {
+        let text = "Evolución: 15/01/2026 a las 14:30. Presión 120 mmHg, T° 38.5 °C, dosis 500mg";
+        let chars: Vec<char> = text.chars().collect();
+        let spans = extract_entities_with_spans_internal(text, &EntityOptions::default());
+        let plain = extract_entities_internal(text, &EntityOptions::default());
+        
+        for (category, found) in &spans {
+            let texts: Vec<String> = found.iter().map(|(t, _, _)| t.clone()).collect();
+            assert_eq!(&texts, &plain[category]);
+            for (matched, start, end) in found {
+                let slice: String = chars[*start..*end].iter().collect();
+                assert_eq!(&slice, matched);
+            }
+        }
+        // "Evolución" shifts byte offsets by one; char offsets must not drift
+        assert_eq!(spans["dates"], vec![("15/01/2026".to_string(), 11, 21)]);
+        assert_eq!(spans["times"], vec![("14:30".to_string(), 28, 33)]);
+    }
+
+    #[test]
+    fn test_extract_entities_with_spans_after_wide_characters() {
+        // Two-, three- and four-byte characters before every match
+        let text = "Señora 👵 — Dx: I10 ⚕ €; Ñandú 🩺 3 de marzo de 2025 → 2:30 p.m., 38,5 °C 🔥 E11.9";
+        let chars: Vec<char> = text.chars().collect();
+        let spans = with_py(|py| extract_entities_with_spans(py, text, 1900, 2100, None)).unwrap();
+        for found in spans.values() {
+            for (matched, start, end) in found {
+                assert_eq!(&chars[*start..*end].iter().collect::<String>(), matched);
+            }
+        }
+        let icd10: Vec<&str> = spans["icd10"].iter().map(|(code, _, _)| code.as_str()).collect();
+        assert_eq!(icd10, vec!["I10", "E11.9"]);
+        assert_eq!(spans["dates"][0].0, "3 de marzo de 2025");
+        assert_eq!(spans["times"][0].0, "2:30 p.m.");
+        assert_eq!(spans["measurements"][0].0, "38,5 °C");
+    }
+
+    /// Ticks a second Python thread made while `call` ran with the GIL held
+    /// by this thread; zero unless `call` releases it
+    fn python_ticks_during(call: impl FnOnce(Python<'_>)) -> usize {
+        let done = Arc::new(AtomicBool::new(false));
+        let ticks = Arc::new(AtomicUsize::new(0));
+        with_py(|_| ());
+        
+        // A second thread that can only make progress while it holds the GIL
+        let ticker = {
+            let (done, ticks) = (done.clone(), ticks.clone());
+            std::thread::spawn(move || {
+                while !done.load(Ordering::SeqCst) {
+                    Python::with_gil(|_| ticks.fetch_add(1, Ordering::SeqCst));
+                    std::thread::yield_now();
+                }
+            })
+        };
+        
+        let ticks_during_call = with_py(|py| {
+            // While this thread holds the GIL the ticker is blocked, so any
+            // ticks counted here happened inside allow_threads
+            let before = ticks.load(Ordering::SeqCst);
+            call(py);
+            ticks.load(Ordering::SeqCst) - before
+        });
+        done.store(true, Ordering::SeqCst);
+        ticker.join().unwrap();
+        ticks_during_call
+    }
+
+    #[test]
+    fn test_parallel_chunk_texts_releases_gil() {
+        let sentence = "Paciente estable, sin cambios en la medicación habitual. ";
+        let texts: Vec<String> = (0..32).map(|_| sentence.repeat(1_000)).collect();
+        let ticks = python_ticks_during(|py| {
+            parallel_chunk_texts(py, texts, 500, 50, "chars", None, true).unwrap();
+        });
+        assert!(ticks > 0, "no Python thread ran during parallel_chunk_texts");
+    }
+
+    #[test]
+    fn test_parallel_chunk_texts_releases_gil_with_tokens() {
+        let sentence = "Control 15/01/2026: TA 120/80 mmHg, FC 72 lpm, afebril. ";
+        let texts: Vec<String> = (0..32).map(|_| sentence.repeat(1_000)).collect();
+        let ticks = python_ticks_during(|py| {
+            parallel_chunk_texts(py, texts, 256, 32, "tokens", None, true).unwrap();
+        });
+        assert!(ticks > 0, "no Python thread ran during parallel_chunk_texts (tokens)");
+    }
+
+    #[test]
+    fn test_large_inputs_match_small_input_results() {
+        let note = "<p>Control 15/01/2026 a las 14:30. Dosis: 500mg</p>   ";
+        let large = note.repeat(GIL_RELEASE_THRESHOLD / note.len() + 1);
+        assert!(large.len() >= GIL_RELEASE_THRESHOLD);
+        with_py(|py| {
+            let cleaned: String = wrap_pyfunction_bound!(clean_medical_text, py).unwrap().call1((&large,)).unwrap().extract().unwrap();
+            assert_eq!(cleaned, clean_medical_text_internal(&large, CleanOptions::default()));
+            assert_eq!(extract_entities(py, &large, 1900, 2100, false, None, false).unwrap(), extract_entities_internal(&large, &EntityOptions::default()));
+        });
+    }
+
+    #[test]
+    fn test_extract_entities_drops_impossible_dates() {
+        let text = "Ingreso 12/08/2025, lote 45/23/0000, control 31/04/2025 y 29/02/2024";
+        assert_eq!(
+            extract_entities_internal(text, &EntityOptions::default())["dates"],
+            vec!["12/08/2025", "29/02/2024"]
+        );
+        let spans = with_py(|py| extract_entities_with_spans(py, text, 2025, 2030, None)).unwrap();
+        assert_eq!(spans["dates"], vec![("12/08/2025".to_string(), 8, 18)]);
+        assert!(with_py(|py| extract_entities(py, text, 2030, 2025, false, None, false).is_err()));
+    }
+
+    #[test]
+    fn test_extract_times_validated_and_normalized() {
+        let text = "Ingresa 14:30 hs, ECG 8.15hs, control 2:30 p.m.; OCR 72:95 y 25:10; \
+                    temperatura 37.50, dilución 1:1000, a las 23:59:59.";
+        assert_eq!(
+            extract_entities_internal(text, &EntityOptions::default())["times"],
+            vec!["14:30 hs", "8.15hs", "2:30 p.m.", "23:59:59"]
+        );
+        let normalized = with_py(|py| extract_entities(py, text, 1900, 2100, true, None, false)).unwrap();
+        assert_eq!(normalized["times"], vec!["14:30", "08:15", "14:30", "23:59:59"]);
+
+        let text = "Dosis 08:00 y 20:00; heparina dilución 1:10, relación I:E 1:2, relación 1:30; \
+                    control 9:15 AM y 9:15 PM, 12:05 a. m.";
+        assert_eq!(
+            extract_entities_internal(text, &EntityOptions::default())["times"],
+            vec!["08:00", "20:00", "9:15 AM", "9:15 PM", "12:05 a. m."]
+        );
+        let normalized = with_py(|py| extract_entities(py, text, 1900, 2100, true, None, false)).unwrap();
+        assert_eq!(normalized["times"], vec!["08:00", "20:00", "09:15", "21:15", "00:05"]);
+    }
+
+    #[test]
+    fn test_extract_entities_extra_units() {
+        let text = "Creatinina 1,2 mg/dL, 20 gotas y programa de 5 sesiones";
+        let default = with_py(|py| extract_entities(py, text, 1900, 2100, false, None, false)).unwrap();
+        assert_eq!(default["measurements"], vec!["1,2 mg/dL"]);
+        let extra = Some(vec!["gotas".to_string(), "sesiones".to_string()]);
+        let found = with_py(|py| extract_entities(py, text, 1900, 2100, false, extra, false)).unwrap();
+        assert_eq!(found["measurements"], vec!["1,2 mg/dL", "20 gotas", "5 sesiones"]);
+    }
+
+    #[test]
+    fn test_extract_textual_dates() {
+        let text = "Sábado 15 de enero de 2026 consulta; control el 3 de marzo del 2025, \
+                    alta 1° de Mayo. Cirugía 20 DIC. 2024 y 7 sept, 2023. Vuelve en enero \
+                    con marcha normal; 12/08/2025.";
+        assert_eq!(
+            extract_entities_internal(text, &EntityOptions::default())["dates"],
+            vec![
+                "15 de enero de 2026",
+                "3 de marzo del 2025",
+                "1° de Mayo",
+                "20 DIC. 2024",
+                "7 sept, 2023",
+                "12/08/2025",
+            ]
+        );
+        assert!(extract_entities_internal("Internado desde enero, mayores de 3 meses", &EntityOptions::default())["dates"].is_empty());
+
+        let text = "Ingreso 15 de Enero de 2026, alta 20/01/2026, control 1° de mayo";
+        let iso = with_py(|py| extract_entities(py, text, 1900, 2100, false, None, true)).unwrap();
+        assert_eq!(iso["dates"], vec!["2026-01-15", "2026-01-20", "1° de mayo"]);
     }
 }
@@ -0,0 +1,449 @@
+//! Measurement (number + unit) extraction
+//!
+//! The unit alternation is generated from a unit list: longer units are
+//! tried first so "120 mmHg" is not cut to "120 mm", and units ending in a
+//! letter must end at a word boundary so "5 gotas" is not "5 g". A match
+//! may be a range ("10-20 mg", "500 a 1000 mg") and a unit may carry
+//! denominators ("mg/kg/día", "mcg/kg/min"). Lists other than the default
+//! are compiled on demand and cached.
+
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+
+use lru::LruCache;
+use once_cell::sync::Lazy;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use regex::Regex;
+
+use crate::CharCursor;
+
+/// Units recognized by default (matched case-insensitively)
+pub(crate) const DEFAULT_UNITS: &[&str] = &[
+    // Mass and volume
+    "mg", "g", "kg", "mcg", "µg", "μg", "ml", "cc", "mL/min",
+    // Length and pressure
+    "mm", "cm", "mmHg",
+    // Rates
+    "bpm", "lpm",
+    // Temperature ("ºC" is a common keyboard substitute for "°C") and ratios
+    "°C", "ºC", "%",
+    // Concentrations and activity
+    "UI", "UI/L", "mmol/L", "mEq/L", "ng/mL", "g/dL", "mg/dL",
+];
+
+/// Denominators that may follow a unit ("mg/kg", "mcg/kg/min", "ml/h")
+///
+/// Only allowed after a recognized unit, so a bare "12/05" is never a
+/// measurement.
+const DENOMINATORS: &[&str] = &["kg", "min", "h", "hr", "hs", "hora", "día", "dia", "d", "m2", "m²", "24h", "dosis"];
+
+/// Spellings reported under another unit's name
+const UNIT_ALIASES: &[(&str, &str)] = &[("mcg", "µg"), ("μg", "µg"), ("cc", "ml")];
+
+/// Unit lists built at call time kept between calls
+const CACHE_CAPACITY: usize = 32;
+
+/// "°C" written with a space after the degree sign ("38.5 ° C")
+const SPACED_CELSIUS: &str = r"[°º]\s+C\b";
+
+/// A number, with Spanish thousands dots ("1.200") or a decimal comma/point
+pub(crate) const NUMBER: &str = r"\d{1,3}(?:\.\d{3})+(?:,\d+)?|\d+(?:[.,]\d+)?";
+
+pub(crate) static MEASURE_RE: Lazy<Regex> = Lazy::new(|| build_regex(DEFAULT_UNITS.iter().copied()));
+
+static REGEX_CACHE: Lazy<Mutex<LruCache<Vec<String>, Regex>>> = Lazy::new(|| {
+    Mutex::new(LruCache::new(NonZeroUsize::new(CACHE_CAPACITY).unwrap_or(NonZeroUsize::MIN)))
+});
+
+/// Any number of "/denominator" after a unit, shared by both extractors so
+/// extract_measurements and extract_measurements_structured agree
+static DENOMINATOR_SUFFIX: Lazy<String> =
+    Lazy::new(|| format!("(?:/(?:{}))*", unit_alternation(DENOMINATORS.iter().copied())));
+
+/// Like MEASURE_RE, with value/max/unit groups and ranges ("5-10 mg", "5 a 10 mg");
+/// denominators are part of the unit, so "mg/kg" is never read as "mg"
+static STRUCTURED_RE: Lazy<Regex> = Lazy::new(|| {
+    let pattern = format!(
+        r"(?i)\b(?P<value>{num})(?:(?:\s*[-–]\s*|\s+a\s+)(?P<max>{num}))?\s*(?P<unit>(?:{units}|{celsius}){denominators})",
+        num = NUMBER,
+        units = unit_alternation(DEFAULT_UNITS.iter().copied()),
+        celsius = SPACED_CELSIUS,
+        denominators = *DENOMINATOR_SUFFIX,
+    );
+    Regex::new(&pattern).expect("Invalid structured measurement regex - this is a bug")
+});
+
+/// One measurement with its parsed value
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Measurement {
+    /// The value, or the lower bound of a range
+    pub value: f64,
+    /// Upper bound of a range ("5-10 mg")
+    pub value_max: Option<f64>,
+    /// Normalized unit ("mcg" -> "µg", "cc" -> "ml", "MG" -> "mg")
+    pub unit: String,
+    pub raw: String,
+    /// Character offsets of the match
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Measurement {
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("value", self.value)?;
+        dict.set_item("value_max", self.value_max)?;
+        dict.set_item("unit", &self.unit)?;
+        dict.set_item("raw", &self.raw)?;
+        dict.set_item("span", (self.start, self.end))?;
+        Ok(dict)
+    }
+}
+
+/// Extract measurements with a custom unit list
+///
+/// A labelled weight or height ("Peso: 82 kg", "talla 175 cm") belongs to
+/// extract_demographics and is left out unless include_demographics is set.
+///
+/// Args:
+///     text: The input text
+///     units: Units to recognize instead of the default list (mg, ml, g/dL,
+///            mmol/L, mmHg, °C, %, ...). Matching is case-insensitive.
+///     include_demographics: Also report the measurements inside weights and
+///                           heights found by extract_demographics
+///                           (default: False)
+///
+/// Returns:
+///     List of matched measurements ("500mg", "3,5 mmol/L", "10-20 mg",
+///     "80 mg/kg/día", ...)
+///
+/// Raises:
+///     ValueError: If units is given but holds no non-blank unit
+#[pyfunction]
+#[pyo3(signature = (text, units=None, include_demographics=false))]
+pub fn extract_measurements(
+    py: Python<'_>,
+    text: &str,
+    units: Option<Vec<String>>,
+    include_demographics: bool,
+) -> PyResult<Vec<String>> {
+    let re = match units {
+        // An empty alternation would match every bare number
+        Some(units) if units.iter().all(|unit| unit.trim().is_empty()) => {
+            return Err(PyValueError::new_err("units must contain at least one unit"));
+        }
+        Some(units) => cached_regex(units),
+        None => MEASURE_RE.clone(),
+    };
+    Ok(crate::allow_threads_if_large(py, text.len(), || {
+        let owned = if include_demographics { Vec::new() } else { crate::demographics::body_measure_spans(text) };
+        let mut cursor = CharCursor::new(text);
+        re.find_iter(text)
+            .filter(|m| !within(&owned, cursor.char_offset(m.start()), cursor.char_offset(m.end())))
+            .map(|m| m.as_str().to_string())
+            .collect()
+    }))
+}
+
+/// Extract measurements as numeric value plus normalized unit
+///
+/// Numbers follow Spanish conventions: "38,5" and "38.5" are 38.5, while
+/// dot-grouped thousands ("1.200 ml", "1.500,5 mg") are 1200 and 1500.5;
+/// "0.125" stays a decimal. A range ("5-10 mg", "5 a 10 mg") is a single
+/// entry with its lower bound in "value" and upper bound in "value_max".
+/// Denominators stay in the unit: "5-10 mg/kg" is a per-weight dose with
+/// unit "mg/kg", never an absolute "mg" dose. As in extract_measurements,
+/// labelled weights and heights are left to extract_demographics.
+///
+/// Args:
+///     text: The input text
+///     include_demographics: Also report the measurements inside weights and
+///                           heights found by extract_demographics
+///                           (default: False)
+///
+/// Returns:
+///     List of dicts with "value" (float), "value_max" (float or None),
+///     "unit" (normalized: "mcg" -> "µg", "cc" -> "ml"), "raw" and "span"
+///     (start_char, end_char)
+#[pyfunction]
+#[pyo3(signature = (text, include_demographics=false))]
+pub fn extract_measurements_structured<'py>(
+    py: Python<'py>,
+    text: &str,
+    include_demographics: bool,
+) -> PyResult<Vec<Bound<'py, PyDict>>> {
+    extract_measurements_structured_internal(text, include_demographics)
+        .iter()
+        .map(|measurement| measurement.to_dict(py))
+        .collect()
+}
+
+pub(crate) fn extract_measurements_structured_internal(text: &str, include_demographics: bool) -> Vec<Measurement> {
+    let owned = if include_demographics { Vec::new() } else { crate::demographics::body_measure_spans(text) };
+    let mut cursor = CharCursor::new(text);
+    STRUCTURED_RE
+        .captures_iter(text)
+        .filter_map(|caps| {
+            let whole = caps.get(0)?;
+            let value = parse_number(caps.name("value")?.as_str())?;
+            let value_max = match caps.name("max") {
+                Some(max) => Some(parse_number(max.as_str())?),
+                None => None,
+            };
+            Some(Measurement {
+                value,
+                value_max,
+                unit: normalize_unit(caps.name("unit")?.as_str()),
+                raw: whole.as_str().to_string(),
+                start: cursor.char_offset(whole.start()),
+                end: cursor.char_offset(whole.end()),
+            })
+        })
+        .filter(|measurement| !within(&owned, measurement.start, measurement.end))
+        .collect()
+}
+
+/// Whether a character range lies inside one of `spans`
+fn within(spans: &[(usize, usize)], start: usize, end: usize) -> bool {
+    spans.iter().any(|&(span_start, span_end)| span_start <= start && end <= span_end)
+}
+
+/// Parse a number written with Spanish or plain conventions
+pub(crate) fn parse_number(raw: &str) -> Option<f64> {
+    let groups: Vec<&str> = raw.split('.').collect();
+    let is_grouped = raw.contains(',') && groups.len() > 1
+        || groups.len() > 1 && groups[0] != "0" && groups[1..].iter().all(|g| g.len() == 3);
+    let plain = if is_grouped { raw.replace('.', "") } else { raw.to_string() };
+    plain.replace(',', ".").parse().ok()
+}
+
+/// Canonical spelling of a matched unit
+///
+/// Denominators after a known unit are kept in lowercase: "MG/KG/día"
+/// becomes "mg/kg/día", "cc/h" becomes "ml/h".
+pub(crate) fn normalize_unit(unit: &str) -> String {
+    // Only the spaced "° C" has inner whitespace
+    let unit: String = unit.split_whitespace().collect();
+    // The longest known unit before a "/", so "mg/dL" is not "mg" + "/dl"
+    let mut base_end = unit.len();
+    loop {
+        if let Some(known) = known_unit(&unit[..base_end]) {
+            return format!("{}{}", known, unit[base_end..].to_lowercase());
+        }
+        match unit[..base_end].rfind('/') {
+            Some(slash) => base_end = slash,
+            None => return unit,
+        }
+    }
+}
+
+/// Canonical spelling of a default unit or alias
+fn known_unit(unit: &str) -> Option<&'static str> {
+    let lower = unit.to_lowercase();
+    if let Some(&(_, canonical)) = UNIT_ALIASES.iter().find(|(alias, _)| *alias == lower) {
+        return Some(canonical);
+    }
+    DEFAULT_UNITS.iter().find(|known| known.to_lowercase() == lower).copied()
+}
+
+/// Measurement regex for the default units plus `extra_units`
+pub(crate) fn regex_with_extra_units(extra_units: &[String]) -> Regex {
+    if extra_units.is_empty() {
+        return MEASURE_RE.clone();
+    }
+    cached_regex(DEFAULT_UNITS.iter().map(|u| u.to_string()).chain(extra_units.iter().cloned()).collect())
+}
+
+fn cached_regex(mut units: Vec<String>) -> Regex {
+    units.sort();
+    units.dedup();
+    let mut cache = REGEX_CACHE.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(re) = cache.get(&units) {
+        return re.clone();
+    }
+    let re = build_regex(units.iter().map(String::as_str));
+    cache.put(units, re.clone());
+    re
+}
+
+/// Measurement regex: an optional range start ("10-", "10 a "), a number,
+/// a unit and optional per-weight/per-time denominators
+fn build_regex<'a>(units: impl Iterator<Item = &'a str>) -> Regex {
+    // Escaped literals only, so this cannot fail short of a regex crate bug
+    Regex::new(&format!(
+        r"(?i)\b(?:(?:{num})(?:\s*[-–]\s*|\s+a\s+))?(?:{num})\s*(?:{units}|{celsius}){denominators}",
+        num = NUMBER,
+        units = unit_alternation(units),
+        celsius = SPACED_CELSIUS,
+        denominators = *DENOMINATOR_SUFFIX,
+    ))
+    .expect("Invalid measurement regex - this is a bug")
+}
+
+/// Longest-first alternation of escaped units with trailing word boundaries
+pub(crate) fn unit_alternation<'a>(units: impl Iterator<Item = &'a str>) -> String {
+    let mut units: Vec<&str> = units.map(str::trim).filter(|u| !u.is_empty()).collect();
+    units.sort_by_key(|u| std::cmp::Reverse(u.chars().count()));
+    units
+        .iter()
+        .map(|unit| {
+            let escaped = regex::escape(unit);
+            if unit.ends_with(char::is_alphanumeric) {
+                format!(r"{}\b", escaped)
+            } else {
+                escaped
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("|")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(re: &Regex, text: &str) -> Vec<String> {
+        re.find_iter(text).map(|m| m.as_str().to_string()).collect()
+    }
+
+    #[test]
+    fn test_lab_units() {
+        let text = "Na 138 mEq/L, glucemia 110 mg/dL, Hb 12,5 g/dL, K 4.1 mmol/L, TSH 2 ng/mL, \
+                    CPK 150 UI/L, clearance 90 mL/min, FC 80 lpm, B12 500 µg, TA 120 mmHg";
+        assert_eq!(
+            find(&MEASURE_RE, text),
+            vec![
+                "138 mEq/L", "110 mg/dL", "12,5 g/dL", "4.1 mmol/L", "2 ng/mL",
+                "150 UI/L", "90 mL/min", "80 lpm", "500 µg", "120 mmHg",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unit_word_boundaries() {
+        assert_eq!(find(&MEASURE_RE, "500mg y 500 mg"), vec!["500mg", "500 mg"]);
+        assert!(find(&MEASURE_RE, "5 gotas del programa 2 gramos").is_empty());
+        assert_eq!(find(&MEASURE_RE, "Sat 97%, 38.5°C"), vec!["97%", "38.5°C"]);
+    }
+
+    #[test]
+    fn test_compound_units_and_ranges() {
+        let text = "Clearance 85 mL/min, Na 140 mEq/L, K 4,2 mmol/L, CPK 200 UI/L, PSA 1,5 ng/mL, \
+                    Hb 13 g/dL; noradrenalina 0,1 mcg/kg/min, amoxicilina 80 mg/kg/día, \
+                    ibuprofeno 10-20 mg, paracetamol 500 a 1000 mg, diuresis 1,5 ml/kg/h";
+        assert_eq!(
+            find(&MEASURE_RE, text),
+            vec![
+                "85 mL/min", "140 mEq/L", "4,2 mmol/L", "200 UI/L", "1,5 ng/mL", "13 g/dL",
+                "0,1 mcg/kg/min", "80 mg/kg/día", "10-20 mg", "500 a 1000 mg", "1,5 ml/kg/h",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dates_and_fractions_are_not_units() {
+        let text = "Control 12/05/2025, TA 120/80, dosis 1/2 comprimido, 3/día, cama 4-5, 24/7";
+        assert!(find(&MEASURE_RE, text).is_empty());
+        // A denominator needs a unit before it
+        assert_eq!(find(&MEASURE_RE, "IMC 31 kg/m2, 2 /kg"), vec!["31 kg/m2"]);
+    }
+
+    #[test]
+    fn test_extra_and_replacement_units() {
+        let text = "Creatinina 1,2 mg/dL y 5 gotas cada 8 h; INR 2 U";
+        let extra = regex_with_extra_units(&["gotas".to_string(), "U".to_string()]);
+        assert_eq!(find(&extra, text), vec!["1,2 mg/dL", "5 gotas", "2 U"]);
+        // Same list in another order comes from the cache
+        let again = regex_with_extra_units(&["U".to_string(), "gotas".to_string()]);
+        assert_eq!(extra.as_str(), again.as_str());
+
+        let only_drops = cached_regex(vec!["gotas".to_string()]);
+        assert_eq!(find(&only_drops, text), vec!["5 gotas"]);
+    }
+
+    #[test]
+    fn test_empty_unit_list_is_rejected() {
+        crate::with_py(|py| {
+            for units in [vec![], vec![" ".to_string()]] {
+                let err = extract_measurements(py, "Peso 80, edad 45", Some(units), false).unwrap_err();
+                assert!(err.is_instance_of::<PyValueError>(py));
+            }
+            let found = extract_measurements(py, "Peso 80 kg, edad 45", Some(vec!["kg".to_string()]), true).unwrap();
+            assert_eq!(found, vec!["80 kg"]);
+        });
+    }
+
+    #[test]
+    fn test_structured_values_and_units() {
+        let text = "Fiebre 38,5 °C, diuresis 1.200 ml, B12 250 mcg, suero 500 cc, \
+                    Na 138 MEQ/L, digoxina 0.125 mg, peso 1.500,5 g";
+        let found: Vec<(f64, String)> = extract_measurements_structured_internal(text, true)
+            .into_iter()
+            .map(|m| (m.value, m.unit))
+            .collect();
+        let expected = [
+            (38.5, "°C"), (1200.0, "ml"), (250.0, "µg"), (500.0, "ml"),
+            (138.0, "mEq/L"), (0.125, "mg"), (1500.5, "g"),
+        ];
+        assert_eq!(found, expected.iter().map(|&(v, u)| (v, u.to_string())).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_structured_ranges_and_spans() {
+        let text = "Ibuprofeno 5-10 mg/kg? no: 200 a 400 mg, luego 37,5 – 38 °C";
+        let found = extract_measurements_structured_internal(text, true);
+        let summary: Vec<(f64, Option<f64>, &str)> =
+            found.iter().map(|m| (m.value, m.value_max, m.unit.as_str())).collect();
+        assert_eq!(summary, vec![(5.0, Some(10.0), "mg/kg"), (200.0, Some(400.0), "mg"), (37.5, Some(38.0), "°C")]);
+        let spaced = extract_measurements_structured_internal("T 38 ° C", true);
+        assert_eq!((spaced[0].value, spaced[0].unit.as_str(), spaced[0].raw.as_str()), (38.0, "°C", "38 ° C"));
+
+        let chars: Vec<char> = text.chars().collect();
+        for m in &found {
+            assert_eq!(chars[m.start..m.end].iter().collect::<String>(), m.raw);
+        }
+        assert_eq!(found[1].raw, "200 a 400 mg");
+    }
+
+    #[test]
+    fn test_demographics_own_labelled_weight_and_height() {
+        let text = "Peso: 82 kg, talla 175 cm. Paracetamol 500 mg; lesión de 2 cm";
+        let raw: Vec<String> = extract_measurements_structured_internal(text, false).into_iter().map(|m| m.raw).collect();
+        assert_eq!(raw, vec!["500 mg", "2 cm"]);
+        assert_eq!(extract_measurements_structured_internal(text, true).len(), 4);
+        crate::with_py(|py| {
+            assert_eq!(extract_measurements(py, text, None, false).unwrap(), vec!["500 mg", "2 cm"]);
+            assert_eq!(extract_measurements(py, text, None, true).unwrap(), vec!["82 kg", "175 cm", "500 mg", "2 cm"]);
+        });
+    }
+
+    #[test]
+    fn test_structured_per_weight_doses() {
+        let text = "Amoxicilina 80 MG/KG/día, noradrenalina 0,1 mcg/kg/min, suero 20 cc/h, Hb 13 g/dL";
+        let units: Vec<String> = extract_measurements_structured_internal(text, false).into_iter().map(|m| m.unit).collect();
+        assert_eq!(units, vec!["mg/kg/día", "µg/kg/min", "ml/h", "g/dL"]);
+    }
+
+    #[test]
+    fn test_extractors_agree_on_denominators() {
+        let text = "amoxicilina 80 mg/kg/día, diuresis 1500 ml/24h, IMC 31 kg/m2, 2 dosis de 5 mg/dosis";
+        let raw: Vec<String> = extract_measurements_structured_internal(text, false).into_iter().map(|m| m.raw).collect();
+        assert_eq!(raw, find(&MEASURE_RE, text));
+        assert_eq!(raw, vec!["80 mg/kg/día", "1500 ml/24h", "31 kg/m2", "5 mg/dosis"]);
+    }
+
+    #[test]
+    fn test_extractors_agree_on_grouped_thousands() {
+        let text = "Dieta 1.500,5 g, penicilina 1.500.000 UI, diuresis 1.200 - 1.500 ml";
+        let structured = extract_measurements_structured_internal(text, false);
+        let raw: Vec<String> = structured.iter().map(|m| m.raw.clone()).collect();
+        assert_eq!(raw, find(&MEASURE_RE, text));
+        assert_eq!(raw, vec!["1.500,5 g", "1.500.000 UI", "1.200 - 1.500 ml"]);
+        assert_eq!(structured[0].value, 1500.5);
+        assert_eq!(structured[1].value, 1_500_000.0);
+        assert_eq!((structured[2].value, structured[2].value_max), (1200.0, Some(1500.0)));
+    }
+}
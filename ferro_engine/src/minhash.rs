@@ -0,0 +1,453 @@
+//! MinHash signatures and LSH near-duplicate grouping
+//!
+//! pairwise_similarity is quadratic; at hundreds of thousands of chunks it
+//! is replaced by MinHash: each text becomes a fixed-size signature whose
+//! agreement rate estimates the Jaccard similarity of the texts' shingle
+//! sets, and banded locality-sensitive hashing only compares texts that
+//! share a band. Shingles are character 5-grams of the lowercased words
+//! joined by single spaces, so punctuation and spacing do not matter;
+//! near_dups uses word 3-grams instead. Hashing is seeded with constants:
+//! signatures are stable across runs and machines and can be stored.
+
+use std::collections::HashMap;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+
+/// Characters per shingle
+const SHINGLE_CHARS: usize = 5;
+
+/// Words per shingle in near_dups
+const SHINGLE_WORDS: usize = 3;
+
+/// Modulus of the universal hash family (the Mersenne prime 2^61 - 1)
+const MERSENNE_61: u64 = (1 << 61) - 1;
+
+/// Compute MinHash signatures
+///
+/// Args:
+///     texts: List of texts
+///     num_hashes: Signature length; more hashes give a tighter Jaccard
+///                 estimate (default: 128)
+///
+/// Returns:
+///     One list of num_hashes integers per text. Texts without words get
+///     a signature of all 2**61 - 1.
+///
+/// Raises:
+///     ValueError: If num_hashes is 0
+#[pyfunction]
+#[pyo3(signature = (texts, num_hashes=128))]
+pub fn minhash_signatures(py: Python<'_>, texts: Vec<String>, num_hashes: usize) -> PyResult<Vec<Vec<u64>>> {
+    if num_hashes == 0 {
+        return Err(PyValueError::new_err("num_hashes must be greater than 0"));
+    }
+    Ok(py.allow_threads(|| {
+        let hashers = Hashers::new(num_hashes);
+        texts.par_iter().map(|text| hashers.signature(text)).collect()
+    }))
+}
+
+/// Group texts that are probably near-duplicates
+///
+/// Signatures are split into bands; texts sharing any whole band become
+/// candidates, and candidates whose signatures agree on at least
+/// `threshold` of their hashes are joined. Groups are closed under
+/// chaining: if A~B and B~C, all three share a group. The band layout is
+/// chosen so texts at the threshold collide with high probability. Texts
+/// without words are never grouped.
+///
+/// Args:
+///     texts: List of texts
+///     threshold: Estimated Jaccard similarity to count as a duplicate,
+///                greater than 0.0 and at most 1.0 (default: 0.8)
+///     num_hashes: Signature length (default: 128)
+///
+/// Returns:
+///     Groups of two or more indices, each sorted, ordered by first index
+///
+/// Raises:
+///     ValueError: If threshold is outside (0.0, 1.0] or num_hashes is 0
+#[pyfunction]
+#[pyo3(signature = (texts, threshold=0.8, num_hashes=128))]
+pub fn find_near_duplicates(
+    py: Python<'_>,
+    texts: Vec<String>,
+    threshold: f64,
+    num_hashes: usize,
+) -> PyResult<Vec<Vec<usize>>> {
+    if !(threshold > 0.0 && threshold <= 1.0) {
+        return Err(PyValueError::new_err("threshold must be greater than 0.0 and at most 1.0"));
+    }
+    if num_hashes == 0 {
+        return Err(PyValueError::new_err("num_hashes must be greater than 0"));
+    }
+    Ok(py.allow_threads(|| find_near_duplicates_internal(&texts, threshold, num_hashes)))
+}
+
+/// Pairs of chunks that are probably near-duplicates
+///
+/// Like find_near_duplicates, but over word 3-gram shingles (a chunk
+/// differing by a timestamp or one word still shares most of them) and
+/// without chaining: every qualifying pair is reported on its own.
+///
+/// Args:
+///     chunks: List of chunks
+///     threshold: Estimated Jaccard similarity that a pair must exceed,
+///                0.0-1.0 (default: 0.8)
+///     num_hashes: Signature length (default: 128)
+///
+/// Returns:
+///     Sorted list of (i, j) index pairs with i < j; n copies of one chunk
+///     give all n * (n - 1) / 2 of their pairs, so use find_near_duplicates
+///     for groups of copies
+///
+/// Raises:
+///     ValueError: If threshold is outside [0.0, 1.0] or num_hashes is 0
+#[pyfunction]
+#[pyo3(signature = (chunks, threshold=0.8, num_hashes=128))]
+pub fn near_dups(py: Python<'_>, chunks: Vec<String>, threshold: f64, num_hashes: usize) -> PyResult<Vec<(usize, usize)>> {
+    if !(0.0..=1.0).contains(&threshold) {
+        return Err(PyValueError::new_err("threshold must be between 0.0 and 1.0"));
+    }
+    if num_hashes == 0 {
+        return Err(PyValueError::new_err("num_hashes must be greater than 0"));
+    }
+    Ok(py.allow_threads(|| near_dups_internal(&chunks, threshold, num_hashes)))
+}
+
+pub(crate) fn near_dups_internal(chunks: &[String], threshold: f64, num_hashes: usize) -> Vec<(usize, usize)> {
+    let hashers = Hashers::new(num_hashes);
+    let signatures: Vec<Vec<u64>> =
+        chunks.par_iter().map(|chunk| hashers.signature_of(&word_shingle_hashes(chunk))).collect();
+    // Every qualifying pair is reported, so only unverified pairs are skipped
+    let mut pairs: Vec<(usize, usize)> = band_buckets(&signatures, band_layout(num_hashes, threshold))
+        .flat_map_iter(|members| {
+            pairs(&members)
+                .filter(|&(i, j)| estimated_jaccard(&signatures[i], &signatures[j]) > threshold)
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    pairs.par_sort_unstable();
+    pairs.dedup();
+    pairs
+}
+
+pub(crate) fn find_near_duplicates_internal(texts: &[String], threshold: f64, num_hashes: usize) -> Vec<Vec<usize>> {
+    let hashers = Hashers::new(num_hashes);
+    let signatures: Vec<Vec<u64>> = texts.par_iter().map(|text| hashers.signature(text)).collect();
+    let similar = |i: usize, j: usize| estimated_jaccard(&signatures[i], &signatures[j]) >= threshold;
+    let edges: Vec<(usize, usize)> = band_buckets(&signatures, band_layout(num_hashes, threshold))
+        .flat_map_iter(|members| bucket_edges(&members, similar))
+        .collect();
+
+    let mut groups = UnionFind::new(texts.len());
+    for (i, j) in edges {
+        groups.union(i, j);
+    }
+    groups.groups()
+}
+
+/// Indices sharing a whole band, one bucket per band and band value
+///
+/// Buckets of a single index are dropped, and empty signatures (texts
+/// without words) are never bucketed.
+fn band_buckets(signatures: &[Vec<u64>], (bands, rows): (usize, usize)) -> impl ParallelIterator<Item = Vec<usize>> + '_ {
+    (0..bands).into_par_iter().flat_map_iter(move |band| {
+        let mut buckets: HashMap<&[u64], Vec<usize>> = HashMap::new();
+        for (index, signature) in signatures.iter().enumerate() {
+            if signature.iter().all(|&h| h == MERSENNE_61) {
+                continue;
+            }
+            buckets.entry(&signature[band * rows..(band + 1) * rows]).or_default().push(index);
+        }
+        buckets.into_values().filter(|members| members.len() > 1).collect::<Vec<_>>()
+    })
+}
+
+/// Verified edges of one bucket, enough to connect its similar members
+///
+/// Each member is compared with the earlier members not yet in its group
+/// and joined to every one it matches, so an A~B~C chain is connected even
+/// when A and C differ. A bucket of n copies still costs n - 1 comparisons
+/// and edges instead of n(n - 1) / 2 pairs.
+fn bucket_edges(members: &[usize], similar: impl Fn(usize, usize) -> bool) -> Vec<(usize, usize)> {
+    let mut joined = UnionFind::new(members.len());
+    let mut edges = Vec::new();
+    for k in 1..members.len() {
+        for j in 0..k {
+            if joined.find(j) != joined.find(k) && similar(members[j], members[k]) {
+                joined.union(j, k);
+                edges.push((members[j], members[k]));
+            }
+        }
+    }
+    edges
+}
+
+/// Every (i, j) with i before j in `members`
+fn pairs(members: &[usize]) -> impl Iterator<Item = (usize, usize)> + '_ {
+    members
+        .iter()
+        .enumerate()
+        .flat_map(|(k, &i)| members[k + 1..].iter().map(move |&j| (i, j)))
+}
+
+/// Share of positions where two signatures agree
+pub(crate) fn estimated_jaccard(a: &[u64], b: &[u64]) -> f64 {
+    let agreeing = a.iter().zip(b).filter(|(x, y)| x == y).count();
+    agreeing as f64 / a.len().max(1) as f64
+}
+
+/// Bands and rows per band (bands * rows = num_hashes) for `threshold`
+///
+/// Texts with similarity s share a band with probability
+/// 1 - (1 - s^rows)^bands, an S-curve whose midpoint is about
+/// (1 / bands)^(1 / rows). The layout with the highest midpoint not above
+/// the threshold is picked, so true duplicates are rarely missed and the
+/// extra candidates are filtered afterwards.
+fn band_layout(num_hashes: usize, threshold: f64) -> (usize, usize) {
+    let midpoint = |&(bands, rows): &(usize, usize)| (1.0 / bands as f64).powf(1.0 / rows as f64);
+    (1..=num_hashes)
+        .filter(|&rows| num_hashes.is_multiple_of(rows))
+        .map(|rows| (num_hashes / rows, rows))
+        .filter(|layout| midpoint(layout) <= threshold)
+        .max_by(|a, b| midpoint(a).total_cmp(&midpoint(b)))
+        .unwrap_or((num_hashes, 1))
+}
+
+/// A family of (a * x + b) mod p hash functions
+pub(crate) struct Hashers {
+    coefficients: Vec<(u64, u64)>,
+}
+
+impl Hashers {
+    pub(crate) fn new(num_hashes: usize) -> Self {
+        let mut state = 0x5EED_F3A7_0C0D_E5E5_u64;
+        let coefficients = (0..num_hashes)
+            .map(|_| {
+                let a = splitmix64(&mut state) % (MERSENNE_61 - 1) + 1;
+                let b = splitmix64(&mut state) % MERSENNE_61;
+                (a, b)
+            })
+            .collect();
+        Self { coefficients }
+    }
+
+    pub(crate) fn signature(&self, text: &str) -> Vec<u64> {
+        self.signature_of(&shingle_hashes(text))
+    }
+
+    /// Signature of already hashed shingles
+    pub(crate) fn signature_of(&self, shingles: &[u64]) -> Vec<u64> {
+        self.coefficients
+            .iter()
+            .map(|&(a, b)| {
+                shingles
+                    .iter()
+                    .map(|&x| ((u128::from(a) * u128::from(x) + u128::from(b)) % u128::from(MERSENNE_61)) as u64)
+                    .min()
+                    .unwrap_or(MERSENNE_61)
+            })
+            .collect()
+    }
+}
+
+/// Hashes of the text's distinct character shingles
+fn shingle_hashes(text: &str) -> Vec<u64> {
+    let normalized: Vec<char> = crate::word_tokens(text, true, false).join(" ").chars().collect();
+    if normalized.is_empty() {
+        return Vec::new();
+    }
+    let mut hashes: Vec<u64> = normalized
+        .windows(SHINGLE_CHARS.min(normalized.len()))
+        .map(|window| fnv1a(window) % MERSENNE_61)
+        .collect();
+    hashes.sort_unstable();
+    hashes.dedup();
+    hashes
+}
+
+/// Hashes of the text's distinct word shingles
+///
+/// A text shorter than a shingle is one shingle of all its words.
+fn word_shingle_hashes(text: &str) -> Vec<u64> {
+    let words: Vec<Vec<char>> = crate::word_tokens(text, true, false).iter().map(|word| word.chars().collect()).collect();
+    if words.is_empty() {
+        return Vec::new();
+    }
+    let mut hashes: Vec<u64> = words
+        .windows(SHINGLE_WORDS.min(words.len()))
+        .map(|window| fnv1a(&window.join(&' ')) % MERSENNE_61)
+        .collect();
+    hashes.sort_unstable();
+    hashes.dedup();
+    hashes
+}
+
+/// 64-bit FNV-1a over the UTF-8 bytes of `chars`
+fn fnv1a(chars: &[char]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+    let mut buffer = [0u8; 4];
+    for c in chars {
+        for &byte in c.encode_utf8(&mut buffer).as_bytes() {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Disjoint sets of indices with path halving
+struct UnionFind {
+    parent: Vec<usize>,
+}
+
+impl UnionFind {
+    fn new(size: usize) -> Self {
+        Self { parent: (0..size).collect() }
+    }
+
+    fn find(&mut self, mut index: usize) -> usize {
+        while self.parent[index] != index {
+            self.parent[index] = self.parent[self.parent[index]];
+            index = self.parent[index];
+        }
+        index
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a != b {
+            self.parent[a.max(b)] = a.min(b);
+        }
+    }
+
+    /// Sets with two or more members, each sorted, ordered by first member
+    fn groups(mut self) -> Vec<Vec<usize>> {
+        let mut by_root: HashMap<usize, Vec<usize>> = HashMap::new();
+        for index in 0..self.parent.len() {
+            let root = self.find(index);
+            by_root.entry(root).or_default().push(index);
+        }
+        let mut groups: Vec<Vec<usize>> = by_root.into_values().filter(|group| group.len() > 1).collect();
+        groups.sort_unstable_by_key(|group| group[0]);
+        groups
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTE: &str = "Paciente de 67 años con antecedentes de hipertensión arterial y diabetes tipo 2 \
+                        que consulta por dolor torácico opresivo de dos horas de evolución, irradiado \
+                        a brazo izquierdo, acompañado de sudoración. Se realiza electrocardiograma que \
+                        muestra supradesnivel del ST en cara inferior y se deriva a hemodinamia.";
+
+    #[test]
+    fn test_signatures_are_deterministic_and_estimate_jaccard() {
+        let hashers = Hashers::new(256);
+        let first = hashers.signature(NOTE);
+        assert_eq!(first, Hashers::new(256).signature(NOTE));
+        assert_eq!(first.len(), 256);
+        // Case, punctuation and spacing are normalized away
+        let reformatted = NOTE.to_uppercase().replace(',', " ;  ");
+        assert_eq!(estimated_jaccard(&first, &hashers.signature(&reformatted)), 1.0);
+        let unrelated = hashers.signature("Fractura de radio distal tras caída de propia altura; se inmoviliza.");
+        assert!(estimated_jaccard(&first, &unrelated) < 0.2);
+        assert!(hashers.signature("  ...  ").iter().all(|&h| h == MERSENNE_61));
+    }
+
+    #[test]
+    fn test_band_layout_fits_threshold() {
+        for (num_hashes, threshold) in [(128, 0.8), (128, 0.5), (100, 0.9), (7, 0.8)] {
+            let (bands, rows) = band_layout(num_hashes, threshold);
+            assert_eq!(bands * rows, num_hashes);
+            let midpoint = (1.0 / bands as f64).powf(1.0 / rows as f64);
+            assert!(midpoint <= threshold, "{} hashes at {}: {}x{}", num_hashes, threshold, bands, rows);
+        }
+        assert_eq!(band_layout(128, 0.8), (16, 8));
+        assert_eq!(band_layout(128, 0.01), (128, 1));
+    }
+
+    #[test]
+    fn test_bucket_edges() {
+        let same_parity = |i: usize, j: usize| i % 2 == j % 2;
+        assert_eq!(bucket_edges(&[0, 1, 2, 3, 4], same_parity), vec![(0, 2), (1, 3), (0, 4)]);
+        assert!(bucket_edges(&[7, 8], |_, _| false).is_empty());
+        // A~B and B~C but not A~C: B joins A, then C still reaches B
+        let neighbours = |i: usize, j: usize| i.abs_diff(j) == 1;
+        assert_eq!(bucket_edges(&[5, 6, 7], neighbours), vec![(5, 6), (6, 7)]);
+        assert_eq!(bucket_edges(&[5, 7, 6], neighbours), vec![(5, 6), (7, 6)]);
+        let copies = std::cell::Cell::new(0);
+        let edges = bucket_edges(&[0, 1, 2, 3], |_, _| {
+            copies.set(copies.get() + 1);
+            true
+        });
+        assert_eq!((edges.len(), copies.get()), (3, 3));
+    }
+
+    #[test]
+    fn test_near_dups_one_word_apart() {
+        let a = "Paciente internado en sala general, evoluciona afebril y hemodinámicamente estable, \
+                 tolera la vía oral, se otorga el alta con control ambulatorio en siete días";
+        let b = a.replace("siete", "diez");
+        let chunks = vec![a.to_string(), b, String::new(), "Fractura de radio distal, se inmoviliza.".to_string()];
+        // 19 of 23 distinct word 3-grams shared: Jaccard about 0.83
+        assert!(near_dups_internal(&chunks, 0.95, 128).is_empty());
+        assert_eq!(near_dups_internal(&chunks, 0.5, 128), vec![(0, 1)]);
+        // Whitespace and case differences alone are exact duplicates
+        let spaced = vec![a.to_uppercase(), a.replace(", ", " ,   ")];
+        assert_eq!(near_dups_internal(&spaced, 0.99, 128), vec![(0, 1)]);
+
+        crate::with_py(|py| {
+            assert!(near_dups(py, chunks.clone(), 1.5, 128).is_err());
+            assert!(near_dups(py, chunks.clone(), -0.1, 128).is_err());
+            assert!(near_dups(py, chunks.clone(), 0.5, 0).is_err());
+            // Nothing exceeds 1.0
+            assert!(near_dups(py, spaced, 1.0, 128).unwrap().is_empty());
+        });
+    }
+
+    #[test]
+    fn test_edited_copy_is_grouped_unrelated_is_not() {
+        let edited = NOTE.replace("dos horas", "tres horas").replace("sudoración", "sudoración profusa");
+        let texts: Vec<String> = vec![
+            NOTE.to_string(),
+            "Control de niño sano de 4 meses, vacunas al día, lactancia materna exclusiva.".to_string(),
+            edited,
+            String::new(),
+            "Fractura de radio distal tras caída de propia altura; se inmoviliza con yeso.".to_string(),
+            "Control de niño sano de 4 meses; vacunas al día y lactancia materna exclusiva!".to_string(),
+            String::new(),
+        ];
+        assert_eq!(find_near_duplicates_internal(&texts, 0.8, 128), vec![vec![0, 2], vec![1, 5]]);
+        assert!(find_near_duplicates_internal(&texts[..2], 0.8, 128).is_empty());
+
+        // A large cluster of copies is joined through its representative
+        let copies: Vec<String> = (0..5000).map(|i| if i % 2 == 0 { NOTE.to_string() } else { texts[1].clone() }).collect();
+        let groups = find_near_duplicates_internal(&copies, 0.8, 128);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0], (0..5000).step_by(2).collect::<Vec<_>>());
+        assert_eq!(groups[1].len(), 2500);
+
+        crate::with_py(|py| {
+            assert!(find_near_duplicates(py, texts.clone(), 0.0, 128).is_err());
+            assert!(minhash_signatures(py, texts.clone(), 0).is_err());
+            let signatures = minhash_signatures(py, texts, 64).unwrap();
+            assert_eq!(signatures.len(), 7);
+            assert!(signatures.iter().all(|signature| signature.len() == 64));
+        });
+    }
+}
@@ -0,0 +1,366 @@
+//! Detection and redaction of patient identifiers
+//!
+//! Notes are redacted before leaving the hospital network (external LLM
+//! calls, analytics exports). Detection is pattern-based and tuned for
+//! recall on Spanish and Argentine records: emails, phone numbers, national
+//! ID documents (Spanish DNI/NIE, Argentine DNI) and medical record numbers.
+//! Clinical values such as "150.000 plaquetas", "2019-2020" or "TA 120/80"
+//! must not be mistaken for identifiers, so phones need at least nine
+//! digits in the usual groupings and a bare Argentine DNI needs a label
+//! ("DNI", "documento") or the printed "12.345.678" form with no count unit
+//! after it.
+
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use regex::Regex;
+
+use crate::{CharCursor, Spans};
+
+/// Categories reported by find_pii, in priority order
+const CATEGORIES: &[&str] = &["email", "medical_record", "id", "phone"];
+
+/// Words after a dotted number that make it a count rather than a DNI
+const COUNT_UNITS: &[&str] = &[
+    "ui", "u", "mm3", "mm³", "/mm3", "/mm³", "/ul", "/µl", "/μl", "ufc", "copias", "cel", "células",
+    "plaquetas", "leucocitos", "pesos", "ars",
+];
+
+pub(crate) static EMAIL_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\b[\w.+-]+@[\w-]+(?:\.[\w-]+)*\.[A-Za-z]{2,}\b").expect("Invalid email regex - this is a bug")
+});
+
+static PHONE_RE: Lazy<Regex> = Lazy::new(|| {
+    // The leading char is matched rather than asserted (no look-behind), so
+    // the number itself is the "phone" group
+    Regex::new(
+        r"(?x)
+        (?:^|[^\w+])
+        (?P<phone>
+            \+54[\ -]?9?[\ -]?\(?\d{2,4}\)?[\ -]?\d{3,4}[\ -]?\d{4}   # +54 9 11 4567-8901
+          | \(?0\d{2,4}\)?[\ -]?15[\ -]?\d{4}[\ -]?\d{4}             # 011 15-4567-8901
+          | (?:\+\d{1,3}[\ .-]?)?
+            (?:
+                \(\d{2,4}\)[\ -]?\d{3,4}[\ -]?\d{4}          # (011) 4567-8901
+              | \d{3,4}[\ -]?\d{3}[\ -]?\d{3,4}              # 612 345 678, 011 4567 8901
+              | \d{2}[\ -]\d{4}[\ -]?\d{4}                   # 11 4567-8901, 15-4567-8901
+              | \d{2}[\ .-]?\d{3}[\ .-]?\d{2}[\ .-]?\d{2}    # 91 123 45 67
+            )
+        )\b",
+    )
+    .expect("Invalid phone regex - this is a bug")
+});
+
+static ID_RE: Lazy<Regex> = Lazy::new(|| {
+    // Control letters never use I, Ñ, O or U
+    Regex::new(
+        r"(?x)
+        \b(?:
+            \d{8} [\ -]? [A-HJ-NP-TV-Z]                      # DNI 12345678Z
+          | \d{1,2}\.\d{3}\.\d{3} [\ -]? [A-HJ-NP-TV-Z]     # DNI 12.345.678-Z
+          | [XYZ] [\ -]? \d{7} [\ -]? [A-HJ-NP-TV-Z]         # NIE X1234567L
+        )\b",
+    )
+    .expect("Invalid national ID regex - this is a bug")
+});
+
+static ARGENTINE_DNI_RE: Lazy<Regex> = Lazy::new(|| {
+    // Only the number is redacted; the label is kept for readability
+    Regex::new(
+        r"(?xi)
+        (?P<label> \b(?:DNI|D\.N\.I\.?|documento|doc\.) \s* (?:n[°º]\.?|nro\.?|n[uú]mero|\#)? \s* :? \s* )?
+        \b(?P<number> \d{1,2}\.?\d{3}\.?\d{3} )\b",
+    )
+    .expect("Invalid DNI regex - this is a bug")
+});
+
+static MEDICAL_RECORD_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?xi)
+        \b(?:HC|H\.\ ?C\.|historia\s+cl[ií]nica) \s* (?:n[°º]\.?|nro\.?|n[uú]mero|\#)? \s* :? \s*
+        (?P<number> \d{4,10} )\b",
+    )
+    .expect("Invalid medical record regex - this is a bug")
+});
+
+/// (category, original, start_char, end_char) of each replacement
+pub(crate) type Audit = Vec<(String, String, usize, usize)>;
+
+/// One detected identifier
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct PiiMatch {
+    pub category: &'static str,
+    /// Byte offsets into the scanned text
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Replace identifiers with a placeholder and report what was replaced
+///
+/// Categories:
+/// - email: email addresses
+/// - phone: Spanish and Argentine numbers ("+54 9 11 4567-8901",
+///   "(011) 4567-8901", "011 15-4567-8901", "612 345 678")
+/// - id: Spanish DNI/NIE ("12345678Z", "X1234567L") and Argentine DNI. A
+///   bare 7-8 digit number counts after a label ("DNI 30123456",
+///   "documento N° 7.654.321") or in dotted form ("30.123.456") unless a
+///   count unit follows ("12.500.000 UI", "15.000.000 copias")
+/// - medical_record: "HC 123456", "H.C. N° 123456", "historia clínica: 1234"
+///
+/// For labelled identifiers only the number is replaced ("DNI [REDACTED]").
+///
+/// Args:
+///     text: The input text
+///     placeholder: Replacement text; "{category}" in it becomes the
+///                  uppercase category, so "[{category}]" gives "[EMAIL]",
+///                  "[PHONE]", ... (default: "[REDACTED]")
+///     categories: Categories to redact (default: all)
+///
+/// Returns:
+///     (redacted_text, [(category, original, start_char, end_char), ...]),
+///     with offsets into the original text
+///
+/// Raises:
+///     ValueError: If a category is unknown
+#[pyfunction]
+#[pyo3(signature = (text, placeholder="[REDACTED]", categories=None))]
+pub fn redact_pii(
+    py: Python<'_>,
+    text: &str,
+    placeholder: &str,
+    categories: Option<Vec<String>>,
+) -> PyResult<(String, Audit)> {
+    let enabled = enabled_categories(categories)?;
+    Ok(crate::allow_threads_if_large(py, text.len(), || redact_pii_internal(text, placeholder, &enabled)))
+}
+
+fn enabled_categories(categories: Option<Vec<String>>) -> PyResult<Vec<&'static str>> {
+    let Some(requested) = categories else {
+        return Ok(CATEGORIES.to_vec());
+    };
+    requested
+        .iter()
+        .map(|name| {
+            CATEGORIES.iter().copied().find(|category| category == name).ok_or_else(|| {
+                PyValueError::new_err(format!("Unknown PII category: {:?} (expected one of {:?})", name, CATEGORIES))
+            })
+        })
+        .collect()
+}
+
+pub(crate) fn redact_pii_internal(text: &str, placeholder: &str, enabled: &[&str]) -> (String, Audit) {
+    let mut redacted = String::with_capacity(text.len());
+    let mut audit = Vec::new();
+    let mut cursor = CharCursor::new(text);
+    let mut last = 0;
+    // Disabled categories still claim their span during overlap resolution,
+    // so the digits of a kept email are not redacted as a phone
+    for found in pii_matches(text).into_iter().filter(|m| enabled.contains(&m.category)) {
+        redacted.push_str(&text[last..found.start]);
+        redacted.push_str(&placeholder.replace("{category}", &found.category.to_uppercase()));
+        audit.push((
+            found.category.to_string(),
+            text[found.start..found.end].to_string(),
+            cursor.char_offset(found.start),
+            cursor.char_offset(found.end),
+        ));
+        last = found.end;
+    }
+    redacted.push_str(&text[last..]);
+    (redacted, audit)
+}
+
+/// Find identifiers without modifying the text
+///
+/// Reports exactly what redact_pii would replace, for auditing.
+///
+/// Args:
+///     text: The input text
+///
+/// Returns:
+///     Dict with keys "email", "medical_record", "id" and "phone", each a
+///     list of (match, start_char, end_char); `text[start:end]` gives the match
+#[pyfunction]
+pub fn find_pii(py: Python<'_>, text: &str) -> PyResult<HashMap<String, Spans>> {
+    Ok(crate::allow_threads_if_large(py, text.len(), || find_pii_internal(text)))
+}
+
+pub(crate) fn find_pii_internal(text: &str) -> HashMap<String, Spans> {
+    let mut found: HashMap<String, Spans> =
+        CATEGORIES.iter().map(|category| (category.to_string(), Vec::new())).collect();
+    let mut cursor = CharCursor::new(text);
+    for m in pii_matches(text) {
+        let span = (text[m.start..m.end].to_string(), cursor.char_offset(m.start), cursor.char_offset(m.end));
+        found.entry(m.category.to_string()).or_default().push(span);
+    }
+    found
+}
+
+/// Non-overlapping identifiers in text order
+///
+/// When two categories overlap, the one listed first in CATEGORIES wins (an
+/// email's digits are not also a phone, and an ID's digits are not a phone).
+pub(crate) fn pii_matches(text: &str) -> Vec<PiiMatch> {
+    let mut found: Vec<(usize, PiiMatch)> = Vec::new();
+    let mut push = |category: &'static str, m: regex::Match<'_>| {
+        let priority = CATEGORIES.iter().position(|c| *c == category).unwrap_or(CATEGORIES.len());
+        found.push((priority, PiiMatch { category, start: m.start(), end: m.end() }));
+    };
+    for m in EMAIL_RE.find_iter(text) {
+        push("email", m);
+    }
+    for number in MEDICAL_RECORD_RE.captures_iter(text).filter_map(|caps| caps.name("number")) {
+        push("medical_record", number);
+    }
+    for m in ID_RE.find_iter(text) {
+        push("id", m);
+    }
+    for caps in ARGENTINE_DNI_RE.captures_iter(text) {
+        let Some(number) = caps.name("number") else { continue };
+        if caps.name("label").is_some() || is_unlabelled_dni(text, number) {
+            push("id", number);
+        }
+    }
+    for phone in PHONE_RE.captures_iter(text).filter_map(|caps| caps.name("phone")) {
+        push("phone", phone);
+    }
+
+    // Stable sort: at the same position a Spanish ID ("12.345.678-Z") beats
+    // the Argentine DNI inside it
+    found.sort_by_key(|&(priority, m)| (priority, m.start));
+    let mut kept: Vec<PiiMatch> = Vec::with_capacity(found.len());
+    for (_, candidate) in found {
+        if kept.iter().all(|k| candidate.end <= k.start || candidate.start >= k.end) {
+            kept.push(candidate);
+        }
+    }
+    kept.sort_by_key(|m| m.start);
+    kept
+}
+
+/// Whether an unlabelled number is a printed DNI rather than a count
+///
+/// Only the "12.345.678" form qualifies, and not after a currency sign or
+/// before a unit such as "UI", "/mm3" or "copias".
+fn is_unlabelled_dni(text: &str, number: regex::Match<'_>) -> bool {
+    let digits = number.as_str().as_bytes();
+    let dotted = digits.len() == 10 && digits[2] == b'.' && digits[6] == b'.';
+    let after_currency = text[..number.start()].trim_end().ends_with('$');
+    let next_word = text[number.end()..]
+        .split_whitespace()
+        .next()
+        .map(|word| word.trim_end_matches(|c: char| c.is_ascii_punctuation()).to_lowercase())
+        .unwrap_or_default();
+    dotted && !after_currency && !COUNT_UNITS.contains(&next_word.as_str())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redact(text: &str) -> String {
+        redact_pii_internal(text, "[{category}]", CATEGORIES).0
+    }
+
+    #[test]
+    fn test_each_category_is_masked() {
+        let text = "Paciente Juan, DNI 12345678Z, NIE X-1234567-L, tel. +34 612 345 678 o (011) 4567-8901, \
+                    mail juan.perez+hc@hospital.com.ar. TA 120/80, plaquetas 150.000, control 2019-2020.";
+        assert_eq!(
+            redact(text),
+            "Paciente Juan, DNI [ID], NIE [ID], tel. [PHONE] o [PHONE], \
+             mail [EMAIL]. TA 120/80, plaquetas 150.000, control 2019-2020."
+        );
+    }
+
+    #[test]
+    fn test_clinical_numbers_are_untouched() {
+        let text = "Leucocitos 12.500/mm3, Hb 12,5 g/dL, 15/01/2026 14:30 hs, FC 80 lpm, \
+                    glucemia 110 mg/dL, lote 2024-11, recibió 500 mg y 1.200 ml";
+        assert_eq!(redact(text), text);
+    }
+
+    #[test]
+    fn test_find_pii_offsets_slice_back() {
+        let text = "Niño Iñaki Muñoz — DNI 12.345.678-Z, tel 612 345 678, ñandú@clínica.es; tel 91 123 45 67";
+        let found = find_pii_internal(text);
+        let chars: Vec<char> = text.chars().collect();
+        for matches in found.values() {
+            for (raw, start, end) in matches {
+                assert_eq!(&chars[*start..*end].iter().collect::<String>(), raw);
+            }
+        }
+        let raws = |category: &str| found[category].iter().map(|(raw, _, _)| raw.as_str()).collect::<Vec<_>>();
+        assert_eq!(raws("id"), vec!["12.345.678-Z"]);
+        assert_eq!(raws("phone"), vec!["612 345 678", "91 123 45 67"]);
+        assert_eq!(raws("email"), vec!["ñandú@clínica.es"]);
+        assert!(find_pii_internal("Sin datos")["email"].is_empty());
+    }
+
+    #[test]
+    fn test_formats_and_overlaps() {
+        let cases = [
+            ("Llamar al 91 123 45 67", "Llamar al [PHONE]"),
+            ("Cel: 11 4567-8901.", "Cel: [PHONE]."),
+            ("Tel 612345678", "Tel [PHONE]"),
+            ("DNI 12.345.678-Z", "DNI [ID]"),
+            ("contacto: 612345678@correo.es", "contacto: [EMAIL]"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(redact(input), expected, "input: {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_argentine_identifiers_with_audit() {
+        let text = "Sra. Pérez, DNI 30.123.456, HC N° 884512. Tel +54 9 11 4567-8901 o 011 15-4567-8901. \
+                    Hijo: documento: 7654321 (ref. 40.111.222).";
+        let (redacted, audit) = redact_pii_internal(text, "[REDACTED]", CATEGORIES);
+        assert_eq!(
+            redacted,
+            "Sra. Pérez, DNI [REDACTED], HC N° [REDACTED]. Tel [REDACTED] o [REDACTED]. \
+             Hijo: documento: [REDACTED] (ref. [REDACTED])."
+        );
+        let summary: Vec<(&str, &str)> = audit.iter().map(|(c, o, _, _)| (c.as_str(), o.as_str())).collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("id", "30.123.456"),
+                ("medical_record", "884512"),
+                ("phone", "+54 9 11 4567-8901"),
+                ("phone", "011 15-4567-8901"),
+                ("id", "7654321"),
+                ("id", "40.111.222"),
+            ]
+        );
+        let chars: Vec<char> = text.chars().collect();
+        for (_, original, start, end) in &audit {
+            assert_eq!(&chars[*start..*end].iter().collect::<String>(), original);
+        }
+    }
+
+    #[test]
+    fn test_large_counts_are_not_dni() {
+        let text = "Penicilina G benzatínica 2.400.000 UI. Recuento 12.500.000/mm3, carga viral \
+                    15.000.000 copias, costo $ 12.345.678, 30123456 ufc";
+        assert_eq!(redact(text), text);
+    }
+
+    #[test]
+    fn test_categories_are_toggleable() {
+        let text = "DNI 30.123.456, HC 884512, mail a@b.com, tel 11 4567-8901";
+        let (only_ids, audit) = redact_pii_internal(text, "***", &["id", "medical_record"]);
+        assert_eq!(only_ids, "DNI ***, HC ***, mail a@b.com, tel 11 4567-8901");
+        assert_eq!(audit.len(), 2);
+
+        crate::with_py(|py| {
+            let err = redact_pii(py, text, "***", Some(vec!["passport".to_string()])).unwrap_err();
+            assert!(err.is_instance_of::<PyValueError>(py));
+            let (all, _) = redact_pii(py, text, "[{category}]", None).unwrap();
+            assert_eq!(all, "DNI [ID], HC [MEDICAL_RECORD], mail [EMAIL], tel [PHONE]");
+        });
+    }
+}
@@ -0,0 +1,395 @@
+//! Semantic validation and normalization of regex entity matches
+//!
+//! The entity regexes are deliberately loose about digits ("45/23/0000"
+//! matches DATE_RE, "72:95" matches TIME_RE); candidates are checked here
+//! before being reported, and times can be rewritten to one format.
+
+use std::ops::RangeInclusive;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::icd10;
+use crate::measurements::NUMBER;
+
+/// Spanish month names and abbreviations as accepted by DATE_RE
+const MONTHS: &[(&str, u32)] = &[
+    ("enero", 1), ("ene", 1), ("febrero", 2), ("feb", 2), ("marzo", 3), ("mar", 3),
+    ("abril", 4), ("abr", 4), ("mayo", 5), ("may", 5), ("junio", 6), ("jun", 6),
+    ("julio", 7), ("jul", 7), ("agosto", 8), ("ago", 8), ("septiembre", 9), ("setiembre", 9),
+    ("sept", 9), ("sep", 9), ("set", 9), ("octubre", 10), ("oct", 10), ("noviembre", 11),
+    ("nov", 11), ("diciembre", 12), ("dic", 12),
+];
+
+/// Words that introduce a ratio written like a time ("dilución 1:10")
+const RATIO_WORDS: &[&str] = &["dilución", "dilucion", "relación", "relacion", "proporción", "proporcion", "razón", "razon", "ratio"];
+
+/// The leading number or range of a measurement; the unit is what follows
+static MEASUREMENT_NUMBER_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(&format!(r"^(?:{num})(?:\s*[-–]\s*(?:{num})|\s+a\s+(?:{num}))?", num = NUMBER))
+        .expect("Invalid measurement number regex - this is a bug")
+});
+
+/// Knobs for entity matching, validation and normalization
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct EntityOptions {
+    /// Accepted four-digit years
+    pub years: RangeInclusive<i32>,
+    /// Report times as HH:MM[:SS] instead of the matched text
+    pub normalize_times: bool,
+    /// Report dates with a four-digit year as YYYY-MM-DD
+    pub normalize_dates: bool,
+    /// Measurement units recognized on top of the default list
+    pub extra_units: Vec<String>,
+}
+
+impl Default for EntityOptions {
+    fn default() -> Self {
+        Self { years: 1900..=2100, normalize_times: false, normalize_dates: false, extra_units: Vec::new() }
+    }
+}
+
+/// Whether a match of `category`'s regex is a real entity
+pub(crate) fn is_valid(category: &str, candidate: &str, options: &EntityOptions) -> bool {
+    match category {
+        "dates" => parse_date(candidate).is_some_and(|date| is_valid_date(date, &options.years)),
+        "times" => parse_time(candidate).is_some(),
+        "icd10" => icd10::is_valid_code(candidate),
+        _ => true,
+    }
+}
+
+/// Whether the text right before a time candidate makes it a ratio
+///
+/// "dilución 1:10" and "relación 1:30" have the shape of a time; the word
+/// before the match ("dilución", "relación", ...) tells them apart.
+pub(crate) fn follows_ratio_word(before: &str) -> bool {
+    before
+        .split(|c: char| !c.is_alphanumeric())
+        .rfind(|word| !word.is_empty())
+        .is_some_and(|word| RATIO_WORDS.contains(&word.to_lowercase().as_str()))
+}
+
+/// Whether the text right after a ratio candidate makes it part of a date
+///
+/// "INR 01/02" is a keyword followed by a date, not the ratio 01.
+pub(crate) fn continues_as_date(after: &str) -> bool {
+    let mut chars = after.chars();
+    matches!(chars.next(), Some('/' | '-')) && chars.next().is_some_and(|c| c.is_ascii_digit())
+}
+
+/// Text to report for a valid match of `category`'s regex
+pub(crate) fn normalize(category: &str, candidate: &str, options: &EntityOptions) -> String {
+    match category {
+        "times" if options.normalize_times => {
+            parse_time(candidate).map_or_else(|| candidate.to_string(), |time| time.to_string())
+        }
+        "dates" if options.normalize_dates => {
+            parse_date(candidate).and_then(DateParts::iso).unwrap_or_else(|| candidate.to_string())
+        }
+        _ => candidate.to_string(),
+    }
+}
+
+/// One spelling for every way of writing the same entity
+///
+/// Used to count entities: "500 mg", "500mg" and "500 MG" are all
+/// "500 mg"; "15/01/2026" and "15 de Enero de 2026" are "2026-01-15"
+/// (dates without a four-digit year become "DD/MM/YY" or "DD/MM"); times
+/// are 24-hour HH:MM[:SS]. Anything else has its whitespace collapsed and
+/// is lowercased, except ICD-10 codes, which are uppercased.
+pub(crate) fn canonical(category: &str, candidate: &str) -> String {
+    let collapsed = candidate.split_whitespace().collect::<Vec<_>>().join(" ");
+    match category {
+        "dates" => match parse_date(candidate) {
+            Some(date @ DateParts { year: Some(Year::Full(_)), .. }) => date.iso().unwrap_or(collapsed),
+            Some(DateParts { day, month, year: Some(Year::TwoDigit(year)) }) => format!("{:02}/{:02}/{:02}", day, month, year),
+            Some(DateParts { day, month, year: None }) => format!("{:02}/{:02}", day, month),
+            None => collapsed,
+        },
+        "times" => parse_time(candidate).map_or(collapsed, |time| time.to_string()),
+        "measurements" | "percentages" => {
+            // Units may hold digits ("ml/24h", "kg/m2"): split after the leading number
+            let unit_start = MEASUREMENT_NUMBER_RE.find(&collapsed).map_or(0, |number| number.end());
+            let (number, unit) = collapsed.split_at(unit_start);
+            let number = number.to_lowercase();
+            match unit.trim() {
+                "" => number,
+                unit if unit.eq_ignore_ascii_case("por ciento") => format!("{} %", number),
+                unit => format!("{} {}", number, crate::measurements::normalize_unit(unit)),
+            }
+        }
+        "icd10" => collapsed.to_uppercase(),
+        _ => collapsed.to_lowercase(),
+    }
+}
+
+/// Day, month and (when written) year of a date candidate
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct DateParts {
+    pub day: u32,
+    pub month: u32,
+    pub year: Option<Year>,
+}
+
+impl DateParts {
+    /// "YYYY-MM-DD", when the year is written with four digits
+    ///
+    /// Two-digit and missing years have no known century, so they have no
+    /// ISO form.
+    pub(crate) fn iso(self) -> Option<String> {
+        match self.year {
+            Some(Year::Full(year)) => Some(format!("{:04}-{:02}-{:02}", year, self.month, self.day)),
+            Some(Year::TwoDigit(_)) | None => None,
+        }
+    }
+}
+
+/// A written year: four digits are exact, two digits have no known century
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Year {
+    Full(i32),
+    TwoDigit(u32),
+}
+
+/// Split "15/01/2026", "15-1-26" or "15 de enero de 2026" into parts
+pub(crate) fn parse_date(candidate: &str) -> Option<DateParts> {
+    let numeric: Vec<&str> = candidate.split(['/', '-']).collect();
+    if let [day, month, year] = numeric[..] {
+        return Some(DateParts { day: day.parse().ok()?, month: month.parse().ok()?, year: Some(parse_year(year)?) });
+    }
+
+    let day_end = candidate.find(|c: char| !c.is_ascii_digit())?;
+    let day = candidate[..day_end].parse().ok()?;
+    let mut words = candidate[day_end..].split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty());
+    let month = words.find_map(|word| {
+        let word = word.to_lowercase();
+        MONTHS.iter().find(|(name, _)| *name == word).map(|&(_, month)| month)
+    })?;
+    let year = match words.next_back() {
+        Some(last) if last.len() == 4 && last.bytes().all(|b| b.is_ascii_digit()) => Some(parse_year(last)?),
+        _ => None,
+    };
+    Some(DateParts { day, month, year })
+}
+
+fn parse_year(year: &str) -> Option<Year> {
+    match year.len() {
+        2 => year.parse().ok().map(Year::TwoDigit),
+        4 => year.parse().ok().map(Year::Full),
+        _ => None,
+    }
+}
+
+/// Calendar check of a parsed date
+///
+/// Days must exist in the month. February 29 needs a leap year when a
+/// four-digit year is written and is always accepted otherwise, since the
+/// century of "29/02/23" (or a missing year) is unknown. Four-digit years
+/// must fall in `years`; two-digit years are not range-checked.
+pub(crate) fn is_valid_date(date: DateParts, years: &RangeInclusive<i32>) -> bool {
+    let leap = match date.year {
+        Some(Year::Full(year)) => {
+            if !years.contains(&year) {
+                return false;
+            }
+            (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+        }
+        Some(Year::TwoDigit(_)) | None => true,
+    };
+    let days_in_month = match date.month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return false,
+    };
+    (1..=days_in_month).contains(&date.day)
+}
+
+/// Hour, minute and optional second of a valid 24-hour time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct TimeParts {
+    pub hour: u32,
+    pub minute: u32,
+    pub second: Option<u32>,
+}
+
+impl std::fmt::Display for TimeParts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:02}:{:02}", self.hour, self.minute)?;
+        match self.second {
+            Some(second) => write!(f, ":{:02}", second),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Parse "14:30", "14:30:15", "14:30 hs", "14.30 hs" or "2:30 p.m."
+///
+/// Hours must be 0-23 (1-12 with am/pm) and minutes and seconds 0-59.
+pub(crate) fn parse_time(candidate: &str) -> Option<TimeParts> {
+    let lower = candidate.to_lowercase();
+    let digits_end = lower.find(|c: char| !(c.is_ascii_digit() || c == ':' || c == '.')).unwrap_or(lower.len());
+    let (clock, suffix) = lower.split_at(digits_end);
+    let clock = clock.trim_end_matches('.');
+    let suffix: String = suffix.chars().filter(char::is_ascii_alphabetic).collect();
+
+    let mut parts = clock.split([':', '.']).map(|part| part.parse::<u32>().ok());
+    let hour = parts.next()??;
+    let minute = parts.next()??;
+    let second = match parts.next() {
+        Some(second) => Some(second?),
+        None => None,
+    };
+    if parts.next().is_some() || minute > 59 || second.is_some_and(|s| s > 59) {
+        return None;
+    }
+
+    let hour = match suffix.as_str() {
+        "am" | "pm" if !(1..=12).contains(&hour) => return None,
+        "am" => hour % 12,
+        "pm" => hour % 12 + 12,
+        _ if hour > 23 => return None,
+        _ => hour,
+    };
+    Some(TimeParts { hour, minute, second })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonical_forms() {
+        assert_eq!(canonical("measurements", "500mg"), "500 mg");
+        assert_eq!(canonical("measurements", "500  MG"), "500 mg");
+        assert_eq!(canonical("measurements", "10-20 mcg"), "10-20 µg");
+        assert_eq!(canonical("measurements", "1500 ml/24h"), "1500 ml/24h");
+        assert_eq!(canonical("measurements", "1500ML/24H"), "1500 ml/24h");
+        assert_eq!(canonical("measurements", "31kg/m2"), "31 kg/m2");
+        assert_eq!(canonical("measurements", "31 kg/m2"), "31 kg/m2");
+        assert_eq!(canonical("measurements", "80 MG/KG/día"), "80 mg/kg/día");
+        assert_eq!(canonical("measurements", "500 a 1000 mg"), "500 a 1000 mg");
+        assert_eq!(canonical("measurements", "1.200 CC"), "1.200 ml");
+        assert_eq!(canonical("percentages", "30 por ciento"), "30 %");
+        assert_eq!(canonical("percentages", "30%"), "30 %");
+        assert_eq!(canonical("dates", "15 de Enero de 2026"), "2026-01-15");
+        assert_eq!(canonical("dates", "15-1-26"), "15/01/26");
+        assert_eq!(canonical("dates", "3 MAR"), "03/03");
+        assert_eq!(canonical("times", "2:30 p.m."), "14:30");
+        assert_eq!(canonical("icd10", "j45.909"), "J45.909");
+    }
+
+    fn valid(candidate: &str) -> bool {
+        is_valid("dates", candidate, &EntityOptions::default())
+    }
+
+    #[test]
+    fn test_day_and_month_boundaries() {
+        for ok in ["1/1/2025", "31/12/2025", "31/01/2025", "30/04/2025", "28/02/2025", "01-06-25"] {
+            assert!(valid(ok), "{} should be valid", ok);
+        }
+        for bad in ["0/1/2025", "32/1/2025", "1/0/2025", "1/13/2025", "31/04/2025", "30/02/2024", "45/23/0000", "99/99/9999"] {
+            assert!(!valid(bad), "{} should be invalid", bad);
+        }
+    }
+
+    #[test]
+    fn test_february_29() {
+        assert!(valid("29/02/2024"));
+        assert!(valid("29/02/2000"));
+        assert!(!valid("29/02/2025"));
+        assert!(!valid("29/02/1900"));
+        // Unknown century or no year at all: accepted
+        assert!(valid("29/02/23"));
+        assert!(valid("29 de febrero"));
+    }
+
+    #[test]
+    fn test_year_range() {
+        assert!(valid("1/1/1900"));
+        assert!(valid("31/12/2100"));
+        assert!(!valid("31/12/1899"));
+        assert!(!valid("1/1/2101"));
+        assert!(!valid("1/1/025"));
+        let options = EntityOptions { years: 2000..=2030, ..EntityOptions::default() };
+        assert!(!is_valid("dates", "1/1/1999", &options));
+        assert!(is_valid("dates", "1/1/2030", &options));
+    }
+
+    #[test]
+    fn test_textual_dates() {
+        assert_eq!(
+            parse_date("3 de marzo del 2025"),
+            Some(DateParts { day: 3, month: 3, year: Some(Year::Full(2025)) })
+        );
+        assert_eq!(parse_date("1° de Mayo"), Some(DateParts { day: 1, month: 5, year: None }));
+        assert_eq!(parse_date("20 DIC. 2024"), Some(DateParts { day: 20, month: 12, year: Some(Year::Full(2024)) }));
+        assert!(valid("15 de enero de 2026"));
+        assert!(!valid("31 de junio de 2025"));
+        assert!(!valid("15 de enero de 1850"));
+    }
+
+    #[test]
+    fn test_dates_normalize_to_iso() {
+        let options = EntityOptions { normalize_dates: true, ..EntityOptions::default() };
+        let cases = [
+            ("15/01/2026", "2026-01-15"),
+            ("1-6-2025", "2025-06-01"),
+            ("15 de enero de 2026", "2026-01-15"),
+            ("3 de Marzo del 2025", "2025-03-03"),
+            ("20 DIC. 2024", "2024-12-20"),
+            // No century or no year: kept as written
+            ("01-06-25", "01-06-25"),
+            ("1° de Mayo", "1° de Mayo"),
+        ];
+        for (raw, expected) in cases {
+            assert_eq!(normalize("dates", raw, &options), expected, "raw: {:?}", raw);
+        }
+        assert_eq!(normalize("dates", "15/01/2026", &EntityOptions::default()), "15/01/2026");
+    }
+
+    #[test]
+    fn test_time_boundaries() {
+        for ok in ["0:00", "00:00", "23:59", "23:59:59", "9:05"] {
+            assert!(is_valid("times", ok, &EntityOptions::default()), "{} should be valid", ok);
+        }
+        for bad in ["24:00", "72:95", "12:60", "12:30:60", "99:99"] {
+            assert!(!is_valid("times", bad, &EntityOptions::default()), "{} should be invalid", bad);
+        }
+    }
+
+    #[test]
+    fn test_ratio_context() {
+        assert!(follows_ratio_word("Heparina en dilución "));
+        assert!(follows_ratio_word("Relación: "));
+        assert!(follows_ratio_word("FiO2, razón "));
+        assert!(!follows_ratio_word("ingresa a las "));
+        assert!(!follows_ratio_word(""));
+    }
+
+    #[test]
+    fn test_time_formats_normalize() {
+        let options = EntityOptions { normalize_times: true, ..EntityOptions::default() };
+        let cases = [
+            ("14:30", "14:30"),
+            ("9:05:07", "09:05:07"),
+            ("14:30 hs", "14:30"),
+            ("8.15hs", "08:15"),
+            ("14.30 h", "14:30"),
+            ("2:30 pm", "14:30"),
+            ("2:30 p.m.", "14:30"),
+            ("12:10 am", "00:10"),
+            ("12:10 PM", "12:10"),
+            ("11.45 a. m.", "11:45"),
+        ];
+        for (raw, expected) in cases {
+            assert_eq!(normalize("times", raw, &options), expected, "raw: {:?}", raw);
+        }
+        assert_eq!(normalize("times", "14:30 hs", &EntityOptions::default()), "14:30 hs");
+        assert!(parse_time("13:00 pm").is_none());
+        assert!(parse_time("0:15 am").is_none());
+    }
+}
@@ -0,0 +1,320 @@
+//! Configured clean → chunk → count pipeline
+//!
+//! Each ingestion pipeline runs the same steps with its own settings.
+//! FerroProcessor validates those settings once, at construction, and then
+//! runs every text through them; it pickles by its constructor arguments,
+//! so it can be sent to multiprocessing workers.
+
+use std::collections::HashMap;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyTuple};
+use rayon::prelude::*;
+use regex::Regex;
+
+use crate::postprocess::EntityOptions;
+use crate::{ChunkUnit, CleanOptions};
+
+/// A chunk produced by FerroProcessor.process
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ProcessedChunk {
+    pub text: String,
+    pub token_count: usize,
+    pub entities: HashMap<String, Vec<String>>,
+}
+
+/// Text pipeline with fixed settings
+///
+/// Args:
+///     chunk_size: Maximum characters (or tokens) per chunk (default: 1000)
+///     overlap: Characters (or tokens) to overlap (default: 200)
+///     unit: "chars" or "tokens", as in chunk_text (default: "chars")
+///     chars_per_token: Average characters per token for token_count
+///                      (default: 4)
+///     clean: Run clean_medical_text before chunking (default: True)
+///     preserve_newlines, normalize_unicode, strip_accents, keep_enie,
+///     lowercase, remove_urls, remove_emails: Same as in
+///         clean_medical_text
+///     extra_units: Measurement units recognized on top of the default list
+///     patterns: Dict of category name -> regex, as in extract_custom
+///
+/// Raises:
+///     ValueError: If chunk_size is 0, overlap >= chunk_size, unit is
+///                 unknown, chars_per_token is not positive, a pattern is
+///                 invalid or a pattern name is a built-in entity category
+#[pyclass(module = "ferro_engine")]
+pub struct FerroProcessor {
+    chunk_size: usize,
+    overlap: usize,
+    unit: ChunkUnit,
+    chars_per_token: f64,
+    clean: bool,
+    clean_options: CleanOptions,
+    entity_options: EntityOptions,
+    /// Patterns as given, kept for pickling
+    patterns: HashMap<String, String>,
+    compiled: Vec<(String, Regex)>,
+}
+
+#[pymethods]
+impl FerroProcessor {
+    #[new]
+    #[pyo3(signature = (
+        chunk_size=1000, overlap=200, unit="chars", chars_per_token=4.0, clean=true,
+        preserve_newlines=false, normalize_unicode=false, strip_accents=false, keep_enie=true,
+        lowercase=false, remove_urls=false, remove_emails=false, extra_units=None, patterns=None
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn py_new(
+        chunk_size: usize,
+        overlap: usize,
+        unit: &str,
+        chars_per_token: f64,
+        clean: bool,
+        preserve_newlines: bool,
+        normalize_unicode: bool,
+        strip_accents: bool,
+        keep_enie: bool,
+        lowercase: bool,
+        remove_urls: bool,
+        remove_emails: bool,
+        extra_units: Option<Vec<String>>,
+        patterns: Option<HashMap<String, String>>,
+    ) -> PyResult<Self> {
+        let clean_options = CleanOptions {
+            preserve_newlines,
+            normalize_unicode,
+            strip_accents,
+            keep_enie,
+            lowercase,
+            remove_urls,
+            remove_emails,
+        };
+        Self::new(
+            chunk_size,
+            overlap,
+            ChunkUnit::parse(unit)?,
+            chars_per_token,
+            clean.then_some(clean_options),
+            extra_units.unwrap_or_default(),
+            patterns.unwrap_or_default(),
+        )
+    }
+
+    /// Clean, chunk and count one text
+    ///
+    /// Args:
+    ///     text: The input text
+    ///
+    /// Returns:
+    ///     List of dicts, one per chunk, with "index", "text",
+    ///     "token_count" (as in count_tokens) and "entities" (the
+    ///     extract_entities categories plus one per custom pattern)
+    fn process<'py>(&self, py: Python<'py>, text: &str) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        let chunks = crate::allow_threads_if_large(py, text.len(), || self.process_internal(text));
+        chunks_to_dicts(py, &chunks)
+    }
+
+    /// process over many texts, in parallel without the GIL
+    ///
+    /// Args:
+    ///     texts: List of texts
+    ///
+    /// Returns:
+    ///     One process result per text, in input order
+    fn process_batch<'py>(&self, py: Python<'py>, texts: Vec<String>) -> PyResult<Vec<Vec<Bound<'py, PyDict>>>> {
+        let batch: Vec<Vec<ProcessedChunk>> =
+            py.allow_threads(|| texts.par_iter().map(|text| self.process_internal(text)).collect());
+        batch.iter().map(|chunks| chunks_to_dicts(py, chunks)).collect()
+    }
+
+    #[getter]
+    fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    #[getter]
+    fn overlap(&self) -> usize {
+        self.overlap
+    }
+
+    #[getter]
+    fn unit(&self) -> &'static str {
+        self.unit.name()
+    }
+
+    /// Rebuild from the constructor arguments when unpickled
+    fn __reduce__<'py>(slf: &Bound<'py, Self>) -> PyResult<(Bound<'py, PyAny>, Bound<'py, PyTuple>)> {
+        let py = slf.py();
+        let this = slf.borrow();
+        let options = this.clean_options;
+        let args: Vec<PyObject> = vec![
+            this.chunk_size.into_py(py),
+            this.overlap.into_py(py),
+            this.unit.name().into_py(py),
+            this.chars_per_token.into_py(py),
+            this.clean.into_py(py),
+            options.preserve_newlines.into_py(py),
+            options.normalize_unicode.into_py(py),
+            options.strip_accents.into_py(py),
+            options.keep_enie.into_py(py),
+            options.lowercase.into_py(py),
+            options.remove_urls.into_py(py),
+            options.remove_emails.into_py(py),
+            this.entity_options.extra_units.clone().into_py(py),
+            this.patterns.clone().into_py(py),
+        ];
+        Ok((slf.get_type().into_any(), PyTuple::new_bound(py, args)))
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "FerroProcessor(chunk_size={}, overlap={}, unit={:?}, clean={}, patterns={})",
+            self.chunk_size,
+            self.overlap,
+            self.unit.name(),
+            self.clean,
+            self.patterns.len()
+        )
+    }
+}
+
+impl FerroProcessor {
+    pub(crate) fn new(
+        chunk_size: usize,
+        overlap: usize,
+        unit: ChunkUnit,
+        chars_per_token: f64,
+        clean_options: Option<CleanOptions>,
+        extra_units: Vec<String>,
+        patterns: HashMap<String, String>,
+    ) -> PyResult<Self> {
+        crate::validate_chunk_params(chunk_size, overlap)?;
+        crate::validate_chars_per_token(chars_per_token)?;
+        let entity_options = EntityOptions { extra_units, ..EntityOptions::default() };
+        let builtin = crate::extract_entities_internal("", &entity_options);
+        if let Some(name) = patterns.keys().find(|name| builtin.contains_key(*name)) {
+            return Err(PyValueError::new_err(format!("Pattern name {:?} is a built-in entity category", name)));
+        }
+        let compiled = crate::custom::compile_patterns(&patterns)?;
+        Ok(Self {
+            chunk_size,
+            overlap,
+            unit,
+            chars_per_token,
+            clean: clean_options.is_some(),
+            clean_options: clean_options.unwrap_or_default(),
+            entity_options,
+            patterns,
+            compiled,
+        })
+    }
+
+    pub(crate) fn process_internal(&self, text: &str) -> Vec<ProcessedChunk> {
+        let cleaned = if self.clean {
+            crate::clean_medical_text_internal(text, self.clean_options)
+        } else {
+            text.to_string()
+        };
+        crate::chunk_text_internal(&cleaned, self.chunk_size, self.overlap, self.unit)
+            .into_iter()
+            .map(|chunk| {
+                let mut entities = crate::extract_entities_internal(&chunk, &self.entity_options);
+                entities.extend(crate::custom::extract_custom_internal(&chunk, &self.compiled));
+                ProcessedChunk {
+                    token_count: crate::count_tokens_internal(&chunk, self.chars_per_token),
+                    text: chunk,
+                    entities,
+                }
+            })
+            .collect()
+    }
+}
+
+fn chunks_to_dicts<'py>(py: Python<'py>, chunks: &[ProcessedChunk]) -> PyResult<Vec<Bound<'py, PyDict>>> {
+    chunks
+        .iter()
+        .enumerate()
+        .map(|(index, chunk)| {
+            let dict = PyDict::new_bound(py);
+            dict.set_item("index", index)?;
+            dict.set_item("text", &chunk.text)?;
+            dict.set_item("token_count", chunk.token_count)?;
+            dict.set_item("entities", &chunk.entities)?;
+            Ok(dict)
+        })
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use pyo3::types::IntoPyDict;
+
+    use super::*;
+
+    fn processor(chunk_size: usize, overlap: usize, patterns: &[(&str, &str)]) -> PyResult<FerroProcessor> {
+        let patterns = patterns.iter().map(|(name, pattern)| (name.to_string(), pattern.to_string())).collect();
+        FerroProcessor::new(chunk_size, overlap, ChunkUnit::Chars, 4.0, Some(CleanOptions::default()), vec![], patterns)
+    }
+
+    #[test]
+    fn test_clean_chunk_count() {
+        let text = "<p>Paciente   en cama 12-B.</p> Recibe 500 mg de amoxicilina. Control el 15/01/2026.";
+        let chunks = processor(40, 0, &[("beds", r"cama \d+-[A-Z]")]).unwrap().process_internal(text);
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|chunk| chunk.text.chars().count() <= 40 && !chunk.text.contains('<')));
+        assert_eq!(chunks[0].entities["beds"], vec!["cama 12-B"]);
+        let all = |category: &str| chunks.iter().flat_map(|c| c.entities[category].clone()).collect::<Vec<_>>();
+        assert_eq!(all("measurements"), vec!["500 mg"]);
+        assert_eq!(all("dates"), vec!["15/01/2026"]);
+        for chunk in &chunks {
+            assert_eq!(chunk.token_count, crate::count_tokens_internal(&chunk.text, 4.0));
+        }
+
+        // Without cleaning the markup stays
+        let raw = FerroProcessor::new(1000, 0, ChunkUnit::Chars, 4.0, None, vec![], HashMap::new()).unwrap();
+        assert!(raw.process_internal("<b>Alta</b>")[0].text.contains("<b>"));
+    }
+
+    #[test]
+    fn test_invalid_config_fails_at_construction() {
+        assert!(processor(0, 0, &[]).is_err());
+        assert!(processor(100, 100, &[]).is_err());
+        assert!(processor(100, 10, &[("beds", "cama (")]).is_err());
+        assert!(processor(100, 10, &[("dates", r"\d+")]).is_err());
+        assert!(FerroProcessor::new(100, 10, ChunkUnit::Chars, 0.0, None, vec![], HashMap::new()).is_err());
+        crate::with_py(|py| {
+            let class = py.get_type_bound::<FerroProcessor>();
+            assert!(class.call((), Some(&[("unit", "words")].into_py_dict_bound(py))).is_err());
+        });
+    }
+
+    #[test]
+    fn test_pickle_round_trip_and_batch() {
+        crate::with_py(|py| {
+            // pickle finds the class through its module, as after `import ferro_engine`
+            let module = PyModule::new_bound(py, "ferro_engine").unwrap();
+            module.add_class::<FerroProcessor>().unwrap();
+            py.import_bound("sys").unwrap().getattr("modules").unwrap().set_item("ferro_engine", module).unwrap();
+
+            let kwargs = [("chunk_size", 30.into_py(py)), ("overlap", 5.into_py(py)), ("lowercase", true.into_py(py))];
+            let original = py.get_type_bound::<FerroProcessor>().call((), Some(&kwargs.into_py_dict_bound(py))).unwrap();
+            let pickle = py.import_bound("pickle").unwrap();
+            let restored = pickle.call_method1("loads", (pickle.call_method1("dumps", (&original,)).unwrap(),)).unwrap();
+            assert_eq!(restored.repr().unwrap().to_string(), original.repr().unwrap().to_string());
+
+            let restored = restored.downcast::<FerroProcessor>().unwrap().borrow();
+            assert!(restored.clean_options.lowercase);
+            let texts = vec!["Paciente ESTABLE. Sin fiebre. Alta mañana.".to_string(), String::new()];
+            let batch = restored.process_batch(py, texts.clone()).unwrap();
+            assert_eq!(batch.len(), 2);
+            assert!(batch[1].is_empty());
+            let single = restored.process(py, &texts[0]).unwrap();
+            assert_eq!(batch[0].len(), single.len());
+            let text: String = single[0].get_item("text").unwrap().unwrap().extract().unwrap();
+            assert_eq!(text, text.to_lowercase());
+        });
+    }
+}
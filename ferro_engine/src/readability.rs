@@ -0,0 +1,131 @@
+//! Readability of Spanish text
+//!
+//! The Fernández-Huerta index adapts Flesch reading ease to Spanish: long
+//! sentences and long words both lower the score, roughly 0-100 with
+//! higher meaning easier. Syllables are counted with a vowel-group
+//! heuristic: a diphthong ("ue", "ai") is one syllable, two strong vowels
+//! ("ea", "oí") are two.
+
+use std::collections::HashMap;
+
+use pyo3::prelude::*;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::SentenceSplitter;
+
+/// Vowels that never join into a diphthong with each other
+const STRONG_VOWELS: &[char] = &['a', 'e', 'o', 'á', 'é', 'í', 'ó', 'ú'];
+
+/// Vowels that join a neighbouring vowel into one syllable
+const WEAK_VOWELS: &[char] = &['i', 'u', 'ü'];
+
+/// Readability statistics of a text
+///
+/// The score is 206.84 - 60 × syllables per word - 1.02 × words per
+/// sentence. Sentences are split as in split_sentences and words as in
+/// count_tokens.
+///
+/// Args:
+///     text: The input text
+///
+/// Returns:
+///     Dict with "sentence_count", "word_count", "avg_words_per_sentence",
+///     "avg_syllables_per_word" and "fernandez_huerta"; all 0.0 for a text
+///     without words
+#[pyfunction]
+pub fn readability(py: Python<'_>, text: &str) -> PyResult<HashMap<String, f64>> {
+    Ok(crate::allow_threads_if_large(py, text.len(), || readability_internal(text)))
+}
+
+pub(crate) fn readability_internal(text: &str) -> HashMap<String, f64> {
+    let sentences = SentenceSplitter::default().split(text).len();
+    let (words, syllables) = text
+        .unicode_words()
+        .fold((0usize, 0usize), |(words, syllables), word| (words + 1, syllables + count_syllables(word)));
+
+    let (words_per_sentence, syllables_per_word, score) = if words == 0 {
+        (0.0, 0.0, 0.0)
+    } else {
+        let words_per_sentence = words as f64 / sentences.max(1) as f64;
+        let syllables_per_word = syllables as f64 / words as f64;
+        (words_per_sentence, syllables_per_word, 206.84 - 60.0 * syllables_per_word - 1.02 * words_per_sentence)
+    };
+    [
+        ("sentence_count", sentences as f64),
+        ("word_count", words as f64),
+        ("avg_words_per_sentence", words_per_sentence),
+        ("avg_syllables_per_word", syllables_per_word),
+        ("fernandez_huerta", score),
+    ]
+    .into_iter()
+    .map(|(name, value)| (name.to_string(), value))
+    .collect()
+}
+
+/// Syllables of a Spanish word, at least one
+pub(crate) fn count_syllables(word: &str) -> usize {
+    let mut syllables = 0;
+    let mut previous: Option<char> = None;
+    for c in word.chars().flat_map(char::to_lowercase) {
+        let vowel = STRONG_VOWELS.contains(&c) || WEAK_VOWELS.contains(&c);
+        if vowel {
+            // A new nucleus after a consonant, or between two strong vowels
+            let joins = previous.is_some_and(|p| {
+                (STRONG_VOWELS.contains(&p) || WEAK_VOWELS.contains(&p))
+                    && !(STRONG_VOWELS.contains(&p) && STRONG_VOWELS.contains(&c))
+            });
+            if !joins {
+                syllables += 1;
+            }
+        }
+        previous = Some(c);
+    }
+    syllables.max(1)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_syllables() {
+        for (word, expected) in [
+            ("pan", 1),
+            ("casa", 2),
+            ("paciente", 3),
+            ("cuidado", 3),
+            ("pingüino", 3),
+            ("teatro", 3),
+            ("día", 2),
+            ("neumonía", 4),
+            ("HTA", 1),
+            ("2026", 1),
+        ] {
+            assert_eq!(count_syllables(word), expected, "{}", word);
+        }
+    }
+
+    #[test]
+    fn test_simple_reads_easier_than_complex() {
+        let simple = readability_internal("El niño come pan. La casa es azul.");
+        let complex = readability_internal(
+            "La administración concomitante de anticoagulantes orales e inhibidores plaquetarios \
+             incrementa significativamente la probabilidad de complicaciones hemorrágicas \
+             gastrointestinales en pacientes hospitalizados con insuficiencia renal crónica.",
+        );
+        assert_eq!(simple["sentence_count"], 2.0);
+        assert_eq!(simple["word_count"], 8.0);
+        assert_eq!(simple["avg_words_per_sentence"], 4.0);
+        assert_eq!(complex["sentence_count"], 1.0);
+        assert!(complex["avg_syllables_per_word"] > simple["avg_syllables_per_word"]);
+        assert!(simple["fernandez_huerta"] > 90.0, "{}", simple["fernandez_huerta"]);
+        assert!(complex["fernandez_huerta"] < 30.0, "{}", complex["fernandez_huerta"]);
+
+        crate::with_py(|py| {
+            let empty = readability(py, "  ").unwrap();
+            assert_eq!(empty.len(), 5);
+            assert!(empty.values().all(|&value| value == 0.0));
+        });
+    }
+}
@@ -0,0 +1,264 @@
+//! Relative date expressions anchored to a reference date
+//!
+//! Notes date events relative to when they were written ("hace 3 días",
+//! "ayer por la noche", "el mes pasado"). Given the date of the note, each
+//! expression resolves to a calendar date. Vague ones ("hace un tiempo",
+//! "hace unos días") are still reported, without a date.
+
+use once_cell::sync::Lazy;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use regex::Regex;
+
+use crate::date_shift::{civil_from_days, days_from_civil};
+use crate::CharCursor;
+
+/// (phrase, resolved ISO date or None, start_char, end_char) of each expression
+pub(crate) type RelativeDates = Vec<(String, Option<String>, usize, usize)>;
+
+static RELATIVE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?xi)
+        \b(?:
+            hace \s+ (?P<count> \d{1,3} | un[oa]? | dos | tres | cuatro | cinco | seis | siete | ocho
+                              | nueve | diez | once | doce | quince | veinte | treinta )
+                 \s+ (?P<unit> d[ií]as? | semanas? | mes(?:es)? | años? )
+          | hace \s+ (?P<vague> (?: un (?:os|as)? | algun(?:os|as) | vari[oa]s | poc[oa]s | much[oa]s? )
+                                \s+ (?: tiempo | rato | d[ií]as | semanas | meses | años )
+                              | poco | mucho (?: \s+ tiempo )? )
+          | (?P<day> antes \s+ de \s+ ayer | anteayer | ayer | hoy | pasado \s+ mañana | mañana )
+          | (?: la | el ) \s+ (?P<period> semana | mes | año )
+                \s+ (?P<direction> pasad[oa] | pr[oó]xim[oa] | que \s+ viene )
+          | (?: la | el ) \s+ (?P<leading> pasad[oa] | pr[oó]xim[oa] ) \s+ (?P<leading_period> semana | mes | año )
+        )\b",
+    )
+    .expect("Invalid relative date regex - this is a bug")
+});
+
+/// Words before "mañana" that make it the morning, not tomorrow
+const MORNING_WORDS: &[&str] = &["la", "esta", "cada", "una", "toda", "media", "las"];
+
+/// How far a phrase moves from the reference date
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Offset {
+    Days(i64),
+    Months(i64),
+}
+
+/// Find Spanish relative date expressions and resolve them
+///
+/// Recognized: "hace N días/semanas/meses/años" (N in digits or words up to
+/// "treinta"), "anteayer"/"antes de ayer", "ayer", "hoy", "mañana",
+/// "pasado mañana", and "la semana / el mes / el año pasado" or "próximo"
+/// ("que viene"). Month and year steps keep the day of the month, moved
+/// back to the last day when the month is shorter ("hace 1 mes" from
+/// 2026-03-31 is 2026-02-28). "mañana" after "la", "esta", "cada", ... is
+/// the morning and is skipped.
+///
+/// Args:
+///     text: The input text
+///     reference_date: Date the text was written, as "YYYY-MM-DD"
+///
+/// Returns:
+///     List of (phrase, resolved_iso_date, start_char, end_char) in text
+///     order; resolved_iso_date is None for vague phrases such as
+///     "hace un tiempo" or "hace unos días"
+///
+/// Raises:
+///     ValueError: If reference_date is not a valid "YYYY-MM-DD" date
+#[pyfunction]
+pub fn extract_relative_dates(py: Python<'_>, text: &str, reference_date: &str) -> PyResult<RelativeDates> {
+    let reference = parse_iso(reference_date).ok_or_else(|| {
+        PyValueError::new_err(format!("reference_date must be a valid YYYY-MM-DD date, got {:?}", reference_date))
+    })?;
+    Ok(crate::allow_threads_if_large(py, text.len(), || extract_relative_dates_internal(text, reference)))
+}
+
+pub(crate) fn extract_relative_dates_internal(text: &str, reference: (i64, u32, u32)) -> RelativeDates {
+    let mut cursor = CharCursor::new(text);
+    RELATIVE_RE
+        .captures_iter(text)
+        .filter_map(|caps| {
+            let m = caps.get(0)?;
+            let day_word = caps.name("day").map(|day| day.as_str().to_lowercase());
+            if day_word.as_deref() == Some("mañana") && is_morning(&text[..m.start()]) {
+                return None;
+            }
+            let offset = if caps.name("vague").is_some() {
+                None
+            } else if let Some(word) = day_word {
+                Some(Offset::Days(match word.split_whitespace().collect::<Vec<_>>()[..] {
+                    ["anteayer"] | ["antes", "de", "ayer"] => -2,
+                    ["ayer"] => -1,
+                    ["hoy"] => 0,
+                    ["mañana"] => 1,
+                    _ => 2,
+                }))
+            } else if let Some(count) = caps.name("count") {
+                let count = parse_count(count.as_str())?;
+                Some(step(caps.name("unit")?.as_str(), -count))
+            } else {
+                let (period, direction) = match (caps.name("period"), caps.name("direction")) {
+                    (Some(period), Some(direction)) => (period, direction),
+                    _ => (caps.name("leading_period")?, caps.name("leading")?),
+                };
+                let sign = if direction.as_str().to_lowercase().starts_with("pasad") { -1 } else { 1 };
+                Some(step(period.as_str(), sign))
+            };
+            let resolved = offset.map(|offset| iso(apply(reference, offset)));
+            Some((m.as_str().to_string(), resolved, cursor.char_offset(m.start()), cursor.char_offset(m.end())))
+        })
+        .collect()
+}
+
+/// "la mañana", "esta mañana": the word right before is an article or determiner
+fn is_morning(before: &str) -> bool {
+    before
+        .split_whitespace()
+        .next_back()
+        .is_some_and(|word| MORNING_WORDS.contains(&word.to_lowercase().as_str()))
+}
+
+fn parse_count(count: &str) -> Option<i64> {
+    if let Ok(number) = count.parse() {
+        return Some(number);
+    }
+    let number = match count.to_lowercase().as_str() {
+        "un" | "uno" | "una" => 1,
+        "dos" => 2,
+        "tres" => 3,
+        "cuatro" => 4,
+        "cinco" => 5,
+        "seis" => 6,
+        "siete" => 7,
+        "ocho" => 8,
+        "nueve" => 9,
+        "diez" => 10,
+        "once" => 11,
+        "doce" => 12,
+        "quince" => 15,
+        "veinte" => 20,
+        "treinta" => 30,
+        _ => return None,
+    };
+    Some(number)
+}
+
+/// `count` units of a "día", "semana", "mes" or "año" unit word
+fn step(unit: &str, count: i64) -> Offset {
+    let unit = unit.to_lowercase();
+    if unit.starts_with('d') {
+        Offset::Days(count)
+    } else if unit.starts_with('s') {
+        Offset::Days(count * 7)
+    } else if unit.starts_with('m') {
+        Offset::Months(count)
+    } else {
+        Offset::Months(count * 12)
+    }
+}
+
+fn apply((year, month, day): (i64, u32, u32), offset: Offset) -> (i64, u32, u32) {
+    match offset {
+        Offset::Days(days) => civil_from_days(days_from_civil(year, month, day) + days),
+        Offset::Months(months) => {
+            let index = year * 12 + i64::from(month) - 1 + months;
+            let (year, month) = (index.div_euclid(12), index.rem_euclid(12) as u32 + 1);
+            (year, month, day.min(days_in_month(year, month)))
+        }
+    }
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    let (next_year, next_month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+    (days_from_civil(next_year, next_month, 1) - days_from_civil(year, month, 1)) as u32
+}
+
+/// "2026-03-05" -> (2026, 3, 5), rejecting impossible dates
+fn parse_iso(date: &str) -> Option<(i64, u32, u32)> {
+    let mut fields = date.trim().splitn(3, '-');
+    let year_field = fields.next()?;
+    let month_field = fields.next()?;
+    let day_field = fields.next()?;
+    if year_field.len() != 4 || month_field.len() != 2 || day_field.len() != 2 {
+        return None;
+    }
+    let (year, month, day): (i64, u32, u32) = (year_field.parse().ok()?, month_field.parse().ok()?, day_field.parse().ok()?);
+    ((1..=12).contains(&month) && (1..=days_in_month(year, month)).contains(&day)).then_some((year, month, day))
+}
+
+fn iso((year, month, day): (i64, u32, u32)) -> String {
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolved(text: &str, reference: &str) -> Vec<(String, Option<String>)> {
+        extract_relative_dates_internal(text, parse_iso(reference).unwrap())
+            .into_iter()
+            .map(|(phrase, date, _, _)| (phrase, date))
+            .collect()
+    }
+
+    fn found(phrase: &str, date: Option<&str>) -> (String, Option<String>) {
+        (phrase.to_string(), date.map(str::to_string))
+    }
+
+    #[test]
+    fn test_resolves_against_reference() {
+        let text = "Fiebre desde hace 3 días, tos hace dos semanas. Ayer por la noche vomitó; \
+                    anteayer cefalea. Control mañana y la semana próxima. Internado el mes pasado, \
+                    cirugía hace 1 año.";
+        assert_eq!(
+            resolved(text, "2026-03-02"),
+            vec![
+                found("hace 3 días", Some("2026-02-27")),
+                found("hace dos semanas", Some("2026-02-16")),
+                found("Ayer", Some("2026-03-01")),
+                found("anteayer", Some("2026-02-28")),
+                found("mañana", Some("2026-03-03")),
+                found("la semana próxima", Some("2026-03-09")),
+                found("el mes pasado", Some("2026-02-02")),
+                found("hace 1 año", Some("2025-03-02")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_month_ends_and_vague_phrases() {
+        assert_eq!(resolved("hace un mes", "2026-03-31"), vec![found("hace un mes", Some("2026-02-28"))]);
+        assert_eq!(resolved("hace 1 año", "2024-02-29"), vec![found("hace 1 año", Some("2023-02-28"))]);
+        assert_eq!(resolved("pasado mañana", "2025-12-31"), vec![found("pasado mañana", Some("2026-01-02"))]);
+        assert_eq!(
+            resolved("Dolor hace un tiempo, mareos hace unos días, hace poco.", "2026-03-02"),
+            vec![found("hace un tiempo", None), found("hace unos días", None), found("hace poco", None)]
+        );
+        // "la mañana" is the morning; "hace" with a time-of-day unit is not a date
+        assert!(resolved("Ayer por la mañana; esta mañana afebril; hace 3 horas", "2026-03-02")
+            .iter()
+            .all(|(phrase, _)| phrase == "Ayer"));
+    }
+
+    #[test]
+    fn test_spans_and_reference_validation() {
+        let text = "Niño febril desde hace 2 días";
+        let chars: Vec<char> = text.chars().collect();
+        for (phrase, _, start, end) in extract_relative_dates_internal(text, (2026, 1, 10)) {
+            assert_eq!(chars[start..end].iter().collect::<String>(), phrase);
+        }
+        for invalid in ["2026-02-30", "2026-13-01", "02/03/2026", "2026-3-2", ""] {
+            assert!(parse_iso(invalid).is_none(), "{}", invalid);
+        }
+
+        crate::with_py(|py| {
+            assert!(extract_relative_dates(py, "ayer", "2026-02-30").is_err());
+            assert_eq!(
+                extract_relative_dates(py, "Alta hoy", "2026-01-10").unwrap(),
+                vec![("hoy".to_string(), Some("2026-01-10".to_string()), 5, 8)]
+            );
+        });
+    }
+}
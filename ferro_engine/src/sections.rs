@@ -0,0 +1,303 @@
+//! Section structure of clinical notes
+//!
+//! Notes are loosely organized under headings (MOTIVO DE CONSULTA,
+//! ANTECEDENTES, EXAMEN FÍSICO, DIAGNÓSTICO, PLAN). A line is a heading
+//! when it is short and either written in capitals (without list or
+//! sentence punctuation) or ends with ":", or when it starts with one of
+//! the known headers (compared without case or accents), in which case
+//! text after a ":" on the same line already belongs to the body. Unless
+//! the caller gives its own list, the known headers are the usual ones of
+//! Spanish discharge summaries.
+
+use pyo3::prelude::*;
+
+use crate::{CharCursor, ChunkUnit};
+
+/// Default name given to the text before the first heading
+const PREAMBLE: &str = "_preamble";
+
+/// Longest line, in characters, taken as a heading by shape alone
+const MAX_HEADING_CHARS: usize = 60;
+
+/// Capitalized headings need this many letters ("PLAN", not "HTA")
+const MIN_CAPS_LETTERS: usize = 4;
+
+/// Headers known when the caller gives none, folded like fold()
+const DEFAULT_HEADERS: &[&str] = &[
+    "motivo de consulta", "motivo de ingreso", "enfermedad actual", "antecedentes",
+    "antecedentes personales", "antecedentes familiares", "alergias", "medicacion habitual",
+    "exploracion", "exploracion fisica", "examen fisico", "exploraciones complementarias",
+    "examenes complementarios", "evolucion", "diagnostico", "diagnosticos", "juicio clinico",
+    "tratamiento", "plan", "indicaciones al alta", "recomendaciones", "epicrisis",
+];
+
+/// (header, body, start_char, end_char) of each section
+pub(crate) type Sections = Vec<(String, String, usize, usize)>;
+
+/// Split a note into its sections
+///
+/// Args:
+///     text: The note
+///     headers: Headings to recognize, case- and accent-insensitive
+///              ("Diagnostico" matches "DIAGNÓSTICO:" and "Diagnóstico: ...").
+///              Defaults to the usual discharge summary headings (MOTIVO
+///              DE CONSULTA, ANTECEDENTES, EXPLORACIÓN, DIAGNÓSTICO,
+///              TRATAMIENTO, EVOLUCIÓN, PLAN, ...).
+///     preamble_header: Header given to text before the first heading
+///                      (default: "_preamble"). Pass "" to file it under
+///                      an empty header instead.
+///
+/// Returns:
+///     Ordered list of (header, body, start_char, end_char), where
+///     text[start_char:end_char] is the trimmed body and header is the
+///     heading as written, without a trailing ":". Text before the first
+///     heading, if any, comes first under preamble_header.
+#[pyfunction]
+#[pyo3(signature = (text, headers=None, preamble_header=PREAMBLE))]
+pub fn split_sections(py: Python<'_>, text: &str, headers: Option<Vec<String>>, preamble_header: &str) -> PyResult<Sections> {
+    let headers = headers.unwrap_or_else(default_headers);
+    Ok(crate::allow_threads_if_large(py, text.len(), || split_sections_internal(text, &headers, preamble_header)))
+}
+
+/// Chunk each section on its own, so no chunk spans two sections
+///
+/// Args:
+///     text: The note
+///     chunk_size: Maximum characters per chunk (default: 1000)
+///     overlap: Characters to overlap between chunks of the same section
+///              (default: 200)
+///     headers: Headings to recognize, as in split_sections
+///     preamble_header: Header of text before the first heading, as in
+///                      split_sections (default: "_preamble")
+///
+/// Returns:
+///     List of (header, chunk) in text order, chunks as in chunk_text
+///
+/// Raises:
+///     ValueError: If chunk_size is 0 or overlap >= chunk_size
+#[pyfunction]
+#[pyo3(signature = (text, chunk_size=1000, overlap=200, headers=None, preamble_header=PREAMBLE))]
+pub fn chunk_by_sections(
+    py: Python<'_>,
+    text: &str,
+    chunk_size: usize,
+    overlap: usize,
+    headers: Option<Vec<String>>,
+    preamble_header: &str,
+) -> PyResult<Vec<(String, String)>> {
+    crate::validate_chunk_params(chunk_size, overlap)?;
+    let headers = headers.unwrap_or_else(default_headers);
+    Ok(crate::allow_threads_if_large(py, text.len(), || {
+        chunk_by_sections_internal(text, chunk_size, overlap, &headers, preamble_header)
+    }))
+}
+
+pub(crate) fn chunk_by_sections_internal(
+    text: &str,
+    chunk_size: usize,
+    overlap: usize,
+    headers: &[String],
+    preamble_header: &str,
+) -> Vec<(String, String)> {
+    split_sections_internal(text, headers, preamble_header)
+        .into_iter()
+        .flat_map(|(header, body, _, _)| {
+            crate::chunk_text_internal(&body, chunk_size, overlap, ChunkUnit::Chars)
+                .into_iter()
+                .map(move |chunk| (header.clone(), chunk))
+        })
+        .collect()
+}
+
+/// Heading found on a line: its name and where its body starts (bytes)
+struct Heading<'a> {
+    name: &'a str,
+    line_start: usize,
+    body_start: usize,
+}
+
+pub(crate) fn split_sections_internal(text: &str, headers: &[String], preamble_header: &str) -> Sections {
+    let headers: Vec<String> = headers.iter().map(|header| fold(header)).filter(|header| !header.is_empty()).collect();
+    let mut headings: Vec<Heading> = Vec::new();
+    let mut line_start = 0;
+    for line in text.split_inclusive('\n') {
+        if let Some(heading) = heading(line, line_start, &headers) {
+            headings.push(heading);
+        }
+        line_start += line.len();
+    }
+
+    let mut bodies: Vec<(&str, usize, usize)> = Vec::with_capacity(headings.len() + 1);
+    let first = headings.first().map_or(text.len(), |heading| heading.line_start);
+    if !text[..first].trim().is_empty() {
+        bodies.push((preamble_header, 0, first));
+    }
+    for (i, heading) in headings.iter().enumerate() {
+        let end = headings.get(i + 1).map_or(text.len(), |next| next.line_start);
+        bodies.push((heading.name, heading.body_start, end));
+    }
+
+    let mut cursor = CharCursor::new(text);
+    bodies
+        .into_iter()
+        .map(|(name, start, end)| {
+            let raw = &text[start..end];
+            let body = raw.trim();
+            let body_start = start + (raw.len() - raw.trim_start().len());
+            let start_char = cursor.char_offset(body_start);
+            let end_char = cursor.char_offset(body_start + body.len());
+            (name.to_string(), body.to_string(), start_char, end_char)
+        })
+        .collect()
+}
+
+/// The heading on a line, if it is one
+fn heading<'a>(line: &'a str, line_start: usize, headers: &[String]) -> Option<Heading<'a>> {
+    let trimmed = line.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+    let offset = line_start + (line.len() - line.trim_start().len());
+
+    // A known header, alone or followed by ":" and inline text
+    let (label, rest) = match trimmed.split_once(':') {
+        Some((label, rest)) => (label.trim_end(), Some(rest)),
+        None => (trimmed, None),
+    };
+    if headers.contains(&fold(label)) {
+        let body_start = match rest {
+            Some(rest) => offset + trimmed.len() - rest.len(),
+            None => line_start + line.len(),
+        };
+        return Some(Heading { name: label, line_start, body_start });
+    }
+
+    if trimmed.chars().count() > MAX_HEADING_CHARS {
+        return None;
+    }
+    let name = trimmed.strip_suffix(':').map(str::trim_end);
+    let letters = trimmed.chars().filter(|c| c.is_alphabetic()).count();
+    // "HTA, DBT2." is an uppercase list, not a heading
+    let capitals = letters >= MIN_CAPS_LETTERS
+        && !trimmed.chars().any(|c| c.is_lowercase() || matches!(c, ',' | ';'))
+        && !trimmed.ends_with('.');
+    if name.is_some_and(|name| name.chars().any(char::is_alphabetic)) || capitals {
+        return Some(Heading { name: name.unwrap_or(trimmed), line_start, body_start: line_start + line.len() });
+    }
+    None
+}
+
+fn default_headers() -> Vec<String> {
+    DEFAULT_HEADERS.iter().map(|header| header.to_string()).collect()
+}
+
+/// Case- and accent-insensitive form of a header
+fn fold(header: &str) -> String {
+    crate::strip_accents(&header.trim().trim_end_matches(':').trim_end().to_lowercase(), false)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTE: &str = "Paciente derivado de guardia.\n\
+                        MOTIVO DE CONSULTA\n\
+                        Dolor abdominal de 2 días.\n\
+                        Antecedentes:\n\
+                        \x20 HTA, DBT2.\n\
+                        EXAMEN FÍSICO\n\
+                        Abdomen blando, doloroso en FID.\n\
+                        Diagnóstico: apendicitis aguda\n\
+                        PLAN\n";
+
+    fn names(sections: &Sections) -> Vec<&str> {
+        sections.iter().map(|(name, _, _, _)| name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_headings_by_shape() {
+        let sections = split_sections_internal(NOTE, &[], PREAMBLE);
+        assert_eq!(names(&sections), vec!["_preamble", "MOTIVO DE CONSULTA", "Antecedentes", "EXAMEN FÍSICO", "PLAN"]);
+        // "Diagnóstico: ..." has inline text, so it is not a heading by shape
+        assert_eq!(sections[3].1, "Abdomen blando, doloroso en FID.\nDiagnóstico: apendicitis aguda");
+        assert_eq!(sections[2].1, "HTA, DBT2.");
+        assert_eq!(sections[4].1, "");
+
+        let chars: Vec<char> = NOTE.chars().collect();
+        for (_, body, start, end) in &sections {
+            assert_eq!(&chars[*start..*end].iter().collect::<String>(), body);
+        }
+    }
+
+    #[test]
+    fn test_known_headers_accent_insensitive() {
+        let headers = vec!["diagnostico".to_string(), "Examen fisico".to_string()];
+        let sections = split_sections_internal(NOTE, &headers, PREAMBLE);
+        assert_eq!(names(&sections), vec!["_preamble", "MOTIVO DE CONSULTA", "Antecedentes", "EXAMEN FÍSICO", "Diagnóstico", "PLAN"]);
+        let (_, body, start, end) = &sections[4];
+        assert_eq!(body, "apendicitis aguda");
+        assert_eq!(NOTE.chars().skip(*start).take(end - start).collect::<String>(), "apendicitis aguda");
+
+        // No headings: everything is preamble; empty text: no sections
+        assert_eq!(names(&split_sections_internal("Evolución favorable.", &[], PREAMBLE)), vec!["_preamble"]);
+        assert!(split_sections_internal("", &[], PREAMBLE).is_empty());
+        // Short capitals and long lines are not headings
+        assert_eq!(names(&split_sections_internal("HTA\nControl en 48 hs.", &[], PREAMBLE)), vec!["_preamble"]);
+    }
+
+    #[test]
+    fn test_default_discharge_headers() {
+        let note = "Hospital General. Servicio de Clínica Médica.\n\
+                    Motivo de consulta: disnea de 3 días.\n\
+                    Exploración:\n\
+                    Crepitantes bibasales, edemas en miembros inferiores.\n\
+                    Tratamiento: furosemida 40 mg/día.\n\
+                    Control por consultorio.";
+        crate::with_py(|py| {
+            let sections = split_sections(py, note, None, PREAMBLE).unwrap();
+            let pairs: Vec<(&str, &str)> = sections.iter().map(|(name, body, _, _)| (name.as_str(), body.as_str())).collect();
+            assert_eq!(
+                pairs,
+                vec![
+                    ("_preamble", "Hospital General. Servicio de Clínica Médica."),
+                    ("Motivo de consulta", "disnea de 3 días."),
+                    ("Exploración", "Crepitantes bibasales, edemas en miembros inferiores."),
+                    ("Tratamiento", "furosemida 40 mg/día.\nControl por consultorio."),
+                ]
+            );
+            // A caller's list replaces the defaults; shape rules still apply
+            let custom = split_sections(py, note, Some(vec!["tratamiento".to_string()]), PREAMBLE).unwrap();
+            assert_eq!(names(&custom), vec!["_preamble", "Exploración", "Tratamiento"]);
+        });
+    }
+
+    #[test]
+    fn test_preamble_under_empty_header() {
+        let sections = split_sections_internal(NOTE, &[], "");
+        assert_eq!(names(&sections), vec!["", "MOTIVO DE CONSULTA", "Antecedentes", "EXAMEN FÍSICO", "PLAN"]);
+        assert_eq!(sections[0].1, split_sections_internal(NOTE, &[], PREAMBLE)[0].1);
+
+        let chunks = chunk_by_sections_internal("Ingresa por guardia.\nPLAN\nAlta.", 100, 0, &[], "");
+        let headers: Vec<&str> = chunks.iter().map(|(header, _)| header.as_str()).collect();
+        assert_eq!(headers, vec!["", "PLAN"]);
+    }
+
+    #[test]
+    fn test_chunks_stay_within_sections() {
+        let text = "EVOLUCIÓN\nPaciente estable. Afebril. Tolera dieta.\nPLAN\nAlta mañana. Control en 7 días.";
+        let chunks = chunk_by_sections_internal(text, 30, 0, &[], PREAMBLE);
+        assert!(chunks.iter().all(|(_, chunk)| chunk.chars().count() <= 30));
+        for (header, chunk) in &chunks {
+            let other = if header == "PLAN" { "Afebril" } else { "Alta" };
+            assert!(!chunk.contains(other), "{}: {}", header, chunk);
+        }
+        assert_eq!(chunks.first().map(|(h, _)| h.as_str()), Some("EVOLUCIÓN"));
+        assert_eq!(chunks.last().map(|(h, _)| h.as_str()), Some("PLAN"));
+
+        crate::with_py(|py| {
+            assert!(chunk_by_sections(py, text, 10, 10, None, PREAMBLE).is_err());
+            assert_eq!(split_sections(py, text, None, PREAMBLE).unwrap().len(), 2);
+        });
+    }
+}
@@ -0,0 +1,257 @@
+//! Lexical similarity between texts
+//!
+//! Used to drop near-identical chunks before embedding. Texts are tokenized
+//! like tokenize (lowercased words, punctuation dropped) and compared as
+//! token sets (Jaccard) or term-frequency vectors (cosine). An empty text
+//! is similar to nothing, itself included: the score is 0.0, never NaN.
+
+use std::collections::HashMap;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+
+/// How two texts are compared
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Method {
+    /// |A ∩ B| / |A ∪ B| over token sets
+    Jaccard,
+    /// Cosine of term-frequency vectors
+    Cosine,
+}
+
+impl Method {
+    pub(crate) fn parse(method: &str) -> PyResult<Self> {
+        match method {
+            "jaccard" => Ok(Self::Jaccard),
+            "cosine" => Ok(Self::Cosine),
+            _ => Err(PyValueError::new_err(format!(
+                "Unknown similarity method: {:?} (expected \"jaccard\" or \"cosine\")",
+                method
+            ))),
+        }
+    }
+}
+
+/// Token counts of one text, computed once and compared many times
+pub(crate) struct Profile {
+    counts: HashMap<String, usize>,
+    /// Sum of squared counts
+    norm_squared: usize,
+}
+
+impl Profile {
+    pub(crate) fn new(text: &str) -> Self {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for token in crate::word_tokens(text, true, false) {
+            *counts.entry(token).or_default() += 1;
+        }
+        let norm_squared = counts.values().map(|&c| c * c).sum();
+        Self { counts, norm_squared }
+    }
+
+    pub(crate) fn similarity(&self, other: &Self, method: Method) -> f64 {
+        if self.counts.is_empty() || other.counts.is_empty() {
+            return 0.0;
+        }
+        let (small, large) =
+            if self.counts.len() <= other.counts.len() { (self, other) } else { (other, self) };
+        match method {
+            Method::Jaccard => {
+                let shared = small.counts.keys().filter(|token| large.counts.contains_key(*token)).count();
+                let union = self.counts.len() + other.counts.len() - shared;
+                shared as f64 / union as f64
+            }
+            Method::Cosine => {
+                let dot: usize = small
+                    .counts
+                    .iter()
+                    .filter_map(|(token, &count)| large.counts.get(token).map(|&other| count * other))
+                    .sum();
+                // The norms are multiplied as integers and rounded to a float once, so
+                // identical texts give dot / sqrt(dot²) = 1.0; the min absorbs rounding
+                let norms = self.norm_squared as u128 * other.norm_squared as u128;
+                (dot as f64 / (norms as f64).sqrt()).min(1.0)
+            }
+        }
+    }
+}
+
+/// Similarity of two texts
+///
+/// Args:
+///     a: First text
+///     b: Second text
+///     method: "jaccard" (token sets) or "cosine" (term frequencies)
+///             (default: "jaccard")
+///
+/// Returns:
+///     Score between 0.0 and 1.0; 0.0 if either text has no tokens
+///
+/// Raises:
+///     ValueError: If method is unknown
+#[pyfunction]
+#[pyo3(signature = (a, b, method="jaccard"))]
+pub fn similarity(a: &str, b: &str, method: &str) -> PyResult<f64> {
+    let method = Method::parse(method)?;
+    Ok(Profile::new(a).similarity(&Profile::new(b), method))
+}
+
+/// Cosine similarity of the term-frequency vectors of two texts
+///
+/// Shorthand for similarity(a, b, method="cosine"): case and word order do
+/// not matter, repeated words weigh more.
+///
+/// Args:
+///     a: First text
+///     b: Second text
+///
+/// Returns:
+///     Score between 0.0 and 1.0; 0.0 if either text has no tokens
+#[pyfunction]
+pub fn cosine_similarity(a: &str, b: &str) -> f64 {
+    Profile::new(a).similarity(&Profile::new(b), Method::Cosine)
+}
+
+/// Jaccard similarity of the token sets of two texts
+///
+/// Shorthand for similarity(a, b, method="jaccard"): shared distinct tokens
+/// over all distinct tokens, ignoring case, order and repetition. Cheap
+/// enough to gate chunks before embedding them.
+///
+/// Args:
+///     a: First text
+///     b: Second text
+///
+/// Returns:
+///     Score between 0.0 and 1.0; 0.0 if either text has no tokens
+#[pyfunction]
+pub fn jaccard_similarity(a: &str, b: &str) -> f64 {
+    Profile::new(a).similarity(&Profile::new(b), Method::Jaccard)
+}
+
+/// Pairs of texts at least `threshold` similar, computed in parallel
+///
+/// Every pair i < j is scored once; with threshold 0.0 the result is the
+/// full upper triangle of the similarity matrix. Runs without the GIL.
+///
+/// Args:
+///     texts: List of texts
+///     method: "jaccard" or "cosine", as in similarity (default: "jaccard")
+///     threshold: Minimum score to report, 0.0-1.0 (default: 0.8)
+///
+/// Returns:
+///     List of (i, j, score) with i < j, ordered by i then j
+///
+/// Raises:
+///     ValueError: If method is unknown or threshold is outside 0.0-1.0
+#[pyfunction]
+#[pyo3(signature = (texts, method="jaccard", threshold=0.8))]
+pub fn pairwise_similarity(
+    py: Python<'_>,
+    texts: Vec<String>,
+    method: &str,
+    threshold: f64,
+) -> PyResult<Vec<(usize, usize, f64)>> {
+    let method = Method::parse(method)?;
+    if !(0.0..=1.0).contains(&threshold) {
+        return Err(PyValueError::new_err("threshold must be between 0.0 and 1.0"));
+    }
+    Ok(py.allow_threads(|| pairwise_similarity_internal(&texts, method, threshold)))
+}
+
+pub(crate) fn pairwise_similarity_internal(texts: &[String], method: Method, threshold: f64) -> Vec<(usize, usize, f64)> {
+    let profiles: Vec<Profile> = texts.par_iter().map(|text| Profile::new(text)).collect();
+    profiles
+        .par_iter()
+        .enumerate()
+        .flat_map_iter(|(i, first)| {
+            let profiles = &profiles;
+            (i + 1..profiles.len()).filter_map(move |j| {
+                let score = first.similarity(&profiles[j], method);
+                (score >= threshold).then_some((i, j, score))
+            })
+        })
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(a: &str, b: &str, method: Method) -> f64 {
+        Profile::new(a).similarity(&Profile::new(b), method)
+    }
+
+    #[test]
+    fn test_jaccard_and_cosine() {
+        // {dolor, abdominal, agudo} vs {dolor, abdominal, cronico}: 2 shared of 4
+        assert_eq!(score("Dolor abdominal agudo.", "dolor ABDOMINAL cronico", Method::Jaccard), 0.5);
+        assert!((score("Dolor abdominal agudo", "dolor abdominal cronico", Method::Cosine) - 2.0 / 3.0).abs() < 1e-12);
+        // Repetition matters for cosine only
+        assert_eq!(score("fiebre fiebre tos", "fiebre tos", Method::Jaccard), 1.0);
+        let cosine = score("fiebre fiebre tos", "fiebre tos", Method::Cosine);
+        assert!((cosine - 3.0 / (5f64.sqrt() * 2f64.sqrt())).abs() < 1e-12);
+        assert_eq!(score("Sin cambios", "sin cambios", Method::Cosine), 1.0);
+    }
+
+    #[test]
+    fn test_empty_texts_score_zero() {
+        for method in [Method::Jaccard, Method::Cosine] {
+            assert_eq!(score("", "", method), 0.0);
+            assert_eq!(score("...", "fiebre", method), 0.0);
+            assert_eq!(score("fiebre", "", method), 0.0);
+        }
+        assert!(similarity("a", "b", "euclidean").is_err());
+    }
+
+    #[test]
+    fn test_cosine_similarity() {
+        assert_eq!(cosine_similarity("Dolor abdominal agudo", "dolor abdominal agudo"), 1.0);
+        assert_eq!(cosine_similarity("agudo abdominal DOLOR", "Dolor, abdominal, agudo."), 1.0);
+        assert_eq!(cosine_similarity("fiebre y tos", "dolor torácico"), 0.0);
+        // {dolor, torácico, agudo} vs {dolor, abdominal, agudo}: 2 / (√3·√3)
+        assert!((cosine_similarity("dolor torácico agudo", "dolor abdominal agudo") - 2.0 / 3.0).abs() < 1e-12);
+        assert_eq!(cosine_similarity("", ""), 0.0);
+        assert_eq!(cosine_similarity("", "fiebre"), 0.0);
+        // Squared norms of 2.25e10 each: their product exceeds usize
+        let repeated = "dolor ".repeat(150_000);
+        assert_eq!(cosine_similarity(&repeated, &repeated), 1.0);
+    }
+
+    #[test]
+    fn test_jaccard_similarity() {
+        assert_eq!(jaccard_similarity("Paciente estable", "paciente estable"), 1.0);
+        assert_eq!(jaccard_similarity("estable estable paciente", "Paciente, estable."), 1.0);
+        assert_eq!(jaccard_similarity("fiebre y tos", "dolor torácico"), 0.0);
+        // {dolor, torácico, agudo} vs {dolor, abdominal, agudo}: 2 shared of 4
+        assert_eq!(jaccard_similarity("dolor torácico agudo", "dolor abdominal agudo"), 0.5);
+        assert_eq!(jaccard_similarity("", ""), 0.0);
+        assert_eq!(jaccard_similarity("fiebre", ""), 0.0);
+    }
+
+    #[test]
+    fn test_pairwise_threshold() {
+        let texts: Vec<String> = [
+            "Paciente estable, sin fiebre.",
+            "paciente estable sin fiebre",
+            "Dolor torácico opresivo",
+            "",
+            "Paciente estable, sin fiebre hoy.",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        let pairs = pairwise_similarity_internal(&texts, Method::Jaccard, 0.8);
+        assert_eq!(pairs, vec![(0, 1, 1.0), (0, 4, 4.0 / 5.0), (1, 4, 4.0 / 5.0)]);
+        let all = pairwise_similarity_internal(&texts, Method::Cosine, 0.0);
+        assert_eq!(all.len(), 10);
+        assert!(all.iter().all(|&(i, j, score)| i < j && (0.0..=1.0).contains(&score)));
+
+        crate::with_py(|py| {
+            assert!(pairwise_similarity(py, texts.clone(), "jaccard", 1.5).is_err());
+            assert_eq!(pairwise_similarity(py, texts, "jaccard", 1.0).unwrap(), vec![(0, 1, 1.0)]);
+        });
+    }
+}
@@ -0,0 +1,236 @@
+//! Result snippets with highlighted query terms
+//!
+//! Matching folds case and accents one character at a time ("Neumonía" and
+//! "neumonia" compare equal, "año" and "ano" do not), so folded and
+//! original text share character offsets and highlights land on the original spelling. Terms only match
+//! whole words: "dolor" is not highlighted inside "dolores".
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+/// Marks a cut at the start or end of the snippet
+const ELLIPSIS: char = '…';
+
+/// Build a snippet around the densest cluster of query terms
+///
+/// The window is placed over the region with the most term occurrences
+/// (the earliest one on ties), centred on them, then narrowed so it starts
+/// and ends on word boundaries. Overlapping or adjacent occurrences are
+/// wrapped as one highlight.
+///
+/// Args:
+///     text: The matched chunk
+///     query_terms: Words or phrases to highlight (case- and
+///                  accent-insensitive)
+///     window_chars: Maximum snippet length in characters, excluding
+///                   markers and ellipses (default: 200)
+///     highlight: (opening, closing) marker around each match
+///                (default: ("<mark>", "</mark>"))
+///
+/// Returns:
+///     The snippet, with "…" where the text was cut. Without any match, the
+///     start of the text.
+///
+/// Raises:
+///     ValueError: If window_chars is 0
+#[pyfunction]
+#[pyo3(signature = (text, query_terms, window_chars=200, highlight=("<mark>".to_string(), "</mark>".to_string())))]
+pub fn make_snippet(
+    py: Python<'_>,
+    text: &str,
+    query_terms: Vec<String>,
+    window_chars: usize,
+    highlight: (String, String),
+) -> PyResult<String> {
+    if window_chars == 0 {
+        return Err(PyValueError::new_err("window_chars must be greater than 0"));
+    }
+    Ok(crate::allow_threads_if_large(py, text.len(), || {
+        make_snippet_internal(text, &query_terms, window_chars, (&highlight.0, &highlight.1))
+    }))
+}
+
+pub(crate) fn make_snippet_internal(text: &str, terms: &[String], window: usize, (open, close): (&str, &str)) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let matches = find_terms(&chars, terms);
+
+    let (start, end) = if chars.len() <= window {
+        (0, chars.len())
+    } else {
+        let (cluster_start, cluster_end) = densest_cluster(&matches, window).unwrap_or((0, 0));
+        let slack = window.saturating_sub(cluster_end - cluster_start);
+        let start = cluster_start.saturating_sub(slack / 2).min(chars.len() - window);
+        word_bounded(&chars, start, start + window)
+    };
+    let (start, end) = trim(&chars, start, end);
+
+    let mut snippet = String::new();
+    if start > 0 {
+        snippet.push(ELLIPSIS);
+    }
+    let mut position = start;
+    for &(match_start, match_end) in &matches {
+        let (match_start, match_end) = (match_start.max(start), match_end.min(end));
+        if match_start >= match_end {
+            continue;
+        }
+        snippet.extend(&chars[position..match_start]);
+        snippet.push_str(open);
+        snippet.extend(&chars[match_start..match_end]);
+        snippet.push_str(close);
+        position = match_end;
+    }
+    snippet.extend(&chars[position..end]);
+    if end < chars.len() {
+        snippet.push(ELLIPSIS);
+    }
+    snippet
+}
+
+/// Lowercase, accent-free form of a character; "ñ" stays
+fn fold(c: char) -> char {
+    crate::fold_char(c, true, true, true)
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric()
+}
+
+/// Whole-word occurrences of any term, merged where they overlap or touch
+fn find_terms(chars: &[char], terms: &[String]) -> Vec<(usize, usize)> {
+    let folded: Vec<char> = chars.iter().copied().map(fold).collect();
+    let mut found: Vec<(usize, usize)> = Vec::new();
+    for term in terms {
+        let term: Vec<char> = term.trim().chars().map(fold).collect();
+        if term.is_empty() || term.len() > folded.len() {
+            continue;
+        }
+        for start in 0..=folded.len() - term.len() {
+            let end = start + term.len();
+            let bounded = (start == 0 || !is_word(folded[start - 1])) && (end == folded.len() || !is_word(folded[end]));
+            if bounded && folded[start..end] == term[..] {
+                found.push((start, end));
+            }
+        }
+    }
+    found.sort_unstable();
+    let mut merged: Vec<(usize, usize)> = Vec::with_capacity(found.len());
+    for (start, end) in found {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// Span of the most matches that fit in the window, earliest on ties
+fn densest_cluster(matches: &[(usize, usize)], window: usize) -> Option<(usize, usize)> {
+    let mut best: Option<(usize, (usize, usize))> = None;
+    for (i, &(start, _)) in matches.iter().enumerate() {
+        let inside = matches[i..].iter().take_while(|&&(_, end)| end - start <= window).count();
+        if inside > 0 && best.is_none_or(|(count, _)| inside > count) {
+            best = Some((inside, (start, matches[i + inside - 1].1)));
+        }
+    }
+    best.map(|(_, span)| span)
+}
+
+/// Narrow a window so no word is cut in half
+///
+/// A single word longer than the window is cut anyway.
+fn word_bounded(chars: &[char], start: usize, end: usize) -> (usize, usize) {
+    let mut bounded_start = start;
+    if start > 0 && is_word(chars[start - 1]) {
+        while bounded_start < end && is_word(chars[bounded_start]) {
+            bounded_start += 1;
+        }
+    }
+    let mut bounded_end = end;
+    if end < chars.len() && is_word(chars[end]) {
+        while bounded_end > bounded_start && is_word(chars[bounded_end - 1]) {
+            bounded_end -= 1;
+        }
+    }
+    if bounded_start >= bounded_end {
+        return (start, end);
+    }
+    (bounded_start, bounded_end)
+}
+
+/// Drop whitespace at both ends of a window
+fn trim(chars: &[char], mut start: usize, mut end: usize) -> (usize, usize) {
+    while start < end && chars[start].is_whitespace() {
+        start += 1;
+    }
+    while end > start && chars[end - 1].is_whitespace() {
+        end -= 1;
+    }
+    (start, end)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MARKS: (&str, &str) = ("<mark>", "</mark>");
+
+    fn terms(terms: &[&str]) -> Vec<String> {
+        terms.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn test_case_and_accent_insensitive() {
+        let text = "Paciente con NEUMONIA bilateral; la neumonía cede con amoxicilina.";
+        assert_eq!(
+            make_snippet_internal(text, &terms(&["Neumonía"]), 200, MARKS),
+            "Paciente con <mark>NEUMONIA</mark> bilateral; la <mark>neumonía</mark> cede con amoxicilina."
+        );
+        // Whole words only
+        assert_eq!(make_snippet_internal("dolores de cabeza", &terms(&["dolor"]), 200, MARKS), "dolores de cabeza");
+    }
+
+    #[test]
+    fn test_densest_region_and_ellipses() {
+        let filler = "sin particularidades en el examen físico general. ".repeat(6);
+        let text = format!("Fiebre al ingreso. {}Persiste fiebre alta con tos productiva y fiebre nocturna. {}", filler, filler);
+        let snippet = make_snippet_internal(&text, &terms(&["fiebre", "tos"]), 80, ("[", "]"));
+        assert!(snippet.starts_with('…') && snippet.ends_with('…'), "{}", snippet);
+        assert!(snippet.contains("[fiebre] alta con [tos] productiva y [fiebre] nocturna"), "{}", snippet);
+        let body = snippet.trim_matches('…').replace(['[', ']'], "");
+        assert!(body.chars().count() <= 80);
+        // Cuts fall between words
+        assert!(text.contains(&body) && body == body.trim());
+        let before = &text[..text.find(&body).unwrap()];
+        assert!(before.ends_with(|c: char| !c.is_alphanumeric()));
+    }
+
+    #[test]
+    fn test_overlapping_terms_and_no_match() {
+        let text = "Dolor torácico opresivo";
+        assert_eq!(
+            make_snippet_internal(text, &terms(&["dolor torácico", "torácico opresivo"]), 200, MARKS),
+            "<mark>Dolor torácico opresivo</mark>"
+        );
+        let long = "Evolución favorable sin intercurrencias durante la internación en sala general.";
+        assert_eq!(make_snippet_internal(long, &terms(&["cardiología"]), 30, MARKS), "Evolución favorable sin…");
+        assert_eq!(make_snippet_internal(long, &terms(&[""]), 200, MARKS), long);
+    }
+
+    #[test]
+    fn test_multibyte_and_long_words() {
+        let text = "ñandú ñandú cigüeña acción camión pingüino añoranza";
+        let snippet = make_snippet_internal(text, &terms(&["CIGUEÑA"]), 12, MARKS);
+        assert_eq!(snippet, "…<mark>cigüeña</mark>…");
+        assert_eq!(make_snippet_internal("Hace un año", &terms(&["ano"]), 200, MARKS), "Hace un año");
+        assert_eq!(make_snippet_internal("Hace un AÑO", &terms(&["año"]), 200, MARKS), "Hace un <mark>AÑO</mark>");
+        // A word longer than the window is cut mid-word rather than dropped
+        assert_eq!(make_snippet_internal("otorrinolaringología", &terms(&[]), 5, MARKS), "otorr…");
+
+        crate::with_py(|py| {
+            assert!(make_snippet(py, text, vec![], 0, ("<b>".into(), "</b>".into())).is_err());
+            assert_eq!(make_snippet(py, "Tos seca", vec!["tos".into()], 200, ("<b>".into(), "</b>".into())).unwrap(), "<b>Tos</b> seca");
+        });
+    }
+}
@@ -0,0 +1,119 @@
+//! Per-document counts for dashboards
+//!
+//! One call instead of tokenize + count_tokens + split_sentences: word
+//! boundaries are walked once, counting characters, words and sentence
+//! ends (with split_sentences' rules) as they go.
+
+use std::collections::{HashMap, HashSet};
+
+use pyo3::prelude::*;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::SentenceSplitter;
+
+/// Character, byte, word, sentence and distinct word counts of a text
+///
+/// Words are those of tokenize and count_tokens, compared in lowercase
+/// for unique_word_count ("Dolor" and "dolor" are one word); sentences
+/// are those of split_sentences.
+///
+/// Args:
+///     text: The input text
+///
+/// Returns:
+///     Dict with "char_count", "byte_count", "word_count",
+///     "sentence_count" and "unique_word_count"
+#[pyfunction]
+pub fn text_stats(py: Python<'_>, text: &str) -> PyResult<HashMap<String, usize>> {
+    Ok(crate::allow_threads_if_large(py, text.len(), || text_stats_internal(text)))
+}
+
+pub(crate) fn text_stats_internal(text: &str) -> HashMap<String, usize> {
+    let splitter = SentenceSplitter::default();
+    let mut chars = 0;
+    let mut words = 0;
+    let mut sentences = 0;
+    let mut unique: HashSet<String> = HashSet::new();
+    // Start of the current sentence, whether it has any non-blank text
+    // (split_sentences drops blank ones), and where the last delimiter ends
+    let mut sentence_start = 0;
+    let mut in_sentence = false;
+    let mut resume = 0;
+    let mut prev: Option<char> = None;
+    for (offset, segment) in text.split_word_bound_indices() {
+        for (i, c) in segment.char_indices() {
+            let idx = offset + i;
+            chars += 1;
+            if idx >= resume {
+                if let Some(len) = splitter.boundary_at(text, idx, &text[sentence_start..idx], prev) {
+                    sentences += usize::from(in_sentence);
+                    in_sentence = false;
+                    sentence_start = idx + len;
+                    resume = sentence_start;
+                } else if !c.is_whitespace() {
+                    in_sentence = true;
+                }
+            }
+            prev = Some(c);
+        }
+        // unicode_words keeps the segments holding a letter or digit
+        if segment.chars().any(char::is_alphanumeric) {
+            words += 1;
+            unique.insert(segment.to_lowercase());
+        }
+    }
+    [
+        ("char_count", chars),
+        ("byte_count", text.len()),
+        ("word_count", words),
+        ("sentence_count", sentences + usize::from(in_sentence)),
+        ("unique_word_count", unique.len()),
+    ]
+    .into_iter()
+    .map(|(name, count)| (name.to_string(), count))
+    .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_paragraph() {
+        let text = "Paciente de 45 años. Dolor abdominal, dolor lumbar.\nDr. Pérez indica 0.5 mg.";
+        let stats = text_stats_internal(text);
+        assert_eq!(stats["char_count"], text.chars().count());
+        assert_eq!(stats["char_count"], 76);
+        assert_eq!(stats["byte_count"], 78);
+        assert_eq!(stats["word_count"], text.unicode_words().count());
+        assert_eq!(stats["word_count"], 13);
+        // "Dr." and "0.5" do not end sentences
+        assert_eq!(stats["sentence_count"], 3);
+        // "Dolor" and "dolor" count once
+        assert_eq!(stats["unique_word_count"], 12);
+    }
+
+    #[test]
+    fn test_sentences_match_split_sentences() {
+        let texts = [
+            "Paciente de 58 años, derivado por el Dr. Ruiz. ¿Dolor torácico? Niega! Fiebre de 38.2 °C\nPlan: control   ",
+            "1. Paracetamol 0.5 g c/8 hs.\n2. Enalapril 10 mg/día\n\n\n3. Dieta, etc.\n. . .",
+            "Sin puntuación final",
+            "...",
+            "Dx: I21.0. p.ej. en guardia.",
+        ];
+        for text in texts {
+            assert_eq!(text_stats_internal(text)["sentence_count"], SentenceSplitter::default().split(text).len(), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn test_empty_text() {
+        crate::with_py(|py| {
+            let stats = text_stats(py, "").unwrap();
+            assert_eq!(stats.len(), 5);
+            assert!(stats.values().all(|&count| count == 0));
+        });
+    }
+}
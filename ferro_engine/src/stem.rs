@@ -0,0 +1,117 @@
+//! Snowball stemming for Spanish and English
+//!
+//! Folds inflected forms onto a common stem ("infección", "infecciones" ->
+//! "infeccion") so keyword matching and BM25 treat them as one term. The
+//! algorithms are the reference Snowball stemmers; stems are index keys,
+//! not dictionary words ("presión" -> "presion", "dolores" -> "dolor").
+
+use once_cell::sync::Lazy;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use rust_stemmers::{Algorithm, Stemmer};
+use unicode_segmentation::UnicodeSegmentation;
+
+static SPANISH: Lazy<Stemmer> = Lazy::new(|| Stemmer::create(Algorithm::Spanish));
+static ENGLISH: Lazy<Stemmer> = Lazy::new(|| Stemmer::create(Algorithm::English));
+
+/// Stem each token with the Snowball stemmer for `lang`
+///
+/// Tokens are lowercased first, as the stemmers expect.
+///
+/// Args:
+///     tokens: List of tokens
+///     lang: "es" (Spanish) or "en" (English) (default: "es")
+///
+/// Returns:
+///     List of stems, one per token
+///
+/// Raises:
+///     ValueError: If lang is unknown
+#[pyfunction]
+#[pyo3(signature = (tokens, lang="es"))]
+pub fn stem_tokens(tokens: Vec<String>, lang: &str) -> PyResult<Vec<String>> {
+    let stemmer = stemmer_for(lang)?;
+    Ok(tokens.iter().map(|token| stem(stemmer, token)).collect())
+}
+
+/// Tokenize like tokenize and stem every token
+///
+/// Args:
+///     text: The input text
+///     lang: "es" or "en", as in stem_tokens (default: "es")
+///
+/// Returns:
+///     List of stems
+///
+/// Raises:
+///     ValueError: If lang is unknown
+#[pyfunction]
+#[pyo3(signature = (text, lang="es"))]
+pub fn tokenize_and_stem(py: Python<'_>, text: &str, lang: &str) -> PyResult<Vec<String>> {
+    let stemmer = stemmer_for(lang)?;
+    Ok(crate::allow_threads_if_large(py, text.len(), || {
+        text.unicode_words().map(|word| stem(stemmer, word)).collect()
+    }))
+}
+
+pub(crate) fn stemmer_for(lang: &str) -> PyResult<&'static Stemmer> {
+    match lang {
+        "es" => Ok(&SPANISH),
+        "en" => Ok(&ENGLISH),
+        _ => Err(PyValueError::new_err(format!("Unknown stemmer language: {:?} (expected \"es\" or \"en\")", lang))),
+    }
+}
+
+pub(crate) fn stem(stemmer: &Stemmer, token: &str) -> String {
+    stemmer.stem(&token.to_lowercase()).into_owned()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reference outputs of the Snowball stemmers
+    const SPANISH_FIXTURE: &[(&str, &str)] = &[
+        ("infección", "infeccion"), ("infecciones", "infeccion"), ("infeccioso", "infecci"),
+        ("infecciosa", "infecci"), ("presión", "presion"), ("presiones", "presion"),
+        ("dolor", "dolor"), ("dolores", "dolor"), ("cardíaco", "cardiac"), ("cardíaca", "cardiac"),
+        ("hipertensión", "hipertension"), ("hipertenso", "hipertens"), ("diabético", "diabet"),
+        ("diabética", "diabet"), ("fractura", "fractur"), ("fracturas", "fractur"),
+        ("operado", "oper"), ("operación", "oper"), ("medicamentos", "medicament"),
+        ("niño", "niñ"), ("Niños", "niñ"), ("TAC", "tac"),
+    ];
+
+    const ENGLISH_FIXTURE: &[(&str, &str)] = &[
+        ("infection", "infect"), ("infections", "infect"), ("infected", "infect"),
+        ("running", "run"), ("fractures", "fractur"), ("diabetes", "diabet"), ("diabetic", "diabet"),
+    ];
+
+    #[test]
+    fn test_spanish_fixture() {
+        for (word, expected) in SPANISH_FIXTURE {
+            assert_eq!(stem(&SPANISH, word), *expected, "word: {:?}", word);
+        }
+    }
+
+    #[test]
+    fn test_english_fixture() {
+        for (word, expected) in ENGLISH_FIXTURE {
+            assert_eq!(stem(&ENGLISH, word), *expected, "word: {:?}", word);
+        }
+    }
+
+    #[test]
+    fn test_stem_tokens_and_tokenize_and_stem() {
+        let tokens = vec!["Infecciones".to_string(), "urinarias".to_string()];
+        assert_eq!(stem_tokens(tokens, "es").unwrap(), vec!["infeccion", "urinari"]);
+        crate::with_py(|py| {
+            assert_eq!(
+                tokenize_and_stem(py, "Infección urinaria; infecciones previas", "es").unwrap(),
+                vec!["infeccion", "urinari", "infeccion", "previ"]
+            );
+            let err = stem_tokens(vec![], "pt").unwrap_err();
+            assert!(err.is_instance_of::<PyValueError>(py));
+        });
+    }
+}
@@ -0,0 +1,98 @@
+//! Built-in stopword lists
+//!
+//! Negations ("no", "sin", "ni", "niega") are deliberately absent from the
+//! Spanish list: in clinical text they flip the meaning of what follows
+//! ("no fiebre", "sin disnea") and must survive filtering. So are words
+//! that grade or describe a finding ("más", "menos", "bajo", "estado":
+//! "bajo peso", "estado general").
+//!
+//! Lookups are exact after lowercasing, so accents matter: "si" is a
+//! stopword while "sí" (an answer) is not.
+
+use std::collections::HashSet;
+
+use once_cell::sync::Lazy;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+/// Spanish function words plus boilerplate verbs/nouns of clinical notes
+pub(crate) const SPANISH: &[&str] = &[
+    // Articles, prepositions, conjunctions
+    "el", "la", "los", "las", "lo", "un", "una", "unos", "unas", "al", "del",
+    "a", "ante", "con", "contra", "de", "desde", "durante", "en", "entre",
+    "hacia", "hasta", "mediante", "para", "por", "según", "sobre", "tras",
+    "y", "e", "o", "u", "que", "pero", "como", "porque", "aunque", "si", "pues",
+    // Pronouns and determiners
+    "se", "su", "sus", "le", "les", "me", "mi", "mis", "te", "ti", "tu", "tus", "nos",
+    "yo", "él", "ella", "ellos", "ellas", "nosotros", "usted", "ustedes",
+    "este", "esta", "estos", "estas", "ese", "esa", "esos", "esas", "esto", "eso",
+    "aquel", "aquella", "cual", "cuales", "quien", "quienes", "cuyo", "cuya",
+    "otro", "otra", "otros", "otras", "todo", "toda", "todos", "todas",
+    "algún", "alguno", "alguna", "algunos", "algunas", "algo", "mismo", "misma",
+    // Adverbs
+    "muy", "ya", "también", "tanto", "donde", "cuando", "mientras",
+    "luego", "aquí", "allí", "así", "bien",
+    // Auxiliary and copular verbs
+    "es", "son", "fue", "fueron", "era", "eran", "ser", "sido", "siendo", "está",
+    "están", "estaba", "estar", "ha", "han", "había", "haber", "habiendo",
+    "hay", "tiene", "tienen", "tenía", "tener",
+    // Clinical boilerplate
+    "paciente", "pacientes", "refiere", "presenta", "realiza", "realizó", "acude", "consulta",
+];
+
+/// Lowercased Spanish stopwords for O(1) lookup
+pub(crate) static SPANISH_SET: Lazy<HashSet<&'static str>> = Lazy::new(|| SPANISH.iter().copied().collect());
+
+/// Short English list for notes and abstracts written in English
+pub(crate) const ENGLISH: &[&str] = &[
+    "a", "an", "the", "and", "or", "but", "if", "of", "at", "by", "for", "with", "about",
+    "to", "from", "in", "on", "into", "over", "under", "as", "than", "then", "so",
+    "is", "are", "was", "were", "be", "been", "being", "has", "have", "had", "do", "does", "did",
+    "it", "its", "this", "that", "these", "those", "he", "she", "they", "them", "his", "her",
+    "their", "we", "our", "you", "your", "i", "me", "my", "which", "who", "whom", "what",
+    "also", "very", "there", "here", "patient", "patients",
+];
+
+/// Lowercased English stopwords for O(1) lookup
+pub(crate) static ENGLISH_SET: Lazy<HashSet<&'static str>> = Lazy::new(|| ENGLISH.iter().copied().collect());
+
+/// Built-in list for a language code, optionally extended with user words
+pub(crate) struct StopwordFilter {
+    builtin: &'static HashSet<&'static str>,
+    extra: HashSet<String>,
+}
+
+impl StopwordFilter {
+    /// Filter for `lang` ("es" or "en") plus the lowercased `extra` words
+    pub(crate) fn new(lang: &str, extra: Option<Vec<String>>) -> PyResult<Self> {
+        let builtin = match lang {
+            "es" => &*SPANISH_SET,
+            "en" => &*ENGLISH_SET,
+            _ => return Err(PyValueError::new_err(format!("Unknown stopword language: {:?} (expected \"es\" or \"en\")", lang))),
+        };
+        let extra = extra.unwrap_or_default().iter().map(|w| w.to_lowercase()).collect();
+        Ok(Self { builtin, extra })
+    }
+
+    /// Whether an already lowercased word is a stopword
+    pub(crate) fn contains(&self, lower: &str) -> bool {
+        self.builtin.contains(lower) || self.extra.contains(lower)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_languages_and_extra_words() {
+        let spanish = StopwordFilter::new("es", Some(vec!["Control".to_string()])).unwrap();
+        assert!(spanish.contains("si") && !spanish.contains("sí"));
+        assert!(["más", "menos", "bajo", "estado"].iter().all(|word| !spanish.contains(word)));
+        assert!(spanish.contains("control") && !spanish.contains("the"));
+        let english = StopwordFilter::new("en", None).unwrap();
+        assert!(english.contains("the") && !english.contains("el"));
+        assert!(StopwordFilter::new("fr", None).is_err());
+    }
+}
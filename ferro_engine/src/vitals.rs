@@ -0,0 +1,283 @@
+//! Vital signs extraction
+//!
+//! Pulls blood pressure, heart rate, respiratory rate, temperature and
+//! oxygen saturation out of triage and evolution notes. Readings need a
+//! label ("TA", "FC", "SatO2", ...) or an unambiguous unit ("mmHg", "lpm",
+//! "°C") so that dates like "12/08" and unrelated percentages are not
+//! mistaken for vitals, and values outside physiological ranges are dropped.
+
+use once_cell::sync::Lazy;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use regex::Regex;
+
+use crate::CharCursor;
+
+static BLOOD_PRESSURE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?xi)
+        (?:
+            \b(?:TA|PA|T\.A\.|P\.A\.|tensi[oó]n\s+arterial|presi[oó]n\s+arterial) \s*:?\s*
+            (?P<sys>\d{2,3}) \s*/\s* (?P<dia>\d{2,3}) (?:\s*mm\s*Hg)?
+          | \b(?P<sys2>\d{2,3}) \s*/\s* (?P<dia2>\d{2,3}) \s* mm\s*Hg
+        )",
+    )
+    .expect("Invalid blood pressure regex - this is a bug")
+});
+
+static HEART_RATE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?xi)
+        (?:
+            \b(?:FC|frecuencia\s+card[ií]aca) \s*:?\s* (?P<v>\d{2,3}) (?:\s*(?:lpm|bpm|l/min|/min|x'))?
+          | \b(?P<v2>\d{2,3}) \s* (?:lpm|bpm)\b
+        )",
+    )
+    .expect("Invalid heart rate regex - this is a bug")
+});
+
+static RESPIRATORY_RATE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?xi)
+        (?:
+            \b(?:FR|frecuencia\s+respiratoria) \s*:?\s* (?P<v>\d{1,2}) (?:\s*(?:rpm|resp/min|/min|x'))?
+          | \b(?P<v2>\d{1,2}) \s* rpm\b
+        )",
+    )
+    .expect("Invalid respiratory rate regex - this is a bug")
+});
+
+static TEMPERATURE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        // Only the spelled-out labels ignore case: a lowercase "t" is too
+        // common a token to introduce a temperature
+        r"(?x)
+        (?:
+            \b(?:(?i:temperatura|temp)|T\.?\s*(?i:ax)|T°|Tº|T) \s*:?\s* (?P<v>\d{2}(?:[.,]\d{1,2})?) (?:\s*[°º]\s*[cC]?)?
+          | \b(?P<v2>\d{2}(?:[.,]\d{1,2})?) \s* [°º]\s*[cC]
+        )",
+    )
+    .expect("Invalid temperature regex - this is a bug")
+});
+
+static SATURATION_RE: Lazy<Regex> = Lazy::new(|| {
+    // The label is mandatory: a bare "97%" could be anything
+    Regex::new(
+        r"(?xi)
+        \b(?:SatO2|SpO2|Sat\s*O2|saturaci[oó]n(?:\s+de\s+O2)?|Sat\.?) \s*:?\s* (?P<v>\d{2,3}(?:[.,]\d)?) \s*%",
+    )
+    .expect("Invalid saturation regex - this is a bug")
+});
+
+/// Normalized value of a vital sign reading
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum VitalValue {
+    Single(f64),
+    Pressure { systolic: u32, diastolic: u32 },
+}
+
+/// One vital sign reading found in the text
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Vital {
+    pub value: VitalValue,
+    pub unit: &'static str,
+    pub raw: String,
+    /// Character offsets of the match
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Vital {
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new_bound(py);
+        match self.value {
+            VitalValue::Single(value) => dict.set_item("value", value)?,
+            VitalValue::Pressure { systolic, diastolic } => {
+                dict.set_item("systolic", systolic)?;
+                dict.set_item("diastolic", diastolic)?;
+            }
+        }
+        dict.set_item("unit", self.unit)?;
+        dict.set_item("raw", &self.raw)?;
+        dict.set_item("span", (self.start, self.end))?;
+        Ok(dict)
+    }
+}
+
+
+/// Extract vital signs
+///
+/// Recognizes:
+/// - blood_pressure: "TA 120/80", "PA: 135/85 mmHg", "130/85 mmHg"
+/// - heart_rate: "FC 78 lpm", "FC: 110", "78 bpm"
+/// - respiratory_rate: "FR 18 rpm", "FR: 22"
+/// - temperature: "T 38,5°C", "Temp 37.2", "38.5 °C"
+/// - oxygen_saturation: "SatO2 97%", "Sat 97 %", "SpO2: 94%"
+///
+/// Decimal commas are accepted. Readings outside plausible physiological
+/// ranges (e.g. "TA 12/08", "T 99") are discarded.
+///
+/// Args:
+///     text: The input text
+///
+/// Returns:
+///     Dict of vital type -> list of readings. Each reading is a dict with
+///     "value" (or "systolic"/"diastolic" for blood pressure), "unit",
+///     "raw" (the matched text) and "span" (start_char, end_char)
+#[pyfunction]
+pub fn extract_vitals<'py>(py: Python<'py>, text: &str) -> PyResult<Bound<'py, PyDict>> {
+    let vitals = crate::allow_threads_if_large(py, text.len(), || extract_vitals_internal(text));
+    let result = PyDict::new_bound(py);
+    for (kind, readings) in vitals {
+        let readings = readings
+            .iter()
+            .map(|vital| vital.to_dict(py))
+            .collect::<PyResult<Vec<_>>>()?;
+        result.set_item(kind, readings)?;
+    }
+    Ok(result)
+}
+
+/// Extract blood pressure readings as (systolic, diastolic) pairs
+///
+/// Same rules as the blood_pressure category of extract_vitals: a
+/// "N/N" pair counts only after a label ("TA", "PA", "tensión arterial")
+/// or before "mmHg", so dates such as "15/01" are never read as pressures,
+/// and implausible pairs are dropped.
+///
+/// Args:
+///     text: The input text
+///
+/// Returns:
+///     List of (systolic, diastolic) in document order
+#[pyfunction]
+pub fn extract_blood_pressure(text: &str) -> PyResult<Vec<(u32, u32)>> {
+    Ok(blood_pressures(text)
+        .into_iter()
+        .filter_map(|vital| match vital.value {
+            VitalValue::Pressure { systolic, diastolic } => Some((systolic, diastolic)),
+            VitalValue::Single(_) => None,
+        })
+        .collect())
+}
+
+pub(crate) fn extract_vitals_internal(text: &str) -> Vec<(&'static str, Vec<Vital>)> {
+    vec![
+        ("blood_pressure", blood_pressures(text)),
+        ("heart_rate", single_values(text, &HEART_RATE_RE, "bpm", 20.0..=300.0)),
+        ("respiratory_rate", single_values(text, &RESPIRATORY_RATE_RE, "rpm", 4.0..=80.0)),
+        ("temperature", single_values(text, &TEMPERATURE_RE, "°C", 30.0..=45.0)),
+        ("oxygen_saturation", single_values(text, &SATURATION_RE, "%", 40.0..=100.0)),
+    ]
+}
+
+fn blood_pressures(text: &str) -> Vec<Vital> {
+    let mut cursor = CharCursor::new(text);
+    BLOOD_PRESSURE_RE
+        .captures_iter(text)
+        .filter_map(|caps| {
+            let whole = caps.get(0)?;
+            let systolic: u32 = caps.name("sys").or(caps.name("sys2"))?.as_str().parse().ok()?;
+            let diastolic: u32 = caps.name("dia").or(caps.name("dia2"))?.as_str().parse().ok()?;
+            let plausible = (50..=300).contains(&systolic) && (20..=200).contains(&diastolic) && systolic > diastolic;
+            plausible.then(|| Vital {
+                value: VitalValue::Pressure { systolic, diastolic },
+                unit: "mmHg",
+                raw: whole.as_str().to_string(),
+                start: cursor.char_offset(whole.start()),
+                end: cursor.char_offset(whole.end()),
+            })
+        })
+        .collect()
+}
+
+fn single_values(text: &str, re: &Regex, unit: &'static str, range: std::ops::RangeInclusive<f64>) -> Vec<Vital> {
+    let mut cursor = CharCursor::new(text);
+    re.captures_iter(text)
+        .filter_map(|caps| {
+            let whole = caps.get(0)?;
+            let value: f64 = caps.name("v").or(caps.name("v2"))?.as_str().replace(',', ".").parse().ok()?;
+            range.contains(&value).then(|| Vital {
+                value: VitalValue::Single(value),
+                unit,
+                raw: whole.as_str().trim_end().to_string(),
+                start: cursor.char_offset(whole.start()),
+                end: cursor.char_offset(whole.start() + whole.as_str().trim_end().len()),
+            })
+        })
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(text: &str, kind: &str) -> Vec<VitalValue> {
+        extract_vitals_internal(text)
+            .into_iter()
+            .find(|(k, _)| *k == kind)
+            .map(|(_, readings)| readings.into_iter().map(|v| v.value).collect())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn test_triage_note() {
+        let text = "Ingreso 12/08/2025. TA 130/85 mmHg, FC 78 lpm, FR 18 rpm, T 38,5°C, SatO2 97% aa.";
+        assert_eq!(
+            values(text, "blood_pressure"),
+            vec![VitalValue::Pressure { systolic: 130, diastolic: 85 }]
+        );
+        assert_eq!(values(text, "heart_rate"), vec![VitalValue::Single(78.0)]);
+        assert_eq!(values(text, "respiratory_rate"), vec![VitalValue::Single(18.0)]);
+        assert_eq!(values(text, "temperature"), vec![VitalValue::Single(38.5)]);
+        assert_eq!(values(text, "oxygen_saturation"), vec![VitalValue::Single(97.0)]);
+    }
+
+    #[test]
+    fn test_blood_pressure_is_not_a_date() {
+        assert!(values("Control el 12/08, luego el 15/09", "blood_pressure").is_empty());
+        assert!(values("TA 12/08", "blood_pressure").is_empty());
+        assert_eq!(
+            values("Tensión arterial: 145/95; previa 120/80 mmHg", "blood_pressure"),
+            vec![
+                VitalValue::Pressure { systolic: 145, diastolic: 95 },
+                VitalValue::Pressure { systolic: 120, diastolic: 80 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_saturation_ignores_unrelated_percentages() {
+        let text = "Hematocrito 38%, Sat 94 % con máscara, neutrófilos 80%";
+        assert_eq!(values(text, "oxygen_saturation"), vec![VitalValue::Single(94.0)]);
+    }
+
+    #[test]
+    fn test_spans_and_raw_text() {
+        let text = "Niño febril: Temp 39.2 °C y 110 bpm";
+        let vitals = extract_vitals_internal(text);
+        let chars: Vec<char> = text.chars().collect();
+        for (_, readings) in vitals {
+            for vital in readings {
+                let slice: String = chars[vital.start..vital.end].iter().collect();
+                assert_eq!(slice, vital.raw);
+            }
+        }
+        assert_eq!(values(text, "heart_rate"), vec![VitalValue::Single(110.0)]);
+        assert!(values("T 99 grados", "temperature").is_empty());
+        // Labels other than a capital "T" ignore case
+        assert!(values("Ver ítem t 38 del protocolo", "temperature").is_empty());
+        assert_eq!(values("TEMP: 37,8; t.ax 38", "temperature"), vec![VitalValue::Single(37.8)]);
+        assert_eq!(values("T.ax 38 y temperatura 37 °c", "temperature"), vec![VitalValue::Single(38.0), VitalValue::Single(37.0)]);
+    }
+
+    #[test]
+    fn test_blood_pressure_pairs_are_not_dates() {
+        let text = "Control del 15/01: TA 120/80 mmHg. PA: 135/85, luego 110/70 mmHg. Turno 20/02 y 12/08.";
+        assert_eq!(extract_blood_pressure(text).unwrap(), vec![(120, 80), (135, 85), (110, 70)]);
+        assert!(extract_blood_pressure("Ingreso 15/01/2026, alta 20/01").unwrap().is_empty());
+        // Labelled but implausible: a date after the label
+        assert!(extract_blood_pressure("TA 15/01").unwrap().is_empty());
+    }
+}